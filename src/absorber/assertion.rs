@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::time::Instant;
+
+use super::stats_svc::{StatsSummary, StatsSvc};
+use crate::config::absorber::AbsorberConfig;
+
+/// How often the stats are checked against the expectations
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Conditions for stopping the absorber and judging the run, for use as a CI gate
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Expectations {
+    events: Option<u64>,
+    bytes: Option<u64>,
+    idle_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum StopReason {
+    /// The expected counts were reached
+    Expected,
+    /// No events arrived within the idle timeout
+    Idle,
+    /// The overall timeout passed
+    Timeout,
+    /// Stopped by the user or a signal
+    Interrupted,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AssertionResult {
+    pub(crate) passed: bool,
+    pub(crate) stop_reason: StopReason,
+    pub(crate) failures: Vec<String>,
+}
+
+impl From<&AbsorberConfig> for Expectations {
+    fn from(config: &AbsorberConfig) -> Self {
+        Self {
+            events: config.expect_events,
+            bytes: config.expect_bytes,
+            idle_timeout: config.idle_timeout.map(Duration::from_millis),
+            timeout: config.timeout.map(Duration::from_millis),
+        }
+    }
+}
+
+impl Expectations {
+    pub(crate) fn is_active(&self) -> bool {
+        self.events.is_some() || self.bytes.is_some() || self.idle_timeout.is_some() || self.timeout.is_some()
+    }
+
+    fn reached(&self, summary: &StatsSummary) -> bool {
+        let events = self.events.is_none_or(|n| summary.total_events as u64 >= n);
+        let bytes = self.bytes.is_none_or(|n| summary.total_decomp_bytes as u64 >= n);
+        (self.events.is_some() || self.bytes.is_some()) && events && bytes
+    }
+
+    /// Resolves once a stop condition is met. The idle timeout only starts with the first event.
+    /// With one set, reaching the expected counts isn't enough: it keeps waiting for traffic to
+    /// stop, so extra events still fail the run.
+    pub(crate) async fn watch(&self, stats: &StatsSvc) -> StopReason {
+        let start = Instant::now();
+        let mut last_seen = 0;
+        let mut last_change = start;
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            poll.tick().await;
+            let Some(summary) = stats.summary().await else {
+                return StopReason::Interrupted;
+            };
+            let seen = summary.total_events + summary.invalid_events;
+            if seen != last_seen {
+                last_seen = seen;
+                last_change = Instant::now();
+            }
+
+            if self.idle_timeout.is_none() && self.reached(&summary) {
+                return StopReason::Expected;
+            }
+            if last_seen > 0 && self.idle_timeout.is_some_and(|idle| last_change.elapsed() >= idle) {
+                return StopReason::Idle;
+            }
+            if self.timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+                return StopReason::Timeout;
+            }
+        }
+    }
+
+    /// Compare the final stats with the expectations
+    pub(crate) fn check(&self, summary: &StatsSummary, stop_reason: StopReason) -> AssertionResult {
+        let mut failures = vec![];
+        if let Some(expected) = self.events
+            && summary.total_events as u64 != expected
+        {
+            failures.push(format!("expected {expected} events, received {}", summary.total_events));
+        }
+        if let Some(expected) = self.bytes
+            && summary.total_decomp_bytes as u64 != expected
+        {
+            failures.push(format!("expected {expected} bytes, received {}", summary.total_decomp_bytes));
+        }
+        if self.events.is_none() && self.bytes.is_none() && summary.total_events == 0 {
+            failures.push("no events received".to_string());
+        }
+        if summary.invalid_events > 0 {
+            failures.push(format!("{} events failed validation", summary.invalid_events));
+        }
+        AssertionResult {
+            passed: failures.is_empty(),
+            stop_reason,
+            failures,
+        }
+    }
+}
+
+impl std::fmt::Display for AssertionResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.stop_reason {
+            StopReason::Expected => "expected counts reached",
+            StopReason::Idle => "idle timeout",
+            StopReason::Timeout => "timeout",
+            StopReason::Interrupted => "interrupted",
+        };
+        match self.passed {
+            true => write!(f, "Assertion passed ({reason})"),
+            false => write!(f, "Assertion failed ({reason}): {}", self.failures.join(", ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::config::StatsFormat;
+
+    async fn stats_with(events: usize, bytes: usize, invalid: usize) -> StatsSvc {
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
+        stats.increment(events, bytes, bytes).await;
+        for _ in 0..invalid {
+            stats.record_invalid("bad_pri").await;
+        }
+        stats
+    }
+
+    #[tokio::test]
+    async fn passes_when_counts_match_exactly() {
+        let expect = Expectations {
+            events: Some(10),
+            bytes: Some(800),
+            ..Default::default()
+        };
+        let stats = stats_with(10, 800, 0).await;
+
+        let reason = expect.watch(&stats).await;
+        let result = expect.check(&stats.summary().await.unwrap(), reason);
+
+        assert_eq!(reason, StopReason::Expected);
+        assert!(result.passed, "{result}");
+    }
+
+    #[tokio::test]
+    async fn fails_on_wrong_counts_and_validation_failures() {
+        let expect = Expectations {
+            events: Some(10),
+            idle_timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let stats = stats_with(12, 960, 1).await;
+
+        let reason = expect.watch(&stats).await;
+        let result = expect.check(&stats.summary().await.unwrap(), reason);
+
+        assert_eq!(reason, StopReason::Idle);
+        assert_eq!(result.failures, ["expected 10 events, received 12", "1 events failed validation"]);
+    }
+
+    #[tokio::test]
+    async fn times_out_waiting_for_events() {
+        let expect = Expectations {
+            events: Some(10),
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let stats = stats_with(3, 240, 0).await;
+
+        let reason = expect.watch(&stats).await;
+        let result = expect.check(&stats.summary().await.unwrap(), reason);
+
+        assert_eq!(reason, StopReason::Timeout);
+        assert_eq!(result.to_string(), "Assertion failed (timeout): expected 10 events, received 3");
+    }
+
+    #[test]
+    fn is_inactive_by_default() {
+        assert!(!Expectations::from(&AbsorberConfig::default()).is_active());
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write as _},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use jiff::Timestamp;
+use log::{error, info, warn};
+use serde::Serialize;
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::config::absorber::CaptureFormat;
+
+/// Events waiting to be written before new ones are dropped from the capture
+const CAPTURE_QUEUE: usize = 65_536;
+
+struct Captured {
+    listener: Arc<str>,
+    peer: Option<SocketAddr>,
+    received: Timestamp,
+    valid: bool,
+    message: Vec<u8>,
+}
+
+/// Hands received events to a background writer. Capturing never waits on the disk: if the
+/// writer falls behind, events are dropped from the capture (but still counted in stats).
+#[derive(Clone)]
+pub(crate) struct CaptureSvc {
+    tx: mpsc::Sender<Captured>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl CaptureSvc {
+    pub(crate) fn run(dir: &Path, format: CaptureFormat, max_bytes: Option<u64>) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        info!("Capturing received events to {} as {}", dir.display(), format);
+        let (tx, rx) = mpsc::channel(CAPTURE_QUEUE);
+        let writer = CaptureWriter {
+            dir: dir.to_path_buf(),
+            format,
+            max_bytes,
+            files: HashMap::new(),
+        };
+        tokio::task::spawn_blocking(move || writer.run(rx));
+        Ok(Self {
+            tx,
+            dropped: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    pub(crate) fn capture(&self, listener: &Arc<str>, peer: Option<SocketAddr>, message: &[u8], valid: bool) {
+        let captured = Captured {
+            listener: listener.clone(),
+            peer,
+            received: Timestamp::now(),
+            valid,
+            message: message.to_vec(),
+        };
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(captured) {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped == 1 || dropped.is_multiple_of(100_000) {
+                warn!("Capture writer can't keep up, {dropped} events dropped from the capture so far");
+            }
+        }
+    }
+}
+
+struct CaptureWriter {
+    dir: PathBuf,
+    format: CaptureFormat,
+    max_bytes: Option<u64>,
+    /// Open files, by file stem
+    files: HashMap<String, CaptureFile>,
+}
+
+impl CaptureWriter {
+    fn run(mut self, mut rx: mpsc::Receiver<Captured>) {
+        while let Some(captured) = rx.blocking_recv() {
+            let mut result = self.write(&captured);
+            // Write whatever else is queued, then flush so the files stay current when traffic stops
+            while result.is_ok()
+                && let Ok(captured) = rx.try_recv()
+            {
+                result = self.write(&captured);
+            }
+            if let Err(e) = result.and_then(|_| self.flush()) {
+                error!("Failed to write capture to {}, capture stopped: {e}", self.dir.display());
+                return;
+            }
+        }
+    }
+
+    fn write(&mut self, captured: &Captured) -> std::io::Result<()> {
+        let mut line = Vec::with_capacity(captured.message.len() + 128);
+        let stem = match self.format {
+            CaptureFormat::Raw => {
+                line.extend_from_slice(&captured.message);
+                if !line.ends_with(b"\n") {
+                    line.push(b'\n');
+                }
+                file_stem(&captured.listener)
+            }
+            CaptureFormat::Ndjson => {
+                let record = CaptureRecord {
+                    listener: &captured.listener,
+                    peer: captured.peer,
+                    received: captured.received.to_string(),
+                    valid: captured.valid,
+                    event: String::from_utf8_lossy(captured.message.trim_ascii_end()),
+                };
+                serde_json::to_writer(&mut line, &record)?;
+                line.push(b'\n');
+                "capture".to_string()
+            }
+        };
+
+        let file = match self.files.get_mut(&stem) {
+            Some(file) => file,
+            None => {
+                let file = CaptureFile::create(&self.dir, stem.clone(), self.format.extension())?;
+                self.files.entry(stem).or_insert(file)
+            }
+        };
+        if let Some(max_bytes) = self.max_bytes
+            && file.written > 0
+            && file.written + line.len() as u64 > max_bytes
+        {
+            file.rotate(&self.dir)?;
+        }
+        file.write(&line)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.files.values_mut().try_for_each(|file| file.writer.flush())
+    }
+}
+
+#[derive(Serialize)]
+struct CaptureRecord<'a> {
+    listener: &'a str,
+    peer: Option<SocketAddr>,
+    received: String,
+    valid: bool,
+    event: Cow<'a, str>,
+}
+
+/// `tcp://127.0.0.1:9514` becomes `tcp_127.0.0.1_9514`
+fn file_stem(listener: &str) -> String {
+    listener
+        .replace("://", "_")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// A capture file that moves on to `<stem>.1.<ext>`, `<stem>.2.<ext>`, ... once it's full
+struct CaptureFile {
+    stem: String,
+    extension: &'static str,
+    index: u32,
+    written: u64,
+    writer: BufWriter<File>,
+}
+
+impl CaptureFile {
+    fn create(dir: &Path, stem: String, extension: &'static str) -> std::io::Result<Self> {
+        let path = Self::path(dir, &stem, extension, 0);
+        info!("Writing capture file {}", path.display());
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            stem,
+            extension,
+            index: 0,
+            written: 0,
+        })
+    }
+
+    fn path(dir: &Path, stem: &str, extension: &str, index: u32) -> PathBuf {
+        match index {
+            0 => dir.join(format!("{stem}.{extension}")),
+            n => dir.join(format!("{stem}.{n}.{extension}")),
+        }
+    }
+
+    fn rotate(&mut self, dir: &Path) -> std::io::Result<()> {
+        self.writer.flush()?;
+        self.index += 1;
+        let path = Self::path(dir, &self.stem, self.extension, self.index);
+        info!("Rotating capture to {}", path.display());
+        self.writer = BufWriter::new(File::create(path)?);
+        self.written = 0;
+        Ok(())
+    }
+
+    fn write(&mut self, line: &[u8]) -> std::io::Result<()> {
+        self.writer.write_all(line)?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use sealed_test::prelude::*;
+
+    use super::*;
+
+    fn captured(listener: &str, message: &[u8]) -> Captured {
+        Captured {
+            listener: listener.into(),
+            peer: Some("10.1.2.3:5555".parse().unwrap()),
+            received: Timestamp::UNIX_EPOCH,
+            valid: true,
+            message: message.to_vec(),
+        }
+    }
+
+    fn writer(dir: &Path, format: CaptureFormat, max_bytes: Option<u64>) -> CaptureWriter {
+        CaptureWriter {
+            dir: dir.to_path_buf(),
+            format,
+            max_bytes,
+            files: HashMap::new(),
+        }
+    }
+
+    #[sealed_test]
+    fn writes_raw_events_per_listener() {
+        let dir = Path::new(".");
+        let mut writer = writer(dir, CaptureFormat::Raw, None);
+        writer.write(&captured("tcp://127.0.0.1:9514", b"one\n")).unwrap();
+        writer.write(&captured("udp://[::1]:9514", b"two")).unwrap();
+        writer.write(&captured("tcp://127.0.0.1:9514", b"three\n")).unwrap();
+        writer.flush().unwrap();
+
+        let tcp = std::fs::read_to_string(dir.join("tcp_127.0.0.1_9514.log")).unwrap();
+        let udp = std::fs::read_to_string(dir.join("udp____1__9514.log")).unwrap();
+        assert_eq!(tcp, "one\nthree\n");
+        assert_eq!(udp, "two\n");
+    }
+
+    #[sealed_test]
+    fn writes_ndjson_with_metadata() {
+        let dir = Path::new(".");
+        let mut writer = writer(dir, CaptureFormat::Ndjson, None);
+        writer.write(&captured("http://0.0.0.0:8080", b"{\"msg\":\"hi\"}\n")).unwrap();
+        writer.flush().unwrap();
+
+        let capture = std::fs::read_to_string(dir.join("capture.ndjson")).unwrap();
+        let record: serde_json::Value = serde_json::from_str(&capture).unwrap();
+        assert_eq!(
+            record,
+            serde_json::json!({
+                "listener": "http://0.0.0.0:8080",
+                "peer": "10.1.2.3:5555",
+                "received": "1970-01-01T00:00:00Z",
+                "valid": true,
+                "event": "{\"msg\":\"hi\"}",
+            })
+        );
+    }
+
+    #[sealed_test]
+    fn rotates_when_files_fill_up() {
+        let dir = Path::new(".");
+        let mut writer = writer(dir, CaptureFormat::Raw, Some(10));
+        for event in ["aaaa\n", "bbbb\n", "cccc\n", "dddddddddddddddd\n"] {
+            writer.write(&captured("tcp://h:1", event.as_bytes())).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("tcp_h_1.log"), "aaaa\nbbbb\n");
+        assert_eq!(read("tcp_h_1.1.log"), "cccc\n");
+        // An event larger than the limit still gets written, alone in its file
+        assert_eq!(read("tcp_h_1.2.log"), "dddddddddddddddd\n");
+    }
+}
@@ -1,15 +1,21 @@
 use std::{
     io::{Cursor, Read as _},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::Arc,
     time::{Duration, SystemTime},
 };
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use flate2::bufread::GzDecoder;
 use log::{debug, trace};
 use rcgen::{BasicConstraints, CertificateParams, CertifiedKey, ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose};
 use reqwest::Client;
-use rustls::pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject as _};
+use rustls::{
+    RootCertStore, ServerConfig,
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject as _},
+    server::WebPkiClientVerifier,
+};
 use tar::Archive;
 
 use super::CertType;
@@ -17,26 +23,46 @@ use super::CertType;
 pub(super) struct CertKey {
     key_pem: String,
     cert_pem: String,
+    /// CA certs that mTLS client certificates must chain to
     root_cert_pem: Option<String>,
 }
 
 impl CertKey {
-    pub fn cert(&self) -> Vec<CertificateDer<'static>> {
-        CertificateDer::pem_slice_iter(self.cert_pem.as_bytes())
-            .collect::<Result<Vec<_>, _>>()
-            .expect("Failed to parse certificate PEM data")
+    pub fn cert(&self) -> Result<Vec<CertificateDer<'static>>> {
+        parse_pem_certs(&self.cert_pem).context("Failed to parse certificate PEM data")
+    }
+
+    pub fn key(&self) -> Result<PrivateKeyDer<'static>> {
+        PrivateKeyDer::from_pem_slice(self.key_pem.as_bytes()).context("Failed to parse key PEM data")
     }
 
-    pub fn key(&self) -> PrivateKeyDer<'static> {
-        PrivateKeyDer::from_pem_slice(self.key_pem.as_bytes()).expect("Failed to parse key PEM data")
+    pub fn root_cert(&self) -> Result<Option<Vec<CertificateDer<'static>>>> {
+        self.root_cert_pem
+            .as_deref()
+            .map(|pem| parse_pem_certs(pem).context("Failed to parse root certificate PEM data"))
+            .transpose()
     }
 
-    pub fn root_cert(&self) -> Option<Vec<CertificateDer<'static>>> {
-        self.root_cert_pem.as_ref().map(|pem| {
-            CertificateDer::pem_slice_iter(pem.as_bytes())
-                .collect::<Result<Vec<_>, _>>()
-                .expect("Failed to parse root certificate PEM data")
-        })
+    /// Build a TLS server config for this cert, verifying client certs against the root certs with mTLS
+    pub fn server_config(&self, mtls: bool) -> Result<ServerConfig> {
+        let builder = ServerConfig::builder();
+        let builder = match (mtls, self.root_cert()?) {
+            (true, Some(roots)) => {
+                let mut store = RootCertStore::empty();
+                for root in roots {
+                    store.add(root).context("Invalid root cert")?;
+                }
+                let verifier = WebPkiClientVerifier::builder(Arc::new(store))
+                    .build()
+                    .context("Failed to build client verifier")?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            (true, None) => anyhow::bail!("mTLS enabled but no root cert available"),
+            (false, _) => builder.with_no_client_auth(),
+        };
+        builder
+            .with_single_cert(self.cert()?, self.key()?)
+            .context("Server cert does not match its key")
     }
 }
 
@@ -50,23 +76,79 @@ impl From<CertifiedKey> for CertKey {
     }
 }
 
-pub(super) async fn get_cert(cert_type: &CertType, mtls: bool) -> Result<Option<CertKey>> {
-    match cert_type {
-        CertType::None => Ok(None),
+pub(super) async fn get_cert(cert_type: &CertType, mtls: bool, client_ca: Option<&Path>) -> Result<Option<CertKey>> {
+    let client_ca = match (mtls, client_ca) {
+        (true, Some(path)) => Some(read_pem_certs(path, "client CA")?),
+        _ => None,
+    };
+    let mut cert_key = match cert_type {
+        CertType::None => return Ok(None),
         CertType::SelfSigned => {
-            if mtls {
-                anyhow::bail!("mTLS requires --private-ca, it cannot be used with --self-signed");
+            if mtls && client_ca.is_none() {
+                anyhow::bail!("mTLS requires --private-ca or --client-ca, it cannot be used with --self-signed alone");
             }
-            Ok(Some(gen_self_signed()?))
+            gen_self_signed()?
         }
         CertType::PublicCA => {
-            if mtls {
-                anyhow::bail!("mTLS requires --private-ca, it cannot be used with public certs");
+            if mtls && client_ca.is_none() {
+                anyhow::bail!("mTLS requires --private-ca or --client-ca, it cannot be used with public certs alone");
+            }
+            pull_public_certs().await?
+        }
+        CertType::PrivateCA => gen_private_ca(mtls)?,
+        CertType::Files { cert, key } => {
+            if mtls && client_ca.is_none() {
+                anyhow::bail!("mTLS with --cert requires --client-ca");
             }
-            Ok(Some(pull_public_certs().await?))
+            load_cert_files(cert, key)?
         }
-        CertType::PrivateCA => Ok(Some(gen_private_ca(mtls)?)),
+    };
+    // Trust the given client CA alongside a generated one
+    if let Some(client_ca) = client_ca {
+        cert_key.root_cert_pem = Some(cert_key.root_cert_pem.take().unwrap_or_default() + &client_ca);
+    }
+    Ok(Some(cert_key))
+}
+
+fn parse_pem_certs(pem: &str) -> Result<Vec<CertificateDer<'static>>> {
+    Ok(CertificateDer::pem_slice_iter(pem.as_bytes()).collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Read a PEM file, checking that it holds at least one well-formed certificate
+fn read_pem_certs(path: &Path, what: &str) -> Result<String> {
+    let pem = std::fs::read_to_string(path).with_context(|| format!("Failed to read {what} file {}", path.display()))?;
+    let certs = parse_pem_certs(&pem).with_context(|| format!("Invalid {what} file {}", path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates in {what} file {}", path.display());
+    }
+    // The PEM framing can hold anything, so check the DER inside parses as a certificate
+    let mut store = RootCertStore::empty();
+    for cert in certs {
+        store
+            .add(cert)
+            .with_context(|| format!("Invalid certificate in {what} file {}", path.display()))?;
     }
+    Ok(pem)
+}
+
+fn load_cert_files(cert: &Path, key: &Path) -> Result<CertKey> {
+    let cert_pem = read_pem_certs(cert, "cert")?;
+    let key_pem = std::fs::read_to_string(key).with_context(|| format!("Failed to read key file {}", key.display()))?;
+    let cert_key = CertKey {
+        key_pem,
+        cert_pem,
+        root_cert_pem: None,
+    };
+    let key_der = cert_key
+        .key()
+        .with_context(|| format!("No private key in key file {}", key.display()))?;
+    let provider = CryptoProvider::get_default()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(rustls::crypto::ring::default_provider()));
+    rustls::sign::CertifiedKey::from_der(cert_key.cert()?, key_der, &provider)
+        .with_context(|| format!("Cert file {} does not match key file {}", cert.display(), key.display()))?;
+    debug!("Loaded server cert from {}", cert.display());
+    Ok(cert_key)
 }
 
 fn generate_cert(ca: Option<&CertifiedKey>, is_ca: bool, is_client: bool) -> Result<CertifiedKey> {
@@ -208,3 +290,56 @@ fn save_and_print_certs(cert_key: &CertifiedKey, ca_cert: Option<&CertifiedKey>,
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn checks_cert_files() {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let dir = std::env::temp_dir().join("protoglot-test-certs");
+        std::fs::create_dir_all(&dir).unwrap();
+        let cert_key = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let (cert, key, garbage) = (dir.join("cert.pem"), dir.join("key.pem"), dir.join("garbage.pem"));
+        std::fs::write(&cert, cert_key.cert.pem()).unwrap();
+        std::fs::write(&key, cert_key.key_pair.serialize_pem()).unwrap();
+        std::fs::write(&garbage, "not a pem").unwrap();
+        // A well-formed PEM envelope around DER that isn't a certificate
+        let bad_der = dir.join("bad_der.pem");
+        std::fs::write(
+            &bad_der,
+            "-----BEGIN CERTIFICATE-----\nbm90IGEgY2VydGlmaWNhdGU=\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+        let other_key = dir.join("other_key.pem");
+        std::fs::write(&other_key, KeyPair::generate().unwrap().serialize_pem()).unwrap();
+        let files = |cert: &Path, key: &Path| CertType::Files {
+            cert: cert.to_path_buf(),
+            key: key.to_path_buf(),
+        };
+
+        let loaded = get_cert(&files(&cert, &key), false, None).await.unwrap().unwrap();
+        assert_eq!(loaded.cert().unwrap().len(), 1);
+        assert!(loaded.root_cert().unwrap().is_none());
+        assert!(loaded.server_config(false).is_ok());
+        assert!(loaded.server_config(true).is_err());
+        let mtls = get_cert(&files(&cert, &key), true, Some(&cert)).await.unwrap().unwrap();
+        assert_eq!(mtls.root_cert().unwrap().unwrap().len(), 1);
+        assert!(mtls.server_config(true).is_ok());
+
+        for (cert_type, mtls, client_ca) in [
+            (files(&cert, &key), true, None),
+            (files(&garbage, &key), false, None),
+            (files(&cert, &cert), false, None),
+            (files(&cert, &dir.join("missing.pem")), false, None),
+            (files(&cert, &key), true, Some(garbage.as_path())),
+            (files(&bad_der, &key), false, None),
+            (files(&cert, &other_key), false, None),
+            (files(&cert, &key), true, Some(bad_der.as_path())),
+        ] {
+            assert!(get_cert(&cert_type, mtls, client_ca).await.is_err(), "{cert_type:?}");
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
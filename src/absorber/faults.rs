@@ -0,0 +1,410 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use http_body_util::BodyExt;
+use hyper::{Request, Response, StatusCode, body::Body, header::RETRY_AFTER};
+use log::debug;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::{Instant, Sleep},
+};
+
+use crate::config::absorber::AbsorberConfig;
+
+/// Failures the HTTP absorber injects on purpose, to check that senders retry, back off and buffer
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct HttpFaults {
+    /// Percentages of requests answered with 503, 429, or by closing the connection
+    unavailable: f64,
+    throttled: f64,
+    disconnect: f64,
+    /// Seconds sent in `Retry-After` with 503 and 429 responses
+    retry_after: u64,
+    delay: Option<Duration>,
+    max_body_bytes: Option<u64>,
+}
+
+/// The error a request body stream ends with once it runs past `--max-body-bytes`
+#[derive(Debug)]
+pub(crate) struct BodyTooLarge;
+
+impl std::fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Payload too large")
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Fault {
+    Unavailable,
+    Throttled,
+    Disconnect,
+    TooLarge,
+}
+
+impl From<&AbsorberConfig> for HttpFaults {
+    fn from(config: &AbsorberConfig) -> Self {
+        Self {
+            unavailable: config.fail_503.unwrap_or_default(),
+            throttled: config.fail_429.unwrap_or_default(),
+            disconnect: config.fail_disconnect.unwrap_or_default(),
+            retry_after: config.retry_after,
+            delay: config.response_delay.map(Duration::from_millis),
+            max_body_bytes: config.max_body_bytes,
+        }
+    }
+}
+
+impl HttpFaults {
+    /// Largest request body to read before answering 413
+    pub(crate) fn max_body_bytes(&self) -> Option<u64> {
+        self.max_body_bytes
+    }
+
+    /// The fault to inject for a request, if any. Bodies that declare an oversized length are turned
+    /// away here; chunked and HTTP/2 bodies are cut off by `max_body_bytes` as they are read.
+    pub(crate) fn pick<B: Body>(&self, req: &Request<B>) -> Option<Fault> {
+        if let Some(max) = self.max_body_bytes
+            && req.body().size_hint().lower() > max
+        {
+            return Some(Fault::TooLarge);
+        }
+        if self.unavailable + self.throttled + self.disconnect <= 0.0 {
+            return None;
+        }
+        let roll = fastrand::f64() * 100.0;
+        if roll < self.unavailable {
+            Some(Fault::Unavailable)
+        } else if roll < self.unavailable + self.throttled {
+            Some(Fault::Throttled)
+        } else if roll < self.unavailable + self.throttled + self.disconnect {
+            Some(Fault::Disconnect)
+        } else {
+            None
+        }
+    }
+
+    /// Answer a request with the given fault. An error closes the connection, or resets the stream
+    /// on HTTP/2, after reading the start of the body so the client is cut off mid-request.
+    pub(crate) async fn inject(&self, fault: Fault, req: Request<hyper::body::Incoming>) -> anyhow::Result<Response<String>> {
+        debug!("Injecting {fault:?} for {} {}", req.method(), req.uri());
+        let (status, body) = match fault {
+            Fault::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "Service unavailable"),
+            Fault::Throttled => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            Fault::TooLarge => return Ok(too_large()),
+            Fault::Disconnect => {
+                let _ = req.into_body().frame().await;
+                anyhow::bail!("Closing connection on purpose");
+            }
+        };
+        let response = Response::builder().status(status).header(RETRY_AFTER, self.retry_after);
+        Ok(response.body(body.to_string()).unwrap())
+    }
+
+    /// Hold the response back by the configured latency
+    pub(crate) async fn delay(&self) {
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// The answer to a body over `--max-body-bytes`
+pub(crate) fn too_large() -> Response<String> {
+    Response::builder()
+        .status(StatusCode::PAYLOAD_TOO_LARGE)
+        .body(BodyTooLarge.to_string())
+        .unwrap()
+}
+
+/// Backpressure and resets the TCP absorber applies to its connections
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct TcpFaults {
+    /// Bytes per second to read from each connection
+    read_rate: Option<u64>,
+    /// Stop reading for `.1` after every `.0` of reading
+    pause: Option<(Duration, Duration)>,
+    reset_after: Option<Duration>,
+    reset_after_bytes: Option<u64>,
+    pub(crate) max_connections: Option<usize>,
+}
+
+impl From<&AbsorberConfig> for TcpFaults {
+    fn from(config: &AbsorberConfig) -> Self {
+        let pause = config
+            .pause_every
+            .zip(config.pause_for)
+            .map(|(every, pause)| (Duration::from_millis(every), Duration::from_millis(pause)));
+        Self {
+            read_rate: config.read_rate.filter(|&rate| rate > 0),
+            pause,
+            reset_after: config.reset_after.map(Duration::from_millis),
+            reset_after_bytes: config.reset_after_bytes,
+            max_connections: config.max_connections,
+        }
+    }
+}
+
+impl TcpFaults {
+    pub(crate) fn wrap<S>(&self, inner: S) -> FaultyStream<S> {
+        let now = Instant::now();
+        FaultyStream {
+            inner,
+            faults: self.clone(),
+            read: 0,
+            window_start: now,
+            window_read: 0,
+            next_pause: self.pause.map(|(every, _)| now + every),
+            reset_at: self.reset_after.map(|after| Box::pin(tokio::time::sleep(after))),
+            wait: None,
+        }
+    }
+}
+
+/// A connection that reads slowly, pauses, or fails with `ConnectionReset` as configured. Writes
+/// pass straight through, so it can sit under TLS.
+pub(crate) struct FaultyStream<S> {
+    inner: S,
+    faults: TcpFaults,
+    /// Bytes read over the whole connection
+    read: u64,
+    /// The read rate is measured from here, so it doesn't catch up after a pause
+    window_start: Instant,
+    window_read: u64,
+    next_pause: Option<Instant>,
+    reset_at: Option<Pin<Box<Sleep>>>,
+    wait: Option<Pin<Box<Sleep>>>,
+}
+
+/// Sockets that can be made to send a RST rather than a FIN when dropped
+pub(crate) trait Reset {
+    fn prepare_reset(&self);
+}
+
+impl Reset for tokio::net::TcpStream {
+    fn prepare_reset(&self) {
+        if let Err(e) = self.set_zero_linger() {
+            debug!("Failed to set SO_LINGER, the connection will close normally: {e}");
+        }
+    }
+}
+
+/// The error reading fails with when a connection is reset on purpose
+#[derive(Debug)]
+pub(crate) struct InjectedReset(String);
+
+impl std::fmt::Display for InjectedReset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Resetting connection {}", self.0)
+    }
+}
+
+impl std::error::Error for InjectedReset {}
+
+impl InjectedReset {
+    pub(crate) fn is(err: &io::Error) -> bool {
+        err.get_ref().is_some_and(|inner| inner.is::<Self>())
+    }
+}
+
+impl<S: Reset> FaultyStream<S> {
+    fn reset(&self, why: String) -> io::Error {
+        self.inner.prepare_reset();
+        io::Error::new(io::ErrorKind::ConnectionReset, InjectedReset(why))
+    }
+}
+
+impl<S> FaultyStream<S> {
+    /// How much may be read now, or when to try again
+    fn allowance(&mut self, now: Instant) -> Result<u64, Instant> {
+        if let Some(next_pause) = self.next_pause
+            && now >= next_pause
+            && let Some((every, pause)) = self.faults.pause
+        {
+            let resume = now + pause;
+            debug!("Pausing reads for {pause:?}");
+            self.next_pause = Some(resume + every);
+            self.window_start = resume;
+            self.window_read = 0;
+            return Err(resume);
+        }
+        let Some(rate) = self.faults.read_rate else {
+            return Ok(u64::MAX);
+        };
+        // Read in slices of a tenth of a second, so throttled reads stay smooth
+        let slice = (rate / 10).max(1);
+        let due = self.window_start + Duration::from_secs_f64(self.window_read as f64 / rate as f64);
+        match due > now {
+            true => Err(due),
+            false => Ok(slice),
+        }
+    }
+}
+
+impl<S: AsyncRead + Reset + Unpin> AsyncRead for FaultyStream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        if let Some(reset_at) = &mut this.reset_at
+            && reset_at.as_mut().poll(cx).is_ready()
+        {
+            return Poll::Ready(Err(this.reset(format!("after {:?}", this.faults.reset_after.unwrap_or_default()))));
+        }
+        if let Some(limit) = this.faults.reset_after_bytes
+            && this.read >= limit
+        {
+            return Poll::Ready(Err(this.reset(format!("after {limit} bytes"))));
+        }
+        loop {
+            if let Some(wait) = &mut this.wait {
+                ready!(wait.as_mut().poll(cx));
+                this.wait = None;
+            }
+            match this.allowance(Instant::now()) {
+                Ok(allowed) => {
+                    let remaining = this.faults.reset_after_bytes.map_or(u64::MAX, |limit| limit - this.read);
+                    let len = allowed.min(remaining).min(buf.remaining() as u64) as usize;
+                    let mut limited = ReadBuf::new(buf.initialize_unfilled_to(len));
+                    ready!(Pin::new(&mut this.inner).poll_read(cx, &mut limited))?;
+                    let n = limited.filled().len();
+                    buf.advance(n);
+                    this.read += n as u64;
+                    this.window_read += n as u64;
+                    return Poll::Ready(Ok(()));
+                }
+                Err(until) => this.wait = Some(Box::pin(tokio::time::sleep_until(until))),
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for FaultyStream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::Full;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn request(len: usize) -> Request<Full<bytes::Bytes>> {
+        Request::new(Full::new(vec![b'x'; len].into()))
+    }
+
+    #[test]
+    fn rejects_bodies_over_the_limit() {
+        let faults = HttpFaults {
+            max_body_bytes: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(faults.pick(&request(10)), None);
+        assert_eq!(faults.pick(&request(11)), Some(Fault::TooLarge));
+    }
+
+    #[test]
+    fn splits_requests_between_faults() {
+        fastrand::seed(7);
+        let faults = HttpFaults {
+            unavailable: 20.0,
+            throttled: 30.0,
+            ..Default::default()
+        };
+        let picks: Vec<_> = (0..10_000).map(|_| faults.pick(&request(1))).collect();
+        let share = |fault| picks.iter().filter(|pick| pick.as_ref() == fault).count() as f64 / 100.0;
+        assert!((share(Some(&Fault::Unavailable)) - 20.0).abs() < 2.0);
+        assert!((share(Some(&Fault::Throttled)) - 30.0).abs() < 2.0);
+        assert!((share(None) - 50.0).abs() < 2.0);
+    }
+
+    impl Reset for &[u8] {
+        fn prepare_reset(&self) {}
+    }
+
+    async fn read_all(faults: TcpFaults, len: usize) -> (io::Result<usize>, Duration) {
+        use tokio::io::AsyncReadExt as _;
+
+        let start = Instant::now();
+        let data = vec![b'x'; len];
+        let mut stream = faults.wrap(&data[..]);
+        let mut buf = Vec::new();
+        let result = stream.read_to_end(&mut buf).await;
+        (result, start.elapsed())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttles_reads() {
+        let faults = TcpFaults {
+            read_rate: Some(1000),
+            ..Default::default()
+        };
+        let (read, elapsed) = read_all(faults, 3000).await;
+        assert_eq!(read.unwrap(), 3000);
+        assert!(
+            elapsed >= Duration::from_millis(2900) && elapsed < Duration::from_millis(3100),
+            "{elapsed:?}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pauses_between_reads() {
+        let faults = TcpFaults {
+            read_rate: Some(1000),
+            pause: Some((Duration::from_secs(1), Duration::from_secs(5))),
+            ..Default::default()
+        };
+        let (read, elapsed) = read_all(faults, 2000).await;
+        assert_eq!(read.unwrap(), 2000);
+        assert!(
+            elapsed >= Duration::from_millis(6900) && elapsed < Duration::from_millis(7100),
+            "{elapsed:?}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resets_after_a_byte_count() {
+        let faults = TcpFaults {
+            reset_after_bytes: Some(100),
+            ..Default::default()
+        };
+        let (read, _) = read_all(faults, 1000).await;
+        assert!(InjectedReset::is(&read.unwrap_err()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resets_after_a_time() {
+        let faults = TcpFaults {
+            read_rate: Some(100),
+            reset_after: Some(Duration::from_secs(2)),
+            ..Default::default()
+        };
+        let (read, elapsed) = read_all(faults, 1000).await;
+        assert_eq!(read.unwrap_err().kind(), io::ErrorKind::ConnectionReset);
+        assert!(
+            elapsed >= Duration::from_secs(2) && elapsed < Duration::from_millis(2200),
+            "{elapsed:?}"
+        );
+    }
+
+    #[test]
+    fn never_fails_by_default() {
+        let faults = HttpFaults::from(&AbsorberConfig::default());
+        assert!((0..1000).all(|_| faults.pick(&request(1_000_000)).is_none()));
+    }
+}
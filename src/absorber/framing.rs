@@ -0,0 +1,249 @@
+use super::extract_message;
+use crate::config::MessageType;
+
+/// Largest MSG-LEN worth buffering for; anything bigger is taken as a corrupt header
+const MAX_MSG_LEN: usize = 1024 * 1024;
+
+/// How messages are delimited on a single stream connection, per RFC 6587
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum Framing {
+    /// Not yet known; decided from the first bytes of the stream
+    Auto,
+    /// `MSG-LEN SP SYSLOG-MSG`, where MSG-LEN is the octet count of SYSLOG-MSG
+    OctetCounting,
+    /// Messages terminated by a trailing LF
+    NonTransparent,
+}
+
+pub(super) struct Frame {
+    pub(super) message: Vec<u8>,
+    /// Bytes the frame occupied in the stream, including any framing overhead
+    pub(super) frame_len: usize,
+}
+
+pub(super) struct Framer {
+    framing: Framing,
+}
+
+impl Framer {
+    pub(super) fn new(message_type: &MessageType) -> Self {
+        // Octet counting is a syslog transport convention, so only syslog streams need detection
+        let framing = match message_type {
+            MessageType::Syslog3164 | MessageType::Syslog5424 | MessageType::Syslog5424Octet => Framing::Auto,
+            MessageType::NdJson | MessageType::SplunkHec | MessageType::Template => Framing::NonTransparent,
+        };
+        Self { framing }
+    }
+
+    #[cfg(test)]
+    pub(super) fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Pull the next complete frame out of `buf`. With `fin` set, whatever remains is returned
+    /// as a final (possibly malformed) message.
+    pub(super) fn next_frame(&mut self, buf: &mut Vec<u8>, fin: bool) -> Option<Frame> {
+        if self.framing == Framing::Auto {
+            self.framing = detect_framing(buf, fin)?;
+        }
+        match self.framing {
+            Framing::OctetCounting => extract_octet_counted(buf, fin),
+            _ => extract_message(buf, fin).map(|message| Frame {
+                frame_len: message.len(),
+                message,
+            }),
+        }
+    }
+}
+
+fn detect_framing(buf: &[u8], fin: bool) -> Option<Framing> {
+    let start = buf.iter().position(|b| !b.is_ascii_whitespace())?;
+    let rest = &buf[start..];
+    if !matches!(rest[0], b'1'..=b'9') {
+        return Some(Framing::NonTransparent);
+    }
+    match rest.iter().position(|b| !b.is_ascii_digit()) {
+        Some(pos) if rest[pos] == b' ' => Some(Framing::OctetCounting),
+        Some(_) => Some(Framing::NonTransparent),
+        None if fin => Some(Framing::NonTransparent),
+        None => None,
+    }
+}
+
+fn extract_octet_counted(buf: &mut Vec<u8>, fin: bool) -> Option<Frame> {
+    // Senders commonly put a trailer between frames even though RFC 6587 doesn't call for one
+    let skip = buf.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(buf.len());
+    let header_len = buf[skip..].iter().position(|b| !b.is_ascii_digit());
+
+    let msg_len = match header_len {
+        Some(digits) if digits > 0 && buf[skip + digits] == b' ' => std::str::from_utf8(&buf[skip..skip + digits])
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok()),
+        Some(_) => None,
+        None if !fin => return None,
+        None => None,
+    };
+
+    let Some(msg_len) = msg_len else {
+        // Not a valid MSG-LEN header, so hand back the rest of the line to be rejected by validation
+        let message = extract_message(buf, fin)?;
+        return Some(Frame {
+            frame_len: message.len(),
+            message,
+        });
+    };
+
+    let payload_start = skip + header_len.unwrap_or_default() + 1;
+    if msg_len > MAX_MSG_LEN {
+        // Hand back just the header to be rejected, rather than waiting on a payload that won't come
+        let message = buf[skip..payload_start].to_vec();
+        buf.drain(..payload_start);
+        return Some(Frame {
+            message,
+            frame_len: payload_start,
+        });
+    }
+    let frame_end = payload_start + msg_len;
+    if buf.len() < frame_end {
+        if fin && buf.len() > skip {
+            let frame_len = buf.len();
+            let message = buf.split_off(payload_start);
+            buf.clear();
+            return Some(Frame { message, frame_len });
+        }
+        return None;
+    }
+
+    let message = buf[payload_start..frame_end].to_vec();
+    buf.drain(..frame_end);
+    Some(Frame {
+        message,
+        frame_len: frame_end,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::generators::{EventGenerator, Syslog5424EventGenerator};
+
+    fn drain_all(framer: &mut Framer, buf: &mut Vec<u8>) -> Vec<Vec<u8>> {
+        let mut messages = Vec::new();
+        while let Some(frame) = framer.next_frame(buf, false) {
+            messages.push(frame.message);
+        }
+        while let Some(frame) = framer.next_frame(buf, true) {
+            messages.push(frame.message);
+        }
+        messages
+    }
+
+    #[test]
+    fn detects_octet_counting() {
+        let mut framer = Framer::new(&MessageType::Syslog5424);
+        let mut buf = b"11 <13>1 - - - 4 <1>1".to_vec();
+
+        let messages = drain_all(&mut framer, &mut buf);
+
+        assert_eq!(framer.framing(), Framing::OctetCounting);
+        assert_eq!(messages, vec![b"<13>1 - - -".to_vec(), b"<1>1".to_vec()]);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn detects_non_transparent() {
+        let mut framer = Framer::new(&MessageType::Syslog5424);
+        let mut buf = b"<13>1 - - -\n<1>1 a 5 b\n".to_vec();
+
+        let messages = drain_all(&mut framer, &mut buf);
+
+        assert_eq!(framer.framing(), Framing::NonTransparent);
+        assert_eq!(messages, vec![b"<13>1 - - -\n".to_vec(), b"<1>1 a 5 b\n".to_vec()]);
+    }
+
+    #[test]
+    fn waits_for_complete_header_and_payload() {
+        let mut framer = Framer::new(&MessageType::Syslog5424Octet);
+        let mut buf = b"1".to_vec();
+        assert!(framer.next_frame(&mut buf, false).is_none());
+        assert_eq!(framer.framing(), Framing::Auto);
+
+        buf.extend_from_slice(b"1 <13>1 -");
+        assert!(framer.next_frame(&mut buf, false).is_none());
+        assert_eq!(framer.framing(), Framing::OctetCounting);
+
+        buf.extend_from_slice(b" - -");
+        let frame = framer.next_frame(&mut buf, false).unwrap();
+        assert_eq!(frame.message, b"<13>1 - - -");
+        assert_eq!(frame.frame_len, 14);
+    }
+
+    #[test]
+    fn payload_may_contain_newlines() {
+        let mut framer = Framer::new(&MessageType::Syslog5424);
+        let mut buf = b"8 <1>1 a\nb3 <2>".to_vec();
+
+        let messages = drain_all(&mut framer, &mut buf);
+
+        assert_eq!(messages, vec![b"<1>1 a\nb".to_vec(), b"<2>".to_vec()]);
+    }
+
+    #[test]
+    fn tolerates_trailers_between_frames() {
+        let mut framer = Framer::new(&MessageType::Syslog5424);
+        let mut buf = b"4 <1>1\n4 <2>1\n".to_vec();
+
+        let messages = drain_all(&mut framer, &mut buf);
+
+        assert_eq!(messages, vec![b"<1>1".to_vec(), b"<2>1".to_vec()]);
+    }
+
+    #[test]
+    fn rejects_oversized_msg_len_instead_of_buffering() {
+        let mut framer = Framer::new(&MessageType::Syslog5424Octet);
+        let mut buf = b"999999999999 <13>1 - - -\n4 <1>1".to_vec();
+
+        let frame = framer.next_frame(&mut buf, false).unwrap();
+        assert_eq!(frame.message, b"999999999999 ");
+        assert_eq!(frame.frame_len, 13);
+
+        let messages = drain_all(&mut framer, &mut buf);
+        assert_eq!(messages, vec![b"<13>1 - - -\n".to_vec(), b"<1>1".to_vec()]);
+    }
+
+    #[test]
+    fn never_detects_octet_counting_for_json() {
+        let mut framer = Framer::new(&MessageType::NdJson);
+        let mut buf = b"42 \n".to_vec();
+
+        let messages = drain_all(&mut framer, &mut buf);
+
+        assert_eq!(framer.framing(), Framing::NonTransparent);
+        assert_eq!(messages, vec![b"42 \n".to_vec()]);
+    }
+
+    #[test]
+    fn round_trips_generated_octet_frames() {
+        let mut generator = Syslog5424EventGenerator::new(true);
+        let mut buf = Vec::new();
+        for _ in 0..50 {
+            generator.generate_into(&mut buf);
+        }
+        let total_len = buf.len();
+
+        let mut framer = Framer::new(&MessageType::Syslog5424Octet);
+        let mut frame_bytes = 0;
+        let mut count = 0;
+        while let Some(frame) = framer.next_frame(&mut buf, false) {
+            assert!(frame.message.starts_with(b"<"));
+            assert!(!frame.message.ends_with(b"\n"));
+            frame_bytes += frame.frame_len;
+            count += 1;
+        }
+
+        assert_eq!(count, 50);
+        assert_eq!(frame_bytes, total_len);
+    }
+}
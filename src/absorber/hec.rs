@@ -0,0 +1,476 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Mutex,
+};
+
+use hyper::{Method, Request, Response, StatusCode, header::CONTENT_TYPE};
+use log::{debug, warn};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio_stream::StreamExt as _;
+
+use super::{
+    Source, StatsSvc,
+    faults::{self, BodyTooLarge},
+    http::{StatsUpdate, Stream, get_decompressed, record_update},
+    latency::latency_micros,
+    sequence::parse_sequence,
+};
+
+const CHANNEL_HEADER: &str = "x-splunk-request-channel";
+/// Acks a channel keeps for clients that never poll, before the oldest are forgotten
+const MAX_PENDING_ACKS: usize = 100_000;
+
+/// HEC status codes, as documented for Splunk's HTTP Event Collector
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum HecError {
+    TokenRequired,
+    InvalidAuthorization,
+    InvalidToken,
+    NoData,
+    InvalidDataFormat,
+    ChannelMissing,
+    InvalidChannel,
+    EventRequired,
+    EventBlank,
+    AckDisabled,
+    NotFound,
+    MethodNotAllowed,
+}
+
+impl HecError {
+    fn status(self) -> StatusCode {
+        match self {
+            Self::TokenRequired | Self::InvalidAuthorization => StatusCode::UNAUTHORIZED,
+            Self::InvalidToken => StatusCode::FORBIDDEN,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn code(self) -> u16 {
+        match self {
+            Self::TokenRequired => 2,
+            Self::InvalidAuthorization => 3,
+            Self::InvalidToken => 4,
+            Self::NoData => 5,
+            Self::InvalidDataFormat => 6,
+            Self::ChannelMissing => 10,
+            Self::InvalidChannel => 11,
+            Self::EventRequired => 12,
+            Self::EventBlank => 13,
+            Self::AckDisabled => 14,
+            Self::NotFound => 404,
+            Self::MethodNotAllowed => 405,
+        }
+    }
+
+    fn text(self) -> &'static str {
+        match self {
+            Self::TokenRequired => "Token is required",
+            Self::InvalidAuthorization => "Invalid authorization",
+            Self::InvalidToken => "Invalid token",
+            Self::NoData => "No data",
+            Self::InvalidDataFormat => "Invalid data format",
+            Self::ChannelMissing => "Data channel is missing",
+            Self::InvalidChannel => "Invalid data channel",
+            Self::EventRequired => "Event field is required",
+            Self::EventBlank => "Event field cannot be blank",
+            Self::AckDisabled => "ACK is disabled",
+            Self::NotFound => "The requested URL was not found on this server.",
+            Self::MethodNotAllowed => "Method not allowed",
+        }
+    }
+
+    /// Key the absorber counts validation failures by
+    fn reason(self) -> &'static str {
+        match self {
+            Self::EventRequired => "missing_event",
+            Self::EventBlank => "blank_event",
+            _ => "invalid_json",
+        }
+    }
+
+    fn response(self) -> Response<String> {
+        respond(self.status(), json!({"text": self.text(), "code": self.code()}))
+    }
+}
+
+fn respond(status: StatusCode, body: Value) -> Response<String> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .unwrap()
+}
+
+#[derive(Debug, PartialEq)]
+enum Endpoint {
+    Event,
+    Raw,
+    Health,
+    Ack,
+}
+
+impl Endpoint {
+    fn from_path(path: &str) -> Option<Self> {
+        match path.trim_end_matches('/') {
+            "/services/collector" | "/services/collector/event" | "/services/collector/event/1.0" => Some(Self::Event),
+            "/services/collector/raw" | "/services/collector/raw/1.0" => Some(Self::Raw),
+            "/services/collector/health" | "/services/collector/health/1.0" => Some(Self::Health),
+            "/services/collector/ack" => Some(Self::Ack),
+            _ => None,
+        }
+    }
+}
+
+/// Indexer acknowledgement state, shared by every connection to one listener
+#[derive(Default)]
+pub(super) struct HecState {
+    ack: bool,
+    channels: Mutex<HashMap<String, Channel>>,
+}
+
+#[derive(Default)]
+struct Channel {
+    next_ack_id: u64,
+    /// Acks for requests that have been indexed but not yet queried
+    indexed: BTreeSet<u64>,
+}
+
+impl HecState {
+    pub(super) fn new(ack: bool) -> Self {
+        Self { ack, ..Default::default() }
+    }
+
+    /// Everything is indexed as soon as it's received, so a new ack is immediately ready
+    fn next_ack(&self, channel: &str) -> u64 {
+        let mut channels = self.channels.lock().unwrap();
+        let channel = channels.entry(channel.to_string()).or_default();
+        let id = channel.next_ack_id;
+        channel.next_ack_id += 1;
+        channel.indexed.insert(id);
+        if channel.indexed.len() > MAX_PENDING_ACKS {
+            channel.indexed.pop_first();
+        }
+        id
+    }
+
+    /// Statuses for the requested acks. Acks that come back `true` are forgotten, as in Splunk.
+    fn query(&self, channel: &str, ids: &[u64]) -> BTreeMap<String, bool> {
+        let mut channels = self.channels.lock().unwrap();
+        let mut channel = channels.get_mut(channel);
+        ids.iter()
+            .map(|id| {
+                let indexed = channel.as_mut().is_some_and(|channel| channel.indexed.remove(id));
+                (id.to_string(), indexed)
+            })
+            .collect()
+    }
+}
+
+/// Handle a request as Splunk's HTTP Event Collector would
+pub(super) async fn handle_request(
+    req: Request<hyper::body::Incoming>,
+    stats: &StatsSvc,
+    token: Option<&str>,
+    source: &Source,
+    state: &HecState,
+    max_body_bytes: Option<u64>,
+) -> Response<String> {
+    let Some(endpoint) = Endpoint::from_path(req.uri().path()) else {
+        return HecError::NotFound.response();
+    };
+    let allowed = match endpoint {
+        Endpoint::Health => matches!(*req.method(), Method::GET | Method::POST),
+        _ => req.method() == Method::POST,
+    };
+    if !allowed {
+        return HecError::MethodNotAllowed.response();
+    }
+    if endpoint == Endpoint::Health {
+        return respond(StatusCode::OK, json!({"text": "HEC is healthy", "code": 17}));
+    }
+    if let Some(token) = token
+        && let Err(err) = check_token(&req, token)
+    {
+        warn!("Rejected HEC request from {:?}: {}", source.peer, err.text());
+        return err.response();
+    }
+
+    let channel = match request_channel(&req) {
+        Ok(channel) => channel,
+        Err(err) => return err.response(),
+    };
+    // Raw requests always need a channel, event requests only when acks are on
+    let needs_channel = endpoint == Endpoint::Raw || endpoint == Endpoint::Ack || state.ack;
+    if needs_channel && channel.is_none() {
+        return HecError::ChannelMissing.response();
+    }
+
+    let body = match read_body(get_decompressed(req, stats.clone(), source.metrics.clone(), max_body_bytes)).await {
+        Ok(body) => body,
+        Err(e) if e.is::<BodyTooLarge>() => {
+            warn!("Rejected HEC request from {:?}: body over the size limit", source.peer);
+            source.metrics.record_fault();
+            return faults::too_large();
+        }
+        Err(e) => {
+            debug!("Failed to read HEC request body: {e}");
+            return HecError::InvalidDataFormat.response();
+        }
+    };
+
+    let channel = channel.unwrap_or_default();
+    match endpoint {
+        Endpoint::Ack => ack_response(state, &channel, &body),
+        Endpoint::Event => {
+            let (update, rejected) = parse_events(&body, source);
+            record_update(stats, source, update).await;
+            if let Some((_, HecError::NoData)) = rejected {
+                return HecError::NoData.response();
+            }
+            if let Some((index, err)) = rejected {
+                source.record_invalid();
+                stats.record_invalid(err.reason()).await;
+                return respond(
+                    err.status(),
+                    json!({"text": err.text(), "code": err.code(), "invalid-event-number": index}),
+                );
+            }
+            success(state, &channel)
+        }
+        Endpoint::Raw => {
+            let update = parse_raw(&body, source);
+            if update.events == 0 {
+                return HecError::NoData.response();
+            }
+            record_update(stats, source, update).await;
+            success(state, &channel)
+        }
+        Endpoint::Health => unreachable!("health is answered before reading the body"),
+    }
+}
+
+fn success(state: &HecState, channel: &str) -> Response<String> {
+    match state.ack {
+        true => respond(
+            StatusCode::OK,
+            json!({"text": "Success", "code": 0, "ackId": state.next_ack(channel)}),
+        ),
+        false => respond(StatusCode::OK, json!({"text": "Success", "code": 0})),
+    }
+}
+
+fn ack_response(state: &HecState, channel: &str, body: &[u8]) -> Response<String> {
+    #[derive(Deserialize)]
+    struct AckRequest {
+        acks: Vec<u64>,
+    }
+
+    if !state.ack {
+        return HecError::AckDisabled.response();
+    }
+    match serde_json::from_slice::<AckRequest>(body) {
+        Ok(request) => respond(StatusCode::OK, json!({"acks": state.query(channel, &request.acks)})),
+        Err(_) => HecError::InvalidDataFormat.response(),
+    }
+}
+
+/// Accepts `Splunk <token>`, or Basic auth with the token as the password
+fn check_token(req: &Request<hyper::body::Incoming>, expected: &str) -> Result<(), HecError> {
+    use base64::prelude::*;
+
+    let Some(value) = req.headers().get(hyper::header::AUTHORIZATION) else {
+        return Err(HecError::TokenRequired);
+    };
+    let value = value.to_str().map_err(|_| HecError::InvalidAuthorization)?;
+    if value == expected {
+        return Ok(());
+    }
+    let expected_token = expected.strip_prefix("Splunk ").unwrap_or(expected);
+    let token = match value.split_once(' ') {
+        Some(("Splunk", token)) => token.to_string(),
+        Some(("Basic", encoded)) => {
+            let decoded = BASE64_STANDARD.decode(encoded).map_err(|_| HecError::InvalidAuthorization)?;
+            let decoded = String::from_utf8(decoded).map_err(|_| HecError::InvalidAuthorization)?;
+            let (_, password) = decoded.split_once(':').ok_or(HecError::InvalidAuthorization)?;
+            password.to_string()
+        }
+        _ => return Err(HecError::InvalidAuthorization),
+    };
+    match token == expected_token {
+        true => Ok(()),
+        false => Err(HecError::InvalidToken),
+    }
+}
+
+/// The channel from the `X-Splunk-Request-Channel` header or `channel` query parameter, which
+/// must be a GUID
+fn request_channel(req: &Request<hyper::body::Incoming>) -> Result<Option<String>, HecError> {
+    let header = req.headers().get(CHANNEL_HEADER).and_then(|value| value.to_str().ok());
+    let query = req
+        .uri()
+        .query()
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("channel=")));
+    match header.or(query) {
+        None => Ok(None),
+        Some(channel) if is_guid(channel) => Ok(Some(channel.to_ascii_lowercase())),
+        Some(_) => Err(HecError::InvalidChannel),
+    }
+}
+
+/// `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` in hex
+fn is_guid(value: &str) -> bool {
+    let groups: Vec<_> = value.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+async fn read_body(mut stream: Stream) -> anyhow::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk?);
+    }
+    Ok(body)
+}
+
+/// Events in an `/event` body are JSON objects with an `event` field, optionally separated by
+/// whitespace. Events before a bad one are still accepted, as in Splunk; the bad one's index
+/// comes back with the error.
+fn parse_events(body: &[u8], source: &Source) -> (StatsUpdate, Option<(usize, HecError)>) {
+    let mut update = StatsUpdate::default();
+    let mut events = serde_json::Deserializer::from_slice(body).into_iter::<Value>();
+    let mut start = 0;
+    let mut index = 0;
+    let rejected = loop {
+        let next = events.next();
+        let end = events.byte_offset();
+        let event = body[start..end].trim_ascii();
+        let err = match next {
+            None if index == 0 => Some(HecError::NoData),
+            None => None,
+            Some(Err(_)) => Some(HecError::InvalidDataFormat),
+            Some(Ok(value)) => match value.get("event") {
+                _ if !value.is_object() => Some(HecError::InvalidDataFormat),
+                None => Some(HecError::EventRequired),
+                Some(Value::Null) => Some(HecError::EventBlank),
+                Some(Value::String(s)) if s.is_empty() => Some(HecError::EventBlank),
+                Some(_) => {
+                    source.capture(event, true);
+                    update.add(event);
+                    index += 1;
+                    start = end;
+                    continue;
+                }
+            },
+        };
+        if let Some(err) = err {
+            if err != HecError::NoData {
+                // A parse error leaves the offset where it failed, so capture up to the end of the line
+                let rest = &body[start..];
+                let line_end = memchr::memchr(b'\n', &rest[1..]).map_or(rest.len(), |pos| pos + 1);
+                source.capture(rest[..line_end.max(end - start)].trim_ascii(), false);
+            }
+            break Some((index, err));
+        }
+        break None;
+    };
+    // Count whitespace between events too, so byte totals match what the emitter sent
+    update.bytes = match rejected {
+        None => body.len(),
+        Some(_) => start,
+    };
+    (update, rejected)
+}
+
+/// Every non-blank line of a `/raw` body is an event
+fn parse_raw(body: &[u8], source: &Source) -> StatsUpdate {
+    let mut update = StatsUpdate::default();
+    for line in body.split(|&b| b == b'\n').map(<[u8]>::trim_ascii).filter(|line| !line.is_empty()) {
+        source.capture(line, true);
+        update.add(line);
+    }
+    update.bytes = body.len();
+    update
+}
+
+impl StatsUpdate {
+    fn add(&mut self, event: &[u8]) {
+        self.events += 1;
+        if let Some((stream, seq)) = parse_sequence(event) {
+            self.sequences.push((stream.to_string(), seq));
+        }
+        if let Some(micros) = latency_micros(event) {
+            self.latencies.push(micros);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn splits_concatenated_events() {
+        let body = b"{\"event\":\"one\"}{\"event\":{\"n\":2}}\n  {\"event\":3,\"sourcetype\":\"x\"}\n";
+        let (update, rejected) = parse_events(body, &Source::default());
+        assert_eq!(rejected, None);
+        assert_eq!(update.events, 3);
+        assert_eq!(update.bytes, body.len());
+    }
+
+    #[test]
+    fn reports_the_first_bad_event() {
+        for (body, events, rejected) in [
+            (&b""[..], 0, (0, HecError::NoData)),
+            (b"  \n", 0, (0, HecError::NoData)),
+            (
+                b"{\"event\":1}{\"event\":2}{\"host\":\"h\"}{\"event\":4}",
+                2,
+                (2, HecError::EventRequired),
+            ),
+            (b"{\"event\":1}\n{\"event\":\"\"}", 1, (1, HecError::EventBlank)),
+            (b"{\"event\":null}", 0, (0, HecError::EventBlank)),
+            (b"{\"event\":1}\nnot json\n", 1, (1, HecError::InvalidDataFormat)),
+            (b"[1, 2]", 0, (0, HecError::InvalidDataFormat)),
+        ] {
+            let (update, found) = parse_events(body, &Source::default());
+            assert_eq!(found, Some(rejected), "{}", String::from_utf8_lossy(body));
+            assert_eq!(update.events, events, "{}", String::from_utf8_lossy(body));
+        }
+    }
+
+    #[test]
+    fn counts_raw_lines() {
+        let update = parse_raw(b"one\n\ntwo\r\nthree", &Source::default());
+        assert_eq!(update.events, 3);
+        assert_eq!(update.bytes, 15);
+    }
+
+    #[test]
+    fn acks_are_per_channel_and_forgotten_once_seen() {
+        let state = HecState::new(true);
+        assert_eq!(state.next_ack("a"), 0);
+        assert_eq!(state.next_ack("a"), 1);
+        assert_eq!(state.next_ack("b"), 0);
+
+        let acks = state.query("a", &[0, 1, 2]);
+        assert_eq!(acks, BTreeMap::from([("0".into(), true), ("1".into(), true), ("2".into(), false)]));
+        assert_eq!(state.query("a", &[0]), BTreeMap::from([("0".into(), false)]));
+        assert_eq!(state.query("missing", &[0]), BTreeMap::from([("0".into(), false)]));
+    }
+
+    #[test]
+    fn recognises_guids() {
+        assert!(is_guid("FE0ECFAD-13D5-401B-847D-77833BD77131"));
+        assert!(!is_guid("FE0ECFAD-13D5-401B-847D-77833BD7713"));
+        assert!(!is_guid("not-a-guid"));
+    }
+}
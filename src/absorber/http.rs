@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use async_compression::tokio::bufread::{BrotliDecoder, GzipDecoder, Lz4Decoder, ZstdDecoder};
 use bytes::Bytes;
-use http_body_util::BodyExt;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
 use hyper::{
     Request, Response,
     header::CONTENT_ENCODING,
@@ -16,7 +16,16 @@ use tokio_rustls::TlsAcceptor;
 use tokio_stream::{StreamExt, wrappers::TcpListenerStream};
 use tokio_util::io::StreamReader;
 
-use super::{AbsorberInner, ConnOptions, StatsSvc, extract_message, get_cert, validate_message};
+use super::{
+    AbsorberInner, ConnOptions, Invalid, Source, StatsSvc, extract_message,
+    faults::{self, BodyTooLarge, HttpFaults},
+    get_cert,
+    hec::{self, HecState},
+    latency::latency_micros,
+    metrics::ListenerMetrics,
+    sequence::parse_sequence,
+    validate_message,
+};
 use crate::config::MessageType;
 
 pub struct HttpAbsorber {
@@ -25,39 +34,22 @@ pub struct HttpAbsorber {
 }
 
 impl HttpAbsorber {
-    pub async fn build(opts: ConnOptions, message_type: MessageType) -> Self {
-        Self { message_type, opts }
+    pub async fn build(opts: ConnOptions) -> Self {
+        Self {
+            message_type: opts.message_type.clone(),
+            opts,
+        }
     }
 
-    pub(super) async fn run(self, stats: StatsSvc) -> anyhow::Result<()> {
+    pub(super) async fn run(self, stats: StatsSvc, source: Source) -> anyhow::Result<()> {
         debug!("Building a HTTP absorber with opts={:?}", self.opts);
         let ConnOptions { addr, cert_type, .. } = self.opts;
         let listener = TcpListener::bind((addr.host, addr.port))
             .await
             .expect("Could not bind to TCP address & port");
-        let cert_key = get_cert(&cert_type, self.opts.mtls).await?;
+        let cert_key = get_cert(&cert_type, self.opts.mtls, self.opts.client_ca.as_deref()).await?;
         let acceptor = if let Some(cert_key) = cert_key {
-            let key = cert_key.key();
-            let builder = rustls::ServerConfig::builder();
-
-            let builder = if self.opts.mtls {
-                if let Some(roots) = cert_key.root_cert() {
-                    let mut store = rustls::RootCertStore::empty();
-                    for root in roots {
-                        store.add(root).expect("Failed to add root cert");
-                    }
-                    let verifier = rustls::server::WebPkiClientVerifier::builder(Arc::new(store))
-                        .build()
-                        .expect("Failed to build client verifier");
-                    builder.with_client_cert_verifier(verifier)
-                } else {
-                    panic!("mTLS enabled but no root cert available");
-                }
-            } else {
-                builder.with_no_client_auth()
-            };
-
-            let mut config = builder.with_single_cert(cert_key.cert(), key)?;
+            let mut config = cert_key.server_config(self.opts.mtls)?;
             if self.opts.http_version == hyper::Version::HTTP_2 {
                 config.alpn_protocols = vec!["h2".into()];
             } else {
@@ -69,6 +61,9 @@ impl HttpAbsorber {
             None
         };
 
+        // Acks are tracked per listener, as a HEC client may spread requests over connections
+        let hec = Arc::new(HecState::new(self.opts.hec_ack));
+        let faults = Arc::new(self.opts.http_faults.clone());
         let mut listener = TcpListenerStream::new(listener);
         while let Some(s) = listener.next().await {
             match s {
@@ -79,10 +74,24 @@ impl HttpAbsorber {
                     let expected_token = self.opts.token.clone();
 
                     let stats = stats.clone();
+                    let source = source.with_peer(remote_addr);
                     let acceptor = acceptor.clone();
+                    let hec = hec.clone();
+                    let faults = faults.clone();
                     tokio::spawn(async move {
+                        let _connection = source.connection();
                         let message_type = message_type.clone();
-                        let service = service_fn(|req| handle_request(req, stats.clone(), message_type.clone(), expected_token.clone()));
+                        let service = service_fn(|req| {
+                            handle_request(
+                                req,
+                                stats.clone(),
+                                message_type.clone(),
+                                expected_token.clone(),
+                                source.clone(),
+                                hec.clone(),
+                                faults.clone(),
+                            )
+                        });
 
                         // Handle either TLS or non-TLS connection
                         if let Some(tls_acceptor) = acceptor {
@@ -136,26 +145,75 @@ impl HttpAbsorber {
     }
 }
 
+/// Injected faults come first, and an error closes the connection
 async fn handle_request(
     req: Request<hyper::body::Incoming>,
     stats: StatsSvc,
     message_type: MessageType,
     token: Option<String>,
-) -> Result<Response<String>, hyper::Error> {
+    source: Source,
+    hec: Arc<HecState>,
+    faults: Arc<HttpFaults>,
+) -> anyhow::Result<Response<String>> {
+    let response = match faults.pick(&req) {
+        Some(fault) => {
+            source.metrics.record_fault();
+            faults.inject(fault, req).await?
+        }
+        None if message_type == MessageType::SplunkHec => {
+            hec::handle_request(req, &stats, token.as_deref(), &source, &hec, faults.max_body_bytes()).await
+        }
+        None => process_request(req, &stats, message_type, token, &source, faults.max_body_bytes()).await,
+    };
+    faults.delay().await;
+    Ok(response)
+}
+
+async fn process_request(
+    req: Request<hyper::body::Incoming>,
+    stats: &StatsSvc,
+    message_type: MessageType,
+    token: Option<String>,
+    source: &Source,
+    max_body_bytes: Option<u64>,
+) -> Response<String> {
     if let Some(token) = token
         && let Err(err) = check_auth(&req, token)
     {
-        return Ok(*err);
+        return *err;
     }
-    let stream = get_decompressed(req, stats.clone());
+    let stream = get_decompressed(req, stats.clone(), source.metrics.clone(), max_body_bytes);
 
-    let StatsUpdate { events, bytes } = match process_messages(stream, message_type).await {
-        Ok(stats) => stats,
-        Err(err) => return Ok(err),
+    let update = match process_messages(stream, message_type, source).await {
+        Ok(update) => update,
+        Err(err) => {
+            if let Some(invalid) = err.extensions().get::<Invalid>() {
+                source.record_invalid();
+                stats.record_invalid(invalid.reason).await;
+            }
+            return *err;
+        }
     };
-    stats.increment(events, 0, bytes).await;
+    record_update(stats, source, update).await;
+
+    Response::new("OK".to_string())
+}
 
-    Ok(Response::new("OK".to_string()))
+pub(super) async fn record_update(stats: &StatsSvc, source: &Source, update: StatsUpdate) {
+    let StatsUpdate {
+        events,
+        bytes,
+        sequences,
+        latencies,
+    } = update;
+    source.record_events(events as u64, bytes as u64);
+    stats.increment(events, 0, bytes).await;
+    for (stream, seq) in sequences {
+        stats.record_sequence(&stream, seq).await;
+    }
+    for micros in latencies {
+        stats.record_latency(micros).await;
+    }
 }
 
 fn check_auth(req: &Request<hyper::body::Incoming>, expected: String) -> Result<(), Box<Response<String>>> {
@@ -179,30 +237,46 @@ fn check_auth(req: &Request<hyper::body::Incoming>, expected: String) -> Result<
     ))
 }
 
-struct StatsUpdate {
-    events: usize,
-    bytes: usize,
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct StatsUpdate {
+    pub(super) events: usize,
+    pub(super) bytes: usize,
+    pub(super) sequences: Vec<(String, u64)>,
+    pub(super) latencies: Vec<u64>,
 }
-async fn process_messages(stream: Stream, message_type: MessageType) -> Result<StatsUpdate, Response<String>> {
+async fn process_messages(stream: Stream, message_type: MessageType, source: &Source) -> Result<StatsUpdate, Box<Response<String>>> {
     let mut msg = Vec::new();
     let mut events = 0;
     let mut bytes = 0;
+    let mut sequences = Vec::new();
+    let mut latencies = Vec::new();
 
-    let mut extract_all = |msg: &mut Vec<u8>, fin: bool| -> Result<(), Response<String>> {
+    let mut extract_all = |msg: &mut Vec<u8>, fin: bool| -> Result<(), Box<Response<String>>> {
         while let Some(message) = extract_message(msg, fin) {
-            if !validate_message(&message, &message_type) {
+            let result = validate_message(&message, &message_type);
+            source.capture(&message, result.is_ok());
+            if let Err(invalid) = result {
                 error!(
-                    "Invalid message received. Expected type: {:?}, found {:?}",
+                    "Invalid {:?} message ({invalid}): {:?}",
                     message_type,
                     String::from_utf8_lossy(&message)
                 );
-                return Err(Response::builder()
-                    .status(hyper::StatusCode::BAD_REQUEST)
-                    .body("Invalid message format".to_string())
-                    .unwrap());
+                return Err(Box::new(
+                    Response::builder()
+                        .status(hyper::StatusCode::BAD_REQUEST)
+                        .extension(invalid.clone())
+                        .body(format!("Invalid message format: {invalid}"))
+                        .unwrap(),
+                ));
             }
             events += 1;
             bytes += message.len();
+            if let Some((stream, seq)) = parse_sequence(&message) {
+                sequences.push((stream.to_string(), seq));
+            }
+            if let Some(micros) = latency_micros(&message) {
+                latencies.push(micros);
+            }
         }
         Ok(())
     };
@@ -218,12 +292,19 @@ async fn process_messages(stream: Stream, message_type: MessageType) -> Result<S
                     debug!("Empty final message, ignoring.");
                 }
             }
+            Err(e) if e.is::<BodyTooLarge>() => {
+                error!("Request body over the size limit from {:?}", source.peer);
+                source.metrics.record_fault();
+                return Err(Box::new(faults::too_large()));
+            }
             Err(e) => {
                 error!("Error processing message: {}", e);
-                return Err(Response::builder()
-                    .status(hyper::StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(format!("Error processing message: {}", e))
-                    .unwrap());
+                return Err(Box::new(
+                    Response::builder()
+                        .status(hyper::StatusCode::INTERNAL_SERVER_ERROR)
+                        .body(format!("Error processing message: {}", e))
+                        .unwrap(),
+                ));
             }
         }
     }
@@ -231,69 +312,79 @@ async fn process_messages(stream: Stream, message_type: MessageType) -> Result<S
     extract_all(&mut msg, true)?;
     if !msg.is_empty() {
         error!("Received message with trailing data: {}", String::from_utf8_lossy(&msg));
-        return Err(Response::builder()
-            .status(hyper::StatusCode::BAD_REQUEST)
-            .body("Received message with trailing data".to_string())
-            .unwrap());
+        return Err(Box::new(
+            Response::builder()
+                .status(hyper::StatusCode::BAD_REQUEST)
+                .extension(Invalid::new("trailing_data", "unterminated data at the end of the body"))
+                .body("Received message with trailing data".to_string())
+                .unwrap(),
+        ));
     }
 
-    Ok(StatsUpdate { events, bytes })
+    Ok(StatsUpdate {
+        events,
+        bytes,
+        sequences,
+        latencies,
+    })
 }
 
-type Stream = Box<dyn tokio_stream::Stream<Item = anyhow::Result<Bytes>> + Unpin + Send>;
-fn get_decompressed(req: Request<hyper::body::Incoming>, stats: StatsSvc) -> Stream {
+pub(super) type Stream = Box<dyn tokio_stream::Stream<Item = anyhow::Result<Bytes>> + Unpin + Send>;
+/// The request body as a stream of decompressed data, which ends in a `BodyTooLarge` error once more
+/// than `max_body_bytes` have been read
+pub(super) fn get_decompressed(
+    req: Request<hyper::body::Incoming>,
+    stats: StatsSvc,
+    metrics: Arc<ListenerMetrics>,
+    max_body_bytes: Option<u64>,
+) -> Stream {
     let encoding = req
         .headers()
         .get(CONTENT_ENCODING)
         .and_then(|value| value.to_str().ok())
         .map(|s| s.to_lowercase());
 
-    let body = req.into_body().into_data_stream();
+    let limit = max_body_bytes.map_or(usize::MAX, |max| usize::try_from(max).unwrap_or(usize::MAX));
+    let body = Limited::new(req.into_body(), limit).into_data_stream();
 
     let body = body.map(move |result| {
         if let Ok(data) = &result {
             stats.try_increment(0, data.len(), 0);
+            metrics.record_raw_bytes(data.len() as u64);
         }
-        result
+        result.map_err(std::io::Error::other)
     });
 
     match encoding.as_deref() {
         Some("gzip") => {
-            let reader = StreamReader::new(body.map(|result| result.map_err(std::io::Error::other)));
-            let decoder = GzipDecoder::new(reader);
-            let decompressed = tokio_util::io::ReaderStream::new(decoder)
-                .map(|result| result.map_err(|e| anyhow::anyhow!("Gzip decompression error: {}", e)));
-            Box::new(decompressed)
+            let decoder = GzipDecoder::new(StreamReader::new(body));
+            Box::new(tokio_util::io::ReaderStream::new(decoder).map(|result| result.map_err(body_error("Gzip decompression"))))
         }
         Some("zstd") => {
-            let reader = StreamReader::new(body.map(|result| result.map_err(std::io::Error::other)));
-            let decoder = ZstdDecoder::new(reader);
-            let decompressed = tokio_util::io::ReaderStream::new(decoder)
-                .map(|result| result.map_err(|e| anyhow::anyhow!("Zstd decompression error: {}", e)));
-            Box::new(decompressed)
+            let decoder = ZstdDecoder::new(StreamReader::new(body));
+            Box::new(tokio_util::io::ReaderStream::new(decoder).map(|result| result.map_err(body_error("Zstd decompression"))))
         }
         Some("lz4") => {
-            let reader = StreamReader::new(body.map(|result| result.map_err(std::io::Error::other)));
-            let decoder = Lz4Decoder::new(reader);
-            let decompressed = tokio_util::io::ReaderStream::new(decoder)
-                .map(|result| result.map_err(|e| anyhow::anyhow!("LZ4 decompression error: {}", e)));
-            Box::new(decompressed)
+            let decoder = Lz4Decoder::new(StreamReader::new(body));
+            Box::new(tokio_util::io::ReaderStream::new(decoder).map(|result| result.map_err(body_error("LZ4 decompression"))))
         }
         Some("br") => {
-            let reader = StreamReader::new(body.map(|result| result.map_err(std::io::Error::other)));
-            let decoder = BrotliDecoder::new(reader);
-            let decompressed = tokio_util::io::ReaderStream::new(decoder)
-                .map(|result| result.map_err(|e| anyhow::anyhow!("Brotli decompression error: {}", e)));
-            Box::new(decompressed)
+            let decoder = BrotliDecoder::new(StreamReader::new(body));
+            Box::new(tokio_util::io::ReaderStream::new(decoder).map(|result| result.map_err(body_error("Brotli decompression"))))
         }
         Some("snappy") | Some("x-snappy") => {
-            let reader = StreamReader::new(body.map(|result| result.map_err(std::io::Error::other)));
-            let decoder = tokio_snappy::SnappyIO::new(reader);
-            let decompressed = tokio_util::io::ReaderStream::new(decoder)
-                .map(|result| result.map_err(|e| anyhow::anyhow!("Snappy decompression error: {}", e)));
-            Box::new(decompressed)
+            let decoder = tokio_snappy::SnappyIO::new(StreamReader::new(body));
+            Box::new(tokio_util::io::ReaderStream::new(decoder).map(|result| result.map_err(body_error("Snappy decompression"))))
         }
-        _ => Box::new(body.map(|result| result.map_err(|e| anyhow::anyhow!("Body error: {}", e)))),
+        _ => Box::new(body.map(|result| result.map_err(body_error("Body")))),
+    }
+}
+
+/// Keep a body cut off by `Limited` recognisable through the decoders, so it can be answered with a 413
+fn body_error(what: &'static str) -> impl Fn(std::io::Error) -> anyhow::Error {
+    move |e| match e.get_ref().is_some_and(|inner| inner.is::<LengthLimitError>()) {
+        true => anyhow::Error::new(BodyTooLarge),
+        false => anyhow::anyhow!("{what} error: {e}"),
     }
 }
 
@@ -7,13 +7,13 @@ mod tests {
     use tokio::io::AsyncWriteExt;
 
     use crate::{
-        absorber::{stats_svc::StatsSvc, tcp::handle_tcp_connection},
-        config::MessageType,
+        absorber::{Source, stats_svc::StatsSvc, tcp::handle_tcp_connection},
+        config::{MessageType, StatsFormat},
     };
 
     #[test(tokio::test)]
     async fn test_tcp_absorber_decompression_metrics_direct() {
-        let stats = StatsSvc::run(1000);
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
         let message_type = MessageType::NdJson;
 
         // Prepare gzipped data
@@ -29,7 +29,9 @@ mod tests {
         let socket = std::io::Cursor::new(compressed_data);
 
         // Run handle_tcp_connection
-        handle_tcp_connection(socket, &stats, &message_type).await.unwrap();
+        handle_tcp_connection(socket, &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
 
         // Allow some time for the stats task to process messages
         tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
@@ -43,7 +45,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_tcp_absorber_decompression_metrics_with_trailing_newline() {
-        let stats = StatsSvc::run(1000);
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
         let message_type = MessageType::NdJson;
 
         // Prepare gzipped data with TWO newlines
@@ -53,7 +55,9 @@ mod tests {
         let compressed_data = encoder.finish().unwrap();
 
         let socket = std::io::Cursor::new(compressed_data);
-        handle_tcp_connection(socket, &stats, &message_type).await.unwrap();
+        handle_tcp_connection(socket, &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
         tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
 
         let (events, ..) = stats.get_stats().await;
@@ -62,7 +66,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_tcp_absorber_decompression_metrics_with_trailing_space() {
-        let stats = StatsSvc::run(1000);
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
         let message_type = MessageType::NdJson;
 
         // Prepare gzipped data with trailing space
@@ -72,7 +76,9 @@ mod tests {
         let compressed_data = encoder.finish().unwrap();
 
         let socket = std::io::Cursor::new(compressed_data);
-        handle_tcp_connection(socket, &stats, &message_type).await.unwrap();
+        handle_tcp_connection(socket, &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
         tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
 
         let (events, ..) = stats.get_stats().await;
@@ -84,7 +90,7 @@ mod tests {
     #[tokio::test]
     async fn test_tcp_absorber_zstd_metrics_direct() {
         use async_compression::tokio::write::ZstdEncoder;
-        let stats = StatsSvc::run(1000);
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
         let message_type = MessageType::NdJson;
 
         let original_data = b"{\"key\": \"value\"}\n";
@@ -97,7 +103,9 @@ mod tests {
         let original_len = original_data.len();
 
         let socket = std::io::Cursor::new(compressed_data);
-        handle_tcp_connection(socket, &stats, &message_type).await.unwrap();
+        handle_tcp_connection(socket, &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
         tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
 
         let (events, raw_bytes, decomp_bytes) = stats.get_stats().await;
@@ -109,7 +117,7 @@ mod tests {
     #[tokio::test]
     async fn test_tcp_absorber_lz4_metrics_direct() {
         use async_compression::tokio::write::Lz4Encoder;
-        let stats = StatsSvc::run(1000);
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
         let message_type = MessageType::NdJson;
 
         let original_data = b"{\"key\": \"value\"}\n";
@@ -122,7 +130,9 @@ mod tests {
         let original_len = original_data.len();
 
         let socket = std::io::Cursor::new(compressed_data);
-        handle_tcp_connection(socket, &stats, &message_type).await.unwrap();
+        handle_tcp_connection(socket, &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
         tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
 
         let (events, raw_bytes, decomp_bytes) = stats.get_stats().await;
@@ -133,7 +143,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_tcp_absorber_snappy_metrics_direct() {
-        let stats = StatsSvc::run(1000);
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
         let message_type = MessageType::NdJson;
 
         let original_data = b"{\"key\": \"value\"}\n";
@@ -145,7 +155,9 @@ mod tests {
         let original_len = original_data.len();
 
         let socket = std::io::Cursor::new(compressed_data);
-        handle_tcp_connection(socket, &stats, &message_type).await.unwrap();
+        handle_tcp_connection(socket, &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
         tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
 
         let (events, raw_bytes, decomp_bytes) = stats.get_stats().await;
@@ -156,7 +168,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_tcp_absorber_uncompressed_metrics_direct() {
-        let stats = StatsSvc::run(1000);
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
         let message_type = MessageType::NdJson;
 
         let original_data = b"{\"key\": \"value\"}\n";
@@ -164,7 +176,9 @@ mod tests {
 
         let socket = std::io::Cursor::new(original_data);
 
-        handle_tcp_connection(socket, &stats, &message_type).await.unwrap();
+        handle_tcp_connection(socket, &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
 
         tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
 
@@ -175,6 +189,206 @@ mod tests {
         assert_eq!(decomp_bytes, original_len);
     }
 
+    #[tokio::test]
+    async fn test_tcp_absorber_octet_counted_syslog() {
+        use crate::generators::{EventGenerator, Syslog5424EventGenerator};
+
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
+        let message_type = MessageType::Syslog5424Octet;
+
+        let mut generator = Syslog5424EventGenerator::new(true);
+        let mut data = Vec::new();
+        for _ in 0..100 {
+            generator.generate_into(&mut data);
+        }
+        let original_len = data.len();
+
+        let socket = std::io::Cursor::new(data);
+        handle_tcp_connection(socket, &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        let (events, raw_bytes, decomp_bytes) = stats.get_stats().await;
+        assert_eq!(events, 100);
+        assert_eq!(raw_bytes, original_len);
+        assert_eq!(decomp_bytes, original_len);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_absorber_gzipped_octet_counted_syslog() {
+        use crate::generators::{EventGenerator, Syslog5424EventGenerator};
+
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
+        let message_type = MessageType::Syslog5424;
+
+        let mut generator = Syslog5424EventGenerator::new(true);
+        let mut data = Vec::new();
+        for _ in 0..100 {
+            generator.generate_into(&mut data);
+        }
+        let original_len = data.len();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let socket = std::io::Cursor::new(encoder.finish().unwrap());
+        handle_tcp_connection(socket, &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        let (events, _, decomp_bytes) = stats.get_stats().await;
+        assert_eq!(events, 100);
+        assert_eq!(decomp_bytes, original_len);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_absorber_emitter_compressed_streams() {
+        use crate::{
+            config,
+            generators::{EventGenerator, Syslog5424EventGenerator},
+            transports::compression::StreamEncoder,
+        };
+
+        for compression in [
+            config::Compression::Gzip,
+            config::Compression::Zstd,
+            config::Compression::Lz4,
+            config::Compression::Snappy,
+        ] {
+            let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
+            let message_type = MessageType::Syslog5424;
+
+            // One flushed batch per event, as a TCP emitter sends them, with the stream left unfinished
+            let mut generator = Syslog5424EventGenerator::new(false);
+            let mut encoder = StreamEncoder::new(compression);
+            let mut wire = Vec::new();
+            let mut original_len = 0;
+            for _ in 0..20 {
+                let mut event = Vec::new();
+                generator.generate_into(&mut event);
+                original_len += event.len();
+                wire.extend_from_slice(encoder.encode(&event).await.unwrap());
+            }
+            let wire_len = wire.len();
+
+            let _ = handle_tcp_connection(std::io::Cursor::new(wire), &stats, &message_type, &Source::default()).await;
+            tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+            let (events, raw_bytes, decomp_bytes) = stats.get_stats().await;
+            assert_eq!(events, 20, "{compression}");
+            assert_eq!(raw_bytes, wire_len, "{compression}");
+            assert_eq!(decomp_bytes, original_len, "{compression}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tcp_absorber_tracks_emitter_sequences() {
+        use crate::generators::{EventGenerator, Sequence, Syslog5424EventGenerator, Tags};
+
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
+        let message_type = MessageType::Syslog5424Octet;
+
+        let mut generator = Syslog5424EventGenerator::new(true).with_tags(Tags {
+            sequence: Some(Sequence::new("feedbeef".to_string())),
+            ..Default::default()
+        });
+        let mut events = Vec::new();
+        for _ in 0..10 {
+            let mut event = Vec::new();
+            generator.generate_into(&mut event);
+            events.push(event);
+        }
+        // Lose seq 3, deliver seq 6 twice, and swap 8 and 9
+        events.remove(3);
+        events.insert(6, events[5].clone());
+        events.swap(8, 9);
+
+        let socket = std::io::Cursor::new(events.concat());
+        handle_tcp_connection(socket, &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        let (received, ..) = stats.get_stats().await;
+        let sequences = stats.get_sequences().await;
+        assert_eq!(received, 10);
+        assert_eq!(sequences.streams, 1);
+        assert_eq!(sequences.received, 10);
+        assert_eq!(sequences.missing, 1);
+        assert_eq!(sequences.duplicates, 1);
+        assert_eq!(sequences.out_of_order, 1);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_absorber_captures_events() {
+        use crate::{absorber::capture::CaptureSvc, config::absorber::CaptureFormat};
+
+        let dir = std::env::temp_dir().join(format!("protoglot-capture-{}", std::process::id()));
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
+        let message_type = MessageType::NdJson;
+        let source = Source {
+            listener: "tcp://127.0.0.1:9514".into(),
+            peer: Some("10.1.2.3:5555".parse().unwrap()),
+            capture: Some(CaptureSvc::run(&dir, CaptureFormat::Ndjson, None).unwrap()),
+            ..Source::default()
+        };
+
+        let wire = b"{\"n\":1}\n{\"n\":2}\nnot json\n".to_vec();
+        handle_tcp_connection(std::io::Cursor::new(wire), &stats, &message_type, &source)
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let capture = std::fs::read_to_string(dir.join("capture.ndjson")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let records: Vec<serde_json::Value> = capture.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let events: Vec<_> = records
+            .iter()
+            .map(|r| (r["event"].as_str().unwrap(), r["valid"].as_bool().unwrap()))
+            .collect();
+        assert_eq!(events, vec![("{\"n\":1}", true), ("{\"n\":2}", true), ("not json", false)]);
+        assert_eq!(stats.summary().await.unwrap().invalid_events, 1);
+        assert!(
+            records
+                .iter()
+                .all(|r| r["listener"] == "tcp://127.0.0.1:9514" && r["peer"] == "10.1.2.3:5555")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tcp_absorber_measures_latency() {
+        use crate::generators::{EventGenerator, NdJsonEventGenerator, Tags};
+
+        let stats = StatsSvc::run(1000, StatsFormat::Text, Vec::new());
+        let message_type = MessageType::NdJson;
+
+        let mut generator = NdJsonEventGenerator::new().with_tags(Tags {
+            timestamp: true,
+            ..Default::default()
+        });
+        let mut wire = Vec::new();
+        for _ in 0..5 {
+            generator.generate_into(&mut wire);
+        }
+        // One untagged event doesn't contribute a sample
+        wire.extend_from_slice(b"{\"msg\":\"untagged\"}\n");
+
+        handle_tcp_connection(std::io::Cursor::new(wire), &stats, &message_type, &Source::default())
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        let (received, ..) = stats.get_stats().await;
+        let latency = stats.get_latency().await;
+        assert_eq!(received, 6);
+        assert_eq!(latency.samples, 5);
+        assert!(latency.max < 5_000_000, "{latency:?}");
+
+        stats.reset().await;
+        assert_eq!(stats.get_latency().await.samples, 0);
+    }
+
     #[test(tokio::test)]
     async fn test_http_absorber_multi_decompression() {
         use crate::{
@@ -188,6 +402,7 @@ mod tests {
                 host: "127.0.0.1".to_string(),
                 port,
                 protocol: Protocol::Http,
+                ..Default::default()
             }],
             update_interval: 100,
             message_type: MessageType::NdJson,
@@ -267,4 +482,298 @@ mod tests {
             .unwrap();
         assert_eq!(res.status(), 200);
     }
+
+    #[test(tokio::test)]
+    async fn test_http_absorber_speaks_hec() {
+        use serde_json::{Value, json};
+
+        use crate::{
+            absorber::Absorber,
+            config::{ListenAddress, Protocol, absorber::AbsorberConfig},
+        };
+
+        let port = 12347;
+        let config = AbsorberConfig {
+            listen_addresses: vec![ListenAddress {
+                host: "127.0.0.1".to_string(),
+                port,
+                protocol: Protocol::Http,
+                ..Default::default()
+            }],
+            update_interval: 100,
+            message_type: MessageType::SplunkHec,
+            hec_ack: true,
+            ..Default::default()
+        };
+
+        let absorber = Absorber::new(config);
+
+        tokio::spawn(async move {
+            absorber.run().await.unwrap();
+        });
+
+        // Wait for server to start
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let client = reqwest::Client::new();
+        let url = |path: &str| format!("http://127.0.0.1:{port}/services/collector{path}");
+        let channel = "0f6a9e4c-2d4b-4e3a-9c1e-7b5d8a2f3e10";
+        let send = |req: reqwest::RequestBuilder| async move {
+            let res = req.send().await.unwrap();
+            let status = res.status().as_u16();
+            (status, serde_json::from_str::<Value>(&res.text().await.unwrap()).unwrap())
+        };
+
+        let health = send(client.get(url("/health"))).await;
+        assert_eq!(health, (200, json!({"text": "HEC is healthy", "code": 17})));
+
+        let events = "{\"event\":\"one\"}{\"event\":\"two\"}";
+        let missing = send(client.post(url("/event")).body(events)).await;
+        assert_eq!(missing, (400, json!({"text": "Data channel is missing", "code": 10})));
+
+        let first = send(client.post(url("/event")).header("X-Splunk-Request-Channel", channel).body(events)).await;
+        assert_eq!(first, (200, json!({"text": "Success", "code": 0, "ackId": 0})));
+        let raw = send(client.post(format!("{}?channel={channel}", url("/raw"))).body("one\ntwo\n")).await;
+        assert_eq!(raw, (200, json!({"text": "Success", "code": 0, "ackId": 1})));
+
+        let bad = send(
+            client
+                .post(url("/event"))
+                .header("X-Splunk-Request-Channel", channel)
+                .body("{\"event\":1}{\"host\":\"h\"}"),
+        )
+        .await;
+        assert_eq!(
+            bad,
+            (
+                400,
+                json!({"text": "Event field is required", "code": 12, "invalid-event-number": 1})
+            )
+        );
+
+        let acks = client
+            .post(url("/ack"))
+            .header("X-Splunk-Request-Channel", channel)
+            .body(r#"{"acks":[0,1,2]}"#);
+        assert_eq!(send(acks).await, (200, json!({"acks": {"0": true, "1": true, "2": false}})));
+
+        let not_found = send(client.post(url("/nope"))).await;
+        assert_eq!(not_found.0, 404);
+    }
+
+    #[test(tokio::test)]
+    async fn test_http_absorber_applies_per_listener_options() {
+        use crate::{
+            absorber::Absorber,
+            config::{ListenAddress, absorber::AbsorberConfig},
+        };
+
+        let config = AbsorberConfig {
+            listen_addresses: vec![
+                ListenAddress::try_from("http://127.0.0.1:12348?type=splunk-hec&auth=splunk&token=s3cret").unwrap(),
+                ListenAddress::try_from("http://127.0.0.1:12349").unwrap(),
+            ],
+            update_interval: 100,
+            message_type: MessageType::NdJson,
+            ..Default::default()
+        };
+
+        let absorber = Absorber::new(config);
+
+        tokio::spawn(async move {
+            absorber.run().await.unwrap();
+        });
+
+        // Wait for server to start
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let client = reqwest::Client::new();
+        let hec = "http://127.0.0.1:12348/services/collector/health";
+        assert_eq!(client.get(hec).send().await.unwrap().status(), 200);
+        let event = "http://127.0.0.1:12348/services/collector/event";
+        let unauthorized = client.post(event).body(r#"{"event":"one"}"#).send().await.unwrap();
+        assert_eq!(unauthorized.status(), 401);
+        let authorized = client
+            .post(event)
+            .header("Authorization", "Splunk s3cret")
+            .body(r#"{"event":"one"}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(authorized.status(), 200);
+
+        // The other listener keeps the absorber-wide NDJSON without auth
+        let ndjson = client.post("http://127.0.0.1:12349/").body("{\"a\":1}\n").send().await.unwrap();
+        assert_eq!(ndjson.status(), 200);
+        let hec_body = client.post("http://127.0.0.1:12349/").body("not json\n").send().await.unwrap();
+        assert_eq!(hec_body.status(), 400);
+    }
+
+    #[test(tokio::test)]
+    async fn test_tcps_absorber_serves_cert_files_with_client_ca() {
+        use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+
+        use crate::{
+            absorber::Absorber,
+            config::{ListenAddress, Protocol, absorber::AbsorberConfig},
+            transports::{Transport as _, tcp_tls::TcpTlsTransport, tls::TlsOptions},
+        };
+
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let dir = std::env::temp_dir().join("protoglot-test-cert-files");
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, pem: String| {
+            let path = dir.join(name);
+            std::fs::write(&path, pem).unwrap();
+            path
+        };
+
+        let server = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let mut ca_params = CertificateParams::new(vec![]).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca_key = KeyPair::generate().unwrap();
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let client_key = KeyPair::generate().unwrap();
+        let client = CertificateParams::new(vec![])
+            .unwrap()
+            .signed_by(&client_key, &ca, &ca_key)
+            .unwrap();
+
+        let port = 12350;
+        let config = AbsorberConfig {
+            listen_addresses: vec![ListenAddress {
+                host: "127.0.0.1".to_string(),
+                port,
+                protocol: Protocol::Tcps,
+                ..Default::default()
+            }],
+            message_type: MessageType::NdJson,
+            cert: Some(write("server_cert.pem", server.cert.pem())),
+            key: Some(write("server_key.pem", server.key_pair.serialize_pem())),
+            client_ca: Some(write("client_ca.pem", ca.pem())),
+            mtls: true,
+            metrics: Some("127.0.0.1:12351".to_string()),
+            ..Default::default()
+        };
+
+        let absorber = Absorber::new(config);
+
+        tokio::spawn(async move {
+            absorber.run().await.unwrap();
+        });
+
+        // Wait for server to start
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let tls = TlsOptions {
+            ca_file: Some(dir.join("server_cert.pem")),
+            client_cert: Some(write("client_cert.pem", client.pem())),
+            client_key: Some(write("client_key.pem", client_key.serialize_pem())),
+            server_name: Some("localhost".to_string()),
+            ..Default::default()
+        };
+        let mut transport = TcpTlsTransport::new("127.0.0.1".to_string(), port, &tls, Default::default())
+            .await
+            .unwrap();
+        transport.send(b"{\"a\":1}\n{\"a\":2}\n").await.unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let metrics = reqwest::get("http://127.0.0.1:12351/metrics").await.unwrap().text().await.unwrap();
+        let events = format!("protoglot_absorber_events_total{{listener=\"tcps://127.0.0.1:{port}\"}} 2");
+        assert!(metrics.lines().any(|line| line == events), "{metrics}");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test(tokio::test)]
+    async fn test_http_absorber_limits_chunked_bodies() {
+        use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+        use crate::{
+            absorber::Absorber,
+            config::{ListenAddress, absorber::AbsorberConfig},
+        };
+
+        let config = AbsorberConfig {
+            listen_addresses: vec![
+                ListenAddress::try_from("http://127.0.0.1:12352").unwrap(),
+                ListenAddress::try_from("http://127.0.0.1:12353?type=splunk-hec").unwrap(),
+            ],
+            update_interval: 100,
+            message_type: MessageType::NdJson,
+            max_body_bytes: Some(16),
+            ..Default::default()
+        };
+
+        let absorber = Absorber::new(config);
+
+        tokio::spawn(async move {
+            absorber.run().await.unwrap();
+        });
+
+        // Wait for server to start
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        // Chunked bodies declare no length, so the limit has to hold while they are read
+        let post_chunked = |port: u16, path: &'static str, chunks: Vec<&'static str>| async move {
+            let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            let mut request = format!("POST {path} HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n");
+            for chunk in chunks {
+                request += &format!("{:x}\r\n{chunk}\r\n", chunk.len());
+            }
+            request += "0\r\n\r\n";
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response.lines().next().unwrap().to_string()
+        };
+
+        let small = post_chunked(12352, "/", vec!["{\"a\":1}\n"]).await;
+        assert_eq!(small, "HTTP/1.1 200 OK");
+        let large = post_chunked(12352, "/", vec!["{\"a\":1}\n", "{\"b\":2}\n", "{\"c\":3}\n"]).await;
+        assert_eq!(large, "HTTP/1.1 413 Payload Too Large");
+        let hec = post_chunked(12353, "/services/collector/event", vec![r#"{"event":"one"}"#, r#"{"event":"two"}"#]).await;
+        assert_eq!(hec, "HTTP/1.1 413 Payload Too Large");
+    }
+
+    #[test(tokio::test)]
+    async fn test_default_emitter_credentials_match_default_absorber() {
+        use clap::Parser as _;
+
+        use crate::{
+            absorber::Absorber,
+            config::{
+                EmitterConfig,
+                absorber::{AbsorberConfig, PartialAbsorberConfig},
+                cli::CliArgs,
+                emitter::PartialEmitterConfig,
+            },
+            transports::{
+                Transport as _,
+                http::{HttpOptions, HttpTransport},
+                tls::TlsOptions,
+            },
+        };
+
+        for (port, auth) in [(12354, "basic"), (12355, "token"), (12356, "splunk")] {
+            let listen = format!("http://127.0.0.1:{port}");
+            let args = CliArgs::parse_from(["protoglot", "absorber", "--listen", &listen, "--auth", auth]);
+            let config = AbsorberConfig::default().merge(PartialAbsorberConfig::from(args.command));
+            let absorber = Absorber::new(AbsorberConfig {
+                message_type: MessageType::NdJson,
+                ..config
+            });
+            tokio::spawn(async move {
+                absorber.run().await.unwrap();
+            });
+            // Wait for server to start
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+            let args = CliArgs::parse_from(["protoglot", "--message-type", "nd-json", "--http-auth", auth]);
+            let config = EmitterConfig::default().merge(PartialEmitterConfig::from(args));
+            let opts = HttpOptions::try_from(&config).unwrap();
+            let mut transport = HttpTransport::new("http", "127.0.0.1".to_string(), port, opts, &TlsOptions::default()).unwrap();
+            let sent = transport.send(b"{\"a\":1}\n").await;
+            assert!(sent.is_ok(), "{auth}: {sent:?}");
+        }
+    }
 }
@@ -0,0 +1,75 @@
+use memchr::memmem;
+
+use crate::generators::{TIMESTAMP_KEY, unix_micros};
+
+/// Pull the send time, in microseconds since the Unix epoch, out of an event tagged by a latency-measuring
+/// emitter, either as `protoglot_ts=<micros>` or as the JSON field `"protoglot_ts":<micros>`
+pub(super) fn parse_send_time(message: &[u8]) -> Option<u64> {
+    let pos = memmem::find(message, TIMESTAMP_KEY.as_bytes())?;
+    let rest = &message[pos + TIMESTAMP_KEY.len()..];
+    let rest = rest.strip_prefix(b"=").or_else(|| rest.strip_prefix(b"\":"))?;
+    let rest = rest.strip_prefix(b"\"").unwrap_or(rest);
+
+    let len = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+    std::str::from_utf8(&rest[..len]).ok()?.parse().ok()
+}
+
+/// Microseconds between the send time embedded in `message` and now
+pub(super) fn latency_micros(message: &[u8]) -> Option<u64> {
+    parse_send_time(message).map(|sent| unix_micros().saturating_sub(sent))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parses_text_and_json_markers() {
+        assert_eq!(
+            parse_send_time(b"<13>Jan 1 host app: hi protoglot_ts=1700000000123456\n"),
+            Some(1_700_000_000_123_456)
+        );
+        assert_eq!(
+            parse_send_time(br#"{"protoglot_ts":1700000000123456,"msg":"hi"}"#),
+            Some(1_700_000_000_123_456)
+        );
+        assert_eq!(
+            parse_send_time(br#"{"protoglot_ts":"1700000000123456"}"#),
+            Some(1_700_000_000_123_456)
+        );
+        assert_eq!(parse_send_time(b"no marker here"), None);
+        assert_eq!(parse_send_time(b"protoglot_ts=abc"), None);
+    }
+
+    #[test]
+    fn parses_markers_from_every_generator() {
+        use crate::generators::*;
+
+        let tags = || Tags {
+            sequence: Some(Sequence::new("feedbeef".to_string())),
+            timestamp: true,
+        };
+        let mut generators: Vec<Box<dyn EventGenerator>> = vec![
+            Box::new(Syslog3164EventGenerator::new().with_tags(tags())),
+            Box::new(Syslog5424EventGenerator::new(false).with_tags(tags())),
+            Box::new(Syslog5424EventGenerator::new(true).with_tags(tags())),
+            Box::new(NdJsonEventGenerator::new().with_tags(tags())),
+            Box::new(SplunkHecEventGenerator::new().with_tags(tags())),
+            Box::new(
+                TemplateEventGenerator::parse("app msg protoglot_seq={{stream}}:{{seq}} protoglot_ts={{timestamp:epoch_us}}")
+                    .unwrap()
+                    .with_tags(tags())
+                    .unwrap(),
+            ),
+        ];
+
+        for generator in &mut generators {
+            let mut buf = Vec::new();
+            generator.generate_into(&mut buf);
+            let latency = latency_micros(&buf);
+            assert!(latency.is_some_and(|l| l < 5_000_000), "{}", String::from_utf8_lossy(&buf));
+        }
+    }
+}
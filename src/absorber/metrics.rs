@@ -0,0 +1,316 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use tokio::time::Instant;
+
+use crate::metrics::{MetricType, MetricsWriter};
+
+/// Peers a listener keeps counters for before it forgets the idle ones
+const MAX_PEERS: usize = 10_000;
+
+/// Counters for one listener, updated on the receive path and read by the `/metrics` endpoint
+#[derive(Debug, Default)]
+pub(crate) struct ListenerMetrics {
+    events: AtomicU64,
+    raw_bytes: AtomicU64,
+    decomp_bytes: AtomicU64,
+    invalid_events: AtomicU64,
+    connections: AtomicU64,
+    active_connections: AtomicU64,
+    faults: AtomicU64,
+    /// Counters for each remote address that has sent to this listener
+    peers: Mutex<HashMap<IpAddr, Arc<PeerMetrics>>>,
+}
+
+/// Counters for one remote address on one listener
+#[derive(Debug, Default)]
+pub(crate) struct PeerMetrics {
+    events: AtomicU64,
+    bytes: AtomicU64,
+    invalid_events: AtomicU64,
+    connections: AtomicU64,
+    active_connections: AtomicU64,
+    /// Time spent connected by connections that have closed
+    closed_connections: AtomicU64,
+    closed_connection_micros: AtomicU64,
+}
+
+impl PeerMetrics {
+    pub(crate) fn record_events(&self, events: u64, bytes: u64) {
+        self.events.fetch_add(events, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_invalid(&self) {
+        self.invalid_events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn counts(&self) -> PeerCounts {
+        PeerCounts {
+            events: self.events.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            invalid_events: self.invalid_events.load(Ordering::Relaxed),
+            connections: self.connections.load(Ordering::Relaxed),
+            active_connections: self.active_connections.load(Ordering::Relaxed),
+            closed_connections: self.closed_connections.load(Ordering::Relaxed),
+            closed_connection_micros: self.closed_connection_micros.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A peer's counters at one point in time
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct PeerCounts {
+    pub(crate) events: u64,
+    pub(crate) bytes: u64,
+    pub(crate) invalid_events: u64,
+    pub(crate) connections: u64,
+    pub(crate) active_connections: u64,
+    pub(crate) closed_connections: u64,
+    pub(crate) closed_connection_micros: u64,
+}
+
+impl PeerCounts {
+    /// The counts added since `earlier`, keeping the current number of open connections. A peer that
+    /// was forgotten and came back starts over, so counts below `earlier` are all new.
+    pub(crate) fn since(&self, earlier: &Self) -> Self {
+        if self.events < earlier.events || self.connections < earlier.connections || self.bytes < earlier.bytes {
+            return *self;
+        }
+        Self {
+            events: self.events - earlier.events,
+            bytes: self.bytes - earlier.bytes,
+            invalid_events: self.invalid_events - earlier.invalid_events,
+            connections: self.connections - earlier.connections,
+            active_connections: self.active_connections,
+            closed_connections: self.closed_connections - earlier.closed_connections,
+            closed_connection_micros: self.closed_connection_micros - earlier.closed_connection_micros,
+        }
+    }
+}
+
+impl ListenerMetrics {
+    pub(crate) fn record_events(&self, events: u64, decomp_bytes: u64) {
+        self.events.fetch_add(events, Ordering::Relaxed);
+        self.decomp_bytes.fetch_add(decomp_bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_raw_bytes(&self, bytes: u64) {
+        self.raw_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_invalid(&self) {
+        self.invalid_events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_fault(&self) {
+        self.faults.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a new connection, from `peer` if known, which stays active until the guard is dropped
+    pub(crate) fn connection(self: &Arc<Self>, peer: Option<Arc<PeerMetrics>>) -> ConnectionGuard {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+        self.active_connections.fetch_add(1, Ordering::Relaxed);
+        if let Some(peer) = &peer {
+            peer.connections.fetch_add(1, Ordering::Relaxed);
+            peer.active_connections.fetch_add(1, Ordering::Relaxed);
+        }
+        ConnectionGuard {
+            listener: self.clone(),
+            peer,
+            opened: Instant::now(),
+        }
+    }
+
+    /// The counters for a remote address, created on its first use. Once there are too many peers,
+    /// the ones nothing holds on to any more are forgotten.
+    pub(crate) fn peer(&self, addr: IpAddr) -> Arc<PeerMetrics> {
+        let mut peers = self.peers.lock().expect("peer metrics lock poisoned");
+        if peers.len() >= MAX_PEERS && !peers.contains_key(&addr) {
+            peers.retain(|_, peer| Arc::strong_count(peer) > 1);
+        }
+        peers.entry(addr).or_default().clone()
+    }
+
+    pub(crate) fn peer_counts(&self) -> Vec<(IpAddr, PeerCounts)> {
+        let peers = self.peers.lock().expect("peer metrics lock poisoned");
+        peers.iter().map(|(addr, metrics)| (*addr, metrics.counts())).collect()
+    }
+
+    pub(crate) fn counts(&self) -> ListenerCounts {
+        ListenerCounts {
+            events: self.events.load(Ordering::Relaxed),
+            raw_bytes: self.raw_bytes.load(Ordering::Relaxed),
+            decomp_bytes: self.decomp_bytes.load(Ordering::Relaxed),
+            invalid_events: self.invalid_events.load(Ordering::Relaxed),
+            active_connections: self.active_connections.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A listener's counters at one point in time
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct ListenerCounts {
+    pub(crate) events: u64,
+    pub(crate) raw_bytes: u64,
+    pub(crate) decomp_bytes: u64,
+    pub(crate) invalid_events: u64,
+    pub(crate) active_connections: u64,
+}
+
+impl ListenerCounts {
+    /// The counts added since `earlier`, keeping the current number of open connections
+    pub(crate) fn since(&self, earlier: &Self) -> Self {
+        Self {
+            events: self.events - earlier.events,
+            raw_bytes: self.raw_bytes - earlier.raw_bytes,
+            decomp_bytes: self.decomp_bytes - earlier.decomp_bytes,
+            invalid_events: self.invalid_events - earlier.invalid_events,
+            active_connections: self.active_connections,
+        }
+    }
+}
+
+pub(crate) struct ConnectionGuard {
+    listener: Arc<ListenerMetrics>,
+    peer: Option<Arc<PeerMetrics>>,
+    opened: Instant,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.listener.active_connections.fetch_sub(1, Ordering::Relaxed);
+        if let Some(peer) = &self.peer {
+            peer.active_connections.fetch_sub(1, Ordering::Relaxed);
+            peer.closed_connections.fetch_add(1, Ordering::Relaxed);
+            let micros = self.opened.elapsed().as_micros() as u64;
+            peer.closed_connection_micros.fetch_add(micros, Ordering::Relaxed);
+        }
+    }
+}
+
+type Counter = fn(&ListenerMetrics) -> &AtomicU64;
+
+/// Render every listener's counters, labelled by listen address
+pub(crate) fn render(listeners: &[(Arc<str>, Arc<ListenerMetrics>)]) -> String {
+    let mut writer = MetricsWriter::default();
+    let families: [(&str, &str, MetricType, Counter); 7] = [
+        (
+            "protoglot_absorber_events_total",
+            "Valid events received.",
+            MetricType::Counter,
+            |m| &m.events,
+        ),
+        (
+            "protoglot_absorber_raw_bytes_total",
+            "Bytes received on the wire, before decompression.",
+            MetricType::Counter,
+            |m| &m.raw_bytes,
+        ),
+        (
+            "protoglot_absorber_decompressed_bytes_total",
+            "Bytes of valid events after decompression.",
+            MetricType::Counter,
+            |m| &m.decomp_bytes,
+        ),
+        (
+            "protoglot_absorber_invalid_events_total",
+            "Events that failed validation.",
+            MetricType::Counter,
+            |m| &m.invalid_events,
+        ),
+        (
+            "protoglot_absorber_connections_total",
+            "Connections accepted.",
+            MetricType::Counter,
+            |m| &m.connections,
+        ),
+        (
+            "protoglot_absorber_active_connections",
+            "Connections currently open.",
+            MetricType::Gauge,
+            |m| &m.active_connections,
+        ),
+        (
+            "protoglot_absorber_injected_faults_total",
+            "HTTP requests and TCP connections failed on purpose by fault injection.",
+            MetricType::Counter,
+            |m| &m.faults,
+        ),
+    ];
+    for (name, help, typ, counter) in families {
+        let labels: Vec<_> = listeners.iter().map(|(listener, _)| [("listener", listener.as_ref())]).collect();
+        let samples = listeners
+            .iter()
+            .zip(&labels)
+            .map(|((_, metrics), labels)| (&labels[..], counter(metrics).load(Ordering::Relaxed)));
+        writer.family(name, help, typ, samples);
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters_per_listener() {
+        let tcp = Arc::new(ListenerMetrics::default());
+        let udp = Arc::new(ListenerMetrics::default());
+        tcp.record_events(3, 300);
+        tcp.record_raw_bytes(120);
+        udp.record_invalid();
+        let open = tcp.connection(None);
+        drop(tcp.connection(None));
+
+        let text = render(&[("tcp://0.0.0.0:9514".into(), tcp), ("udp://0.0.0.0:9514".into(), udp)]);
+
+        for line in [
+            "protoglot_absorber_events_total{listener=\"tcp://0.0.0.0:9514\"} 3",
+            "protoglot_absorber_raw_bytes_total{listener=\"tcp://0.0.0.0:9514\"} 120",
+            "protoglot_absorber_decompressed_bytes_total{listener=\"tcp://0.0.0.0:9514\"} 300",
+            "protoglot_absorber_invalid_events_total{listener=\"udp://0.0.0.0:9514\"} 1",
+            "protoglot_absorber_connections_total{listener=\"tcp://0.0.0.0:9514\"} 2",
+            "protoglot_absorber_active_connections{listener=\"tcp://0.0.0.0:9514\"} 1",
+            "# TYPE protoglot_absorber_active_connections gauge",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {line:?} in\n{text}");
+        }
+        drop(open);
+    }
+
+    #[test]
+    fn forgets_idle_peers_when_full() {
+        let metrics = ListenerMetrics::default();
+        let held = metrics.peer(IpAddr::from([10, 0, 0, 1]));
+        held.record_events(1, 10);
+        for n in 1..MAX_PEERS as u32 {
+            metrics.peer(IpAddr::from(n.to_be_bytes())).record_events(1, 10);
+        }
+        assert_eq!(metrics.peer_counts().len(), MAX_PEERS);
+
+        metrics.peer(IpAddr::from([192, 168, 0, 1]));
+        let peers: Vec<_> = metrics.peer_counts().into_iter().map(|(addr, _)| addr).collect();
+        assert_eq!(peers.len(), 2);
+        assert!(peers.contains(&IpAddr::from([10, 0, 0, 1])));
+
+        // A forgotten peer that comes back is counted from scratch
+        let before = PeerCounts {
+            events: 5,
+            bytes: 50,
+            ..Default::default()
+        };
+        let after = PeerCounts {
+            events: 2,
+            bytes: 20,
+            ..Default::default()
+        };
+        assert_eq!(after.since(&before), after);
+    }
+}
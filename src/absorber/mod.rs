@@ -1,6 +1,15 @@
+mod assertion;
+mod capture;
 mod certs;
+mod faults;
+mod framing;
+mod hec;
 mod http;
+mod latency;
+mod metrics;
+mod sequence;
 mod stats_svc;
+mod syslog;
 mod tcp;
 mod udp;
 
@@ -8,23 +17,29 @@ mod udp;
 mod integration_tests;
 
 use std::{
+    net::SocketAddr,
     ops::Deref,
+    path::PathBuf,
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
 };
 
+use assertion::{Expectations, StopReason};
+use capture::CaptureSvc;
 use certs::get_cert;
+use faults::{HttpFaults, TcpFaults};
 use http::HttpAbsorber;
-use log::warn;
+use log::{error, info, warn};
+use metrics::{ConnectionGuard, ListenerMetrics, PeerMetrics};
 use stats_svc::StatsSvc;
 use tcp::TcpAbsorber;
 use tokio::io::{AsyncRead, ReadBuf};
 use udp::UdpAbsorber;
 
 use crate::config::{
-    ListenAddress, MessageType, Protocol,
-    absorber::{AbsorberConfig, HttpAuth},
+    ListenAddress, MessageType, Protocol, StatsFormat,
+    absorber::{AbsorberConfig, CertSource, HttpAuth, credentials_for},
 };
 
 #[derive(Clone)]
@@ -44,6 +59,11 @@ enum CertType {
     SelfSigned,
     PrivateCA,
     PublicCA,
+    /// A server certificate chain and key read from PEM files
+    Files {
+        cert: PathBuf,
+        key: PathBuf,
+    },
 }
 
 #[derive(Debug)]
@@ -52,37 +72,47 @@ pub struct ConnOptions {
     addr: ListenAddress,
     cert_type: CertType,
     protocol: Protocol,
+    message_type: MessageType,
     token: Option<String>,
     mtls: bool,
+    /// CA certs to verify mTLS clients against, instead of only the generated CA
+    client_ca: Option<PathBuf>,
+    hec_ack: bool,
+    http_faults: HttpFaults,
+    tcp_faults: TcpFaults,
 }
 
 impl From<&AbsorberConfig> for Vec<ConnOptions> {
     fn from(config: &AbsorberConfig) -> Self {
-        let http_version = match config.http2 {
-            true => hyper::Version::HTTP_2,
-            false => hyper::Version::HTTP_11,
-        };
-        let token = match config.auth {
-            HttpAuth::None => None,
-            _ => Some(config.token.clone()),
-        };
         config
             .listen_addresses
             .iter()
             .map(|addr| {
+                let options = &addr.options;
+                let http2 = options.http2.unwrap_or(config.http2);
+                let http_version = match http2 {
+                    true => hyper::Version::HTTP_2,
+                    false => hyper::Version::HTTP_11,
+                };
+                let auth = options.auth.as_ref().unwrap_or(&config.auth);
+                let token = match auth {
+                    HttpAuth::None => None,
+                    _ if options.auth.is_none() && options.token.is_none() => Some(config.token.clone()),
+                    _ => Some(credentials_for(auth, options.token.as_deref())),
+                };
+
                 let addr_tls = matches!(addr.protocol, Protocol::Https | Protocol::Tcps);
-                let use_tls = config.https || config.http2 || addr_tls;
-
-                let cert_type = if use_tls {
-                    if config.self_signed {
-                        CertType::SelfSigned
-                    } else if config.private_ca {
-                        CertType::PrivateCA
-                    } else {
-                        CertType::PublicCA
-                    }
-                } else {
-                    CertType::None
+                let use_tls = options.tls.unwrap_or(config.https || http2) || addr_tls;
+                let cert_files = config.cert.clone().zip(config.key.clone());
+                let cert_type = match (use_tls, &options.cert, cert_files) {
+                    (false, ..) => CertType::None,
+                    (true, Some(CertSource::SelfSigned), _) => CertType::SelfSigned,
+                    (true, Some(CertSource::PrivateCa), _) => CertType::PrivateCA,
+                    (true, Some(CertSource::PublicCa), _) => CertType::PublicCA,
+                    (true, None, Some((cert, key))) => CertType::Files { cert, key },
+                    (true, None, None) if config.self_signed => CertType::SelfSigned,
+                    (true, None, None) if config.private_ca => CertType::PrivateCA,
+                    (true, None, None) => CertType::PublicCA,
                 };
 
                 ConnOptions {
@@ -90,8 +120,13 @@ impl From<&AbsorberConfig> for Vec<ConnOptions> {
                     addr: addr.clone(),
                     cert_type,
                     protocol: addr.protocol.clone(),
-                    token: token.clone(),
-                    mtls: config.mtls,
+                    message_type: options.message_type.clone().unwrap_or_else(|| config.message_type.clone()),
+                    token,
+                    mtls: options.mtls.unwrap_or(config.mtls),
+                    client_ca: config.client_ca.clone(),
+                    hec_ack: config.hec_ack,
+                    http_faults: config.into(),
+                    tcp_faults: config.into(),
                 }
             })
             .collect()
@@ -99,19 +134,74 @@ impl From<&AbsorberConfig> for Vec<ConnOptions> {
 }
 
 impl AbsorberInner {
-    async fn build(opts: ConnOptions, message_type: MessageType) -> Self {
+    async fn build(opts: ConnOptions) -> Self {
         match opts.protocol {
-            Protocol::Tcp | Protocol::Tcps => TcpAbsorber::build(opts, message_type).await.into(),
-            Protocol::Udp => UdpAbsorber::build(opts, message_type).await.into(),
-            Protocol::Http | Protocol::Https => HttpAbsorber::build(opts, message_type).await.into(),
+            Protocol::Tcp | Protocol::Tcps => TcpAbsorber::build(opts).await.into(),
+            Protocol::Udp => UdpAbsorber::build(opts).await.into(),
+            Protocol::Http | Protocol::Https => HttpAbsorber::build(opts).await.into(),
         }
     }
 
-    async fn run(self, stats_svc: StatsSvc) -> anyhow::Result<()> {
+    async fn run(self, stats_svc: StatsSvc, source: Source) -> anyhow::Result<()> {
         match self {
-            Self::Tcp(absorber) => absorber.run(stats_svc).await,
-            Self::Udp(absorber) => absorber.run(stats_svc).await,
-            Self::Http(absorber) => absorber.run(stats_svc).await,
+            Self::Tcp(absorber) => absorber.run(stats_svc, source).await,
+            Self::Udp(absorber) => absorber.run(stats_svc, source).await,
+            Self::Http(absorber) => absorber.run(stats_svc, source).await,
+        }
+    }
+}
+
+/// Where received events came from, and where to capture them
+#[derive(Clone, Default)]
+pub(crate) struct Source {
+    listener: Arc<str>,
+    peer: Option<SocketAddr>,
+    capture: Option<CaptureSvc>,
+    metrics: Arc<ListenerMetrics>,
+    peer_metrics: Option<Arc<PeerMetrics>>,
+}
+
+impl Source {
+    fn new(addr: &ListenAddress, capture: Option<CaptureSvc>) -> Self {
+        Self {
+            listener: addr.to_string().into(),
+            peer: None,
+            capture,
+            metrics: Arc::default(),
+            peer_metrics: None,
+        }
+    }
+
+    fn with_peer(&self, peer: SocketAddr) -> Self {
+        Self {
+            peer: Some(peer),
+            peer_metrics: Some(self.metrics.peer(peer.ip())),
+            ..self.clone()
+        }
+    }
+
+    /// Count valid events for the listener and the peer they came from
+    fn record_events(&self, events: u64, decomp_bytes: u64) {
+        self.metrics.record_events(events, decomp_bytes);
+        if let Some(peer) = &self.peer_metrics {
+            peer.record_events(events, decomp_bytes);
+        }
+    }
+
+    fn record_invalid(&self) {
+        self.metrics.record_invalid();
+        if let Some(peer) = &self.peer_metrics {
+            peer.record_invalid();
+        }
+    }
+
+    fn connection(&self) -> ConnectionGuard {
+        self.metrics.connection(self.peer_metrics.clone())
+    }
+
+    fn capture(&self, message: &[u8], valid: bool) {
+        if let Some(capture) = &self.capture {
+            capture.capture(&self.listener, self.peer, message, valid);
         }
     }
 }
@@ -124,27 +214,106 @@ impl Absorber {
     pub async fn run(&self) -> tokio::io::Result<()> {
         let mut handles = vec![];
         let update_interval = self.config.update_interval;
-        let stats_svc = StatsSvc::run(update_interval);
+        let capture = match &self.config.capture {
+            Some(dir) => Some(CaptureSvc::run(dir, self.config.capture_format, self.config.capture_max_bytes)?),
+            None => None,
+        };
 
         let conn_opts: Vec<ConnOptions> = self.config.deref().into();
-        for conn_opt in conn_opts {
+        let sources: Vec<_> = conn_opts.iter().map(|opts| Source::new(&opts.addr, capture.clone())).collect();
+        let listeners: Vec<_> = sources.iter().map(|s| (s.listener.clone(), s.metrics.clone())).collect();
+        let stats_svc = StatsSvc::run(update_interval, self.config.stats_format, listeners.clone());
+        if let Some(addr) = self.config.metrics.clone() {
+            tokio::spawn(async move {
+                if let Err(e) = crate::metrics::serve(&addr, move || metrics::render(&listeners)).await {
+                    error!("Metrics endpoint on {addr} failed: {e}");
+                }
+            });
+        }
+
+        for (conn_opt, source) in conn_opts.into_iter().zip(sources) {
             let stats = stats_svc.clone();
-            let absorber = AbsorberInner::build(conn_opt, self.config.message_type.clone()).await;
-            let handle = tokio::spawn(async move { absorber.run(stats).await });
+            let absorber = AbsorberInner::build(conn_opt).await;
+            let handle = tokio::spawn(async move { absorber.run(stats, source).await });
             handles.push(handle);
         }
 
-        let input_handle = tokio::spawn(handle_user_input(stats_svc));
-        handles.push(input_handle);
+        let listeners = async {
+            for handle in handles {
+                handle.await?.unwrap();
+            }
+            tokio::io::Result::Ok(())
+        };
+        let expect = Expectations::from(self.config.deref());
+        let stop_reason = tokio::select! {
+            result = listeners => return result,
+            _ = handle_user_input(stats_svc.clone()) => StopReason::Interrupted,
+            _ = shutdown_signal() => StopReason::Interrupted,
+            reason = expect.watch(&stats_svc), if expect.is_active() => reason,
+        };
+
+        if self.config.stats_format == StatsFormat::Text {
+            println!("Exiting...");
+        }
+        let passed = self
+            .report_summary(&stats_svc, expect.is_active().then_some((&expect, stop_reason)))
+            .await?;
+        // stdin is read on a blocking thread, which would keep the runtime from shutting down
+        std::process::exit(if passed { 0 } else { 1 });
+    }
 
-        for handle in handles {
-            handle.await?.unwrap();
+    /// Print the summary, checking it against the expectations if given. Returns whether they were met.
+    async fn report_summary(&self, stats: &StatsSvc, expect: Option<(&Expectations, StopReason)>) -> tokio::io::Result<bool> {
+        let Some(mut summary) = stats.summary().await else {
+            return Ok(expect.is_none());
+        };
+        if let Some((expect, stop_reason)) = expect {
+            summary.assertion = Some(expect.check(&summary, stop_reason));
+        }
+        let passed = summary.assertion.as_ref().is_none_or(|assertion| assertion.passed);
+        let json = serde_json::to_string(&summary)?;
+        match self.config.stats_format {
+            StatsFormat::Text => println!("{summary}"),
+            StatsFormat::Json => println!("{json}"),
+        }
+        if let Some(path) = &self.config.summary_file {
+            std::fs::write(path, json + "\n")?;
+            info!("Wrote summary to {}", path.display());
+        }
+        Ok(passed)
+    }
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
         }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-        Ok(())
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
     }
 }
 
+/// Peers shown by `top` without a count
+const DEFAULT_TOP_PEERS: usize = 10;
+
+/// Handles interactive commands, returning when the user asks to quit. Keeps running if stdin is
+/// closed, as when started in the background.
 async fn handle_user_input(stats: StatsSvc) -> anyhow::Result<()> {
     loop {
         let stats = stats.clone();
@@ -170,35 +339,48 @@ async fn handle_user_input(stats: StatsSvc) -> anyhow::Result<()> {
                         stats.reset().await;
                         println!("Stats reset")
                     }
-                    "q" => {
-                        println!("Exiting...");
-                        std::process::exit(0);
-                    }
-                    _ => {}
+                    "q" => return Ok(()),
+                    "top" => stats.print_top_peers(DEFAULT_TOP_PEERS).await,
+                    _ => match trimmed.strip_prefix("top ").map(|n| n.trim().parse()) {
+                        Some(Ok(limit)) => stats.print_top_peers(limit).await,
+                        Some(Err(_)) => println!("Usage: top [N]"),
+                        None => {}
+                    },
                 }
             }
             None => break,
         }
     }
-    Ok(())
+    // Without stdin the absorber runs until it's signalled
+    std::future::pending().await
 }
 
-async fn process_message(message: &[u8], stats: &StatsSvc, message_type: &MessageType) {
+async fn process_message(message: &[u8], frame_len: usize, stats: &StatsSvc, message_type: &MessageType, source: &Source) {
     // Ignore whitespace-only messages (e.g., trailing newlines)
     if message.iter().all(|b| b.is_ascii_whitespace()) {
         return;
     }
 
     // Validate and process the message
-    if validate_message(message, message_type) {
-        let message_len = message.len();
-        stats.increment(1, 0, message_len).await;
-    } else {
+    let result = validate_message(message, message_type);
+    source.capture(message, result.is_ok());
+    if let Err(invalid) = result {
+        source.record_invalid();
+        stats.record_invalid(invalid.reason).await;
         warn!(
-            "Failed to validate message of type {:?}: {:?}",
+            "Invalid {:?} message ({invalid}): {:?}",
             message_type,
             String::from_utf8_lossy(message)
         );
+    } else {
+        source.record_events(1, frame_len as u64);
+        stats.increment(1, 0, frame_len).await;
+        if let Some((stream, seq)) = sequence::parse_sequence(message) {
+            stats.record_sequence(stream, seq).await;
+        }
+        if let Some(micros) = latency::latency_micros(message) {
+            stats.record_latency(micros).await;
+        }
     }
 }
 
@@ -219,58 +401,65 @@ pub(super) fn extract_message(buf: &mut Vec<u8>, fin: bool) -> Option<Vec<u8>> {
     }
 }
 
-fn human_events(events: f64) -> String {
-    if events < 1_000.0 {
-        events.to_string()
-    } else if events < 1_000_000.0 {
-        format!("{:.1}k", events / 1_000.0)
-    } else if events < 1_000_000_000.0 {
-        format!("{:.1}M", events / 1_000_000.0)
-    } else {
-        format!("{:.1}B", events / 1_000_000_000.0)
-    }
+/// Why a message failed validation
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Invalid {
+    /// Short, stable key that failures are counted by, e.g. `bad_timestamp`
+    pub(crate) reason: &'static str,
+    pub(crate) detail: String,
 }
 
-fn validate_syslog3164(message: &[u8]) -> bool {
-    // TODO: Implement full Syslog 3164 message validation
-    message.starts_with(b"<") && message.contains(&b'>')
+impl Invalid {
+    fn new(reason: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            reason,
+            detail: detail.into(),
+        }
+    }
 }
 
-fn validate_syslog5424(message: &[u8]) -> bool {
-    // TODO: Implement full Syslog 5424 message validation
-    let s = String::from_utf8_lossy(message);
-    s.starts_with("<") && s.contains(">1 ") && s.split_whitespace().count() >= 7
+impl std::fmt::Display for Invalid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.reason, self.detail)
+    }
 }
 
-fn validate_ndjson(message: &[u8]) -> bool {
-    serde_json::from_slice::<serde_json::Value>(message).is_ok()
+fn validate_ndjson(message: &[u8]) -> Result<(), Invalid> {
+    serde_json::from_slice::<serde_json::Value>(message)
+        .map(|_| ())
+        .map_err(|e| Invalid::new("invalid_json", e.to_string()))
 }
 
-fn validate_splunk_hec(message: &[u8]) -> bool {
-    let Ok(value) = serde_json::from_slice::<serde_json::Value>(message) else {
-        return false;
-    };
-    value.get("event").is_some()
+fn validate_splunk_hec(message: &[u8]) -> Result<(), Invalid> {
+    let value = serde_json::from_slice::<serde_json::Value>(message).map_err(|e| Invalid::new("invalid_json", e.to_string()))?;
+    match value.get("event") {
+        Some(_) => Ok(()),
+        None => Err(Invalid::new("missing_event", "HEC event has no \"event\" field")),
+    }
 }
 
-pub(super) fn validate_message(message: &[u8], typ: &MessageType) -> bool {
+pub(super) fn validate_message(message: &[u8], typ: &MessageType) -> Result<(), Invalid> {
     match typ {
-        MessageType::Syslog3164 => validate_syslog3164(message),
-        MessageType::Syslog5424 => validate_syslog5424(message),
-        MessageType::Syslog5424Octet => validate_syslog5424(message),
+        MessageType::Syslog3164 => syslog::validate_3164(message),
+        MessageType::Syslog5424 => syslog::validate_5424(message),
+        MessageType::Syslog5424Octet => syslog::validate_5424(message),
         MessageType::NdJson => validate_ndjson(message),
         MessageType::SplunkHec => validate_splunk_hec(message),
+        // Templates can render anything, so there is no shape to check beyond framing
+        MessageType::Template if message.is_empty() => Err(Invalid::new("empty", "message is empty")),
+        MessageType::Template => Ok(()),
     }
 }
 
 pub(super) struct CountingReader<R> {
     inner: R,
     stats: StatsSvc,
+    metrics: Arc<ListenerMetrics>,
 }
 
 impl<R> CountingReader<R> {
-    pub(super) fn new(inner: R, stats: StatsSvc) -> Self {
-        Self { inner, stats }
+    pub(super) fn new(inner: R, stats: StatsSvc, metrics: Arc<ListenerMetrics>) -> Self {
+        Self { inner, stats, metrics }
     }
 }
 
@@ -283,6 +472,7 @@ impl<R: AsyncRead + Unpin> AsyncRead for CountingReader<R> {
             let n = after - before;
             if n > 0 {
                 self.stats.try_increment(0, n, 0);
+                self.metrics.record_raw_bytes(n as u64);
             }
         }
         res
@@ -0,0 +1,250 @@
+use std::collections::{BTreeMap, HashMap};
+
+use memchr::memmem;
+use serde::Serialize;
+
+use crate::generators::SEQUENCE_KEY;
+
+/// Pull `(stream, seq)` out of an event tagged by a sequencing emitter, either as
+/// `protoglot_seq=<stream>:<seq>` or as the JSON field `"protoglot_seq":"<stream>:<seq>"`
+pub(super) fn parse_sequence(message: &[u8]) -> Option<(&str, u64)> {
+    let pos = memmem::find(message, SEQUENCE_KEY.as_bytes())?;
+    let rest = &message[pos + SEQUENCE_KEY.len()..];
+    let rest = rest.strip_prefix(b"=").or_else(|| rest.strip_prefix(b"\":\""))?;
+
+    let colon = memchr::memchr(b':', rest)?;
+    let stream = std::str::from_utf8(&rest[..colon]).ok().filter(|s| !s.is_empty())?;
+    let digits = &rest[colon + 1..];
+    let len = digits.iter().take_while(|b| b.is_ascii_digit()).count();
+    let seq = std::str::from_utf8(&digits[..len]).ok()?.parse().ok()?;
+    Some((stream, seq))
+}
+
+/// Delivery counts across all tracked streams
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SequenceSummary {
+    pub(crate) streams: usize,
+    pub(crate) received: u64,
+    /// Sequence numbers skipped over and not (yet) seen
+    pub(crate) missing: u64,
+    pub(crate) duplicates: u64,
+    /// Events that arrived after a later sequence number from the same stream
+    pub(crate) out_of_order: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct SequenceTracker {
+    streams: HashMap<String, StreamState>,
+    /// Where each stream had got to at the last reset, so events sent before it aren't counted as missing
+    resume_at: HashMap<String, u64>,
+}
+
+#[derive(Default)]
+struct StreamState {
+    next: u64,
+    received: u64,
+    duplicates: u64,
+    out_of_order: u64,
+    /// Unseen ranges below `next`, as start -> exclusive end
+    gaps: BTreeMap<u64, u64>,
+}
+
+impl SequenceTracker {
+    pub(crate) fn record(&mut self, stream: &str, seq: u64) {
+        match self.streams.get_mut(stream) {
+            Some(state) => state.record(seq),
+            None => {
+                let state = self.streams.entry(stream.to_string()).or_default();
+                state.next = self.resume_at.get(stream).copied().unwrap_or_default();
+                state.record(seq);
+            }
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Forget the counts so far, resuming each stream after the last sequence number seen
+    pub(crate) fn clear(&mut self) {
+        for (stream, state) in self.streams.drain() {
+            self.resume_at.insert(stream, state.next);
+        }
+    }
+
+    pub(crate) fn summary(&self) -> SequenceSummary {
+        self.streams.values().fold(
+            SequenceSummary {
+                streams: self.streams.len(),
+                ..Default::default()
+            },
+            |mut summary, state| {
+                summary.received += state.received;
+                summary.missing += state.gaps.iter().map(|(start, end)| end - start).sum::<u64>();
+                summary.duplicates += state.duplicates;
+                summary.out_of_order += state.out_of_order;
+                summary
+            },
+        )
+    }
+}
+
+impl StreamState {
+    fn record(&mut self, seq: u64) {
+        self.received += 1;
+        if seq >= self.next {
+            if seq > self.next {
+                self.gaps.insert(self.next, seq);
+            }
+            self.next = seq + 1;
+            return;
+        }
+
+        match self.gaps.range(..=seq).next_back().map(|(&start, &end)| (start, end)) {
+            Some((start, end)) if seq < end => {
+                self.gaps.remove(&start);
+                if start < seq {
+                    self.gaps.insert(start, seq);
+                }
+                if seq + 1 < end {
+                    self.gaps.insert(seq + 1, end);
+                }
+                self.out_of_order += 1;
+            }
+            _ => self.duplicates += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn track(seqs: &[u64]) -> SequenceSummary {
+        let mut tracker = SequenceTracker::default();
+        for &seq in seqs {
+            tracker.record("a", seq);
+        }
+        tracker.summary()
+    }
+
+    #[test]
+    fn parses_text_and_json_markers() {
+        assert_eq!(
+            parse_sequence(b"<13>Jan 1 host app: hi protoglot_seq=0a1b2c3d:42\n"),
+            Some(("0a1b2c3d", 42))
+        );
+        assert_eq!(
+            parse_sequence(br#"{"protoglot_seq":"0a1b2c3d:7","msg":"hi"}"#),
+            Some(("0a1b2c3d", 7))
+        );
+        assert_eq!(parse_sequence(b"no marker here"), None);
+        assert_eq!(parse_sequence(b"protoglot_seq=:5"), None);
+        assert_eq!(parse_sequence(b"protoglot_seq=abc:"), None);
+    }
+
+    #[test]
+    fn parses_markers_from_every_generator() {
+        use crate::generators::*;
+
+        let sequence = || Tags {
+            sequence: Some(Sequence::new("feedbeef".to_string())),
+            ..Default::default()
+        };
+        let mut generators: Vec<Box<dyn EventGenerator>> = vec![
+            Box::new(Syslog3164EventGenerator::new().with_tags(sequence())),
+            Box::new(Syslog5424EventGenerator::new(false).with_tags(sequence())),
+            Box::new(Syslog5424EventGenerator::new(true).with_tags(sequence())),
+            Box::new(NdJsonEventGenerator::new().with_tags(sequence())),
+            Box::new(SplunkHecEventGenerator::new().with_tags(sequence())),
+            Box::new(
+                TemplateEventGenerator::parse("{\"protoglot_seq\":\"{{stream}}:{{seq}}\"}")
+                    .unwrap()
+                    .with_tags(sequence())
+                    .unwrap(),
+            ),
+        ];
+
+        for generator in &mut generators {
+            for expected in 0..3 {
+                let mut buf = Vec::new();
+                generator.generate_into(&mut buf);
+                assert_eq!(
+                    parse_sequence(&buf),
+                    Some(("feedbeef", expected)),
+                    "{}",
+                    String::from_utf8_lossy(&buf)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn in_order_stream_has_no_anomalies() {
+        let summary = track(&[0, 1, 2, 3]);
+
+        assert_eq!(
+            summary,
+            SequenceSummary {
+                streams: 1,
+                received: 4,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn reports_gaps_duplicates_and_reordering() {
+        // 1 and 2 are skipped, 2 arrives late, 4 is sent twice, 6 never arrives
+        let summary = track(&[0, 3, 4, 2, 4, 5, 7]);
+
+        assert_eq!(summary.received, 7);
+        assert_eq!(summary.missing, 2);
+        assert_eq!(summary.out_of_order, 1);
+        assert_eq!(summary.duplicates, 1);
+    }
+
+    #[test]
+    fn late_arrival_splits_a_gap() {
+        let summary = track(&[10, 4, 4]);
+
+        // 0..10 was missing; 4 fills the middle, and the repeat is a duplicate
+        assert_eq!(summary.missing, 9);
+        assert_eq!(summary.out_of_order, 1);
+        assert_eq!(summary.duplicates, 1);
+    }
+
+    #[test]
+    fn resumes_streams_after_a_reset() {
+        let mut tracker = SequenceTracker::default();
+        (0..10).for_each(|seq| tracker.record("a", seq));
+        tracker.clear();
+        assert!(tracker.is_empty());
+        (10..20).for_each(|seq| tracker.record("a", seq));
+
+        assert_eq!(
+            tracker.summary(),
+            SequenceSummary {
+                streams: 1,
+                received: 10,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn tracks_streams_independently() {
+        let mut tracker = SequenceTracker::default();
+        tracker.record("a", 0);
+        tracker.record("b", 0);
+        tracker.record("a", 1);
+        tracker.record("b", 2);
+
+        let summary = tracker.summary();
+        assert_eq!(summary.streams, 2);
+        assert_eq!(summary.missing, 1);
+    }
+}
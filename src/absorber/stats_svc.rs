@@ -1,12 +1,27 @@
-use std::time::Duration;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+    sync::Arc,
+    time::Duration,
+};
 
 use human_bytes::human_bytes;
 use log::info;
-#[cfg(test)]
-use tokio::sync::oneshot;
-use tokio::{sync::mpsc, time::Instant};
+use serde::Serialize;
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
 
-use super::human_events;
+use super::{
+    assertion::AssertionResult,
+    metrics::{ListenerCounts, ListenerMetrics, PeerCounts},
+    sequence::{SequenceSummary, SequenceTracker},
+};
+use crate::{
+    config::StatsFormat,
+    stats::{LatencyHistogram, LatencySummary, human_events, human_latency},
+};
 
 pub(crate) struct AbsorberStats {
     pub(crate) total_events: usize,
@@ -16,6 +31,39 @@ pub(crate) struct AbsorberStats {
     pub(crate) total_decomp_bytes: usize,
     pub(crate) intv_decomp_bytes: usize,
     pub(crate) start_time: Instant,
+    /// Start of the run (or the last reset), for averages
+    pub(crate) run_start: Instant,
+    pub(crate) invalid_events: usize,
+    /// Validation failures by reason
+    pub(crate) invalid_reasons: BTreeMap<&'static str, usize>,
+    pub(crate) peak_eps: f64,
+    pub(crate) sequences: SequenceTracker,
+    pub(crate) total_latency: LatencyHistogram,
+    pub(crate) intv_latency: LatencyHistogram,
+    /// Each listener's counters, for the per-listener breakdown
+    pub(crate) listeners: Vec<ListenerStats>,
+}
+
+/// One listener's live counters, with snapshots at the last reset and the start of the interval
+pub(crate) struct ListenerStats {
+    name: Arc<str>,
+    metrics: Arc<ListenerMetrics>,
+    base: ListenerCounts,
+    last: ListenerCounts,
+    /// Each peer's counters at the last reset
+    peer_base: HashMap<IpAddr, PeerCounts>,
+}
+
+impl ListenerStats {
+    fn new(name: Arc<str>, metrics: Arc<ListenerMetrics>) -> Self {
+        Self {
+            name,
+            metrics,
+            base: ListenerCounts::default(),
+            last: ListenerCounts::default(),
+            peer_base: HashMap::new(),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -24,8 +72,12 @@ pub(crate) struct StatsSvc {
 }
 
 impl StatsSvc {
-    pub fn run(update_intv_millis: u64) -> Self {
+    pub fn run(update_intv_millis: u64, format: StatsFormat, listeners: Vec<(Arc<str>, Arc<ListenerMetrics>)>) -> Self {
         let mut stats = AbsorberStats::new();
+        stats.listeners = listeners
+            .into_iter()
+            .map(|(name, metrics)| ListenerStats::new(name, metrics))
+            .collect();
         let (tx, mut rx) = mpsc::channel(100);
 
         let mut interval = tokio::time::interval(Duration::from_millis(update_intv_millis));
@@ -48,6 +100,18 @@ impl StatsSvc {
                                         stats.total_decomp_bytes = 0;
                                         stats.intv_decomp_bytes = 0;
                                         stats.start_time = Instant::now();
+                                        stats.run_start = Instant::now();
+                                        stats.invalid_events = 0;
+                                        stats.invalid_reasons.clear();
+                                        stats.peak_eps = 0.0;
+                                        stats.sequences.clear();
+                                        stats.total_latency.clear();
+                                        stats.intv_latency.clear();
+                                        for listener in &mut stats.listeners {
+                                            listener.base = listener.metrics.counts();
+                                            listener.last = listener.base;
+                                            listener.peer_base = listener.metrics.peer_counts().into_iter().collect();
+                                        }
                                     },
                                     StatsMessage::Increment { events, raw_bytes, decomp_bytes } => {
                                         stats.total_events += events;
@@ -57,10 +121,39 @@ impl StatsSvc {
                                         stats.total_decomp_bytes += decomp_bytes;
                                         stats.intv_decomp_bytes += decomp_bytes;
                                     },
+                                    StatsMessage::Sequence { stream, seq } => {
+                                        stats.sequences.record(&stream, seq);
+                                    },
+                                    StatsMessage::Latency(micros) => {
+                                        stats.total_latency.record(micros);
+                                        stats.intv_latency.record(micros);
+                                    },
+                                    StatsMessage::Invalid(reason) => {
+                                        stats.invalid_events += 1;
+                                        *stats.invalid_reasons.entry(reason).or_default() += 1;
+                                    },
+                                    StatsMessage::Summary(tx) => {
+                                        let _ = tx.send(stats.summary());
+                                    },
+                                    StatsMessage::TopPeers(limit) => {
+                                        let table = stats.peer_table(limit);
+                                        match format {
+                                            StatsFormat::Text => println!("{table}"),
+                                            StatsFormat::Json => println!("{}", serde_json::to_string(&table).unwrap()),
+                                        }
+                                    },
                                     #[cfg(test)]
                                     StatsMessage::GetStats(tx) => {
                                         let _ = tx.send((stats.total_events, stats.total_raw_bytes, stats.total_decomp_bytes));
                                     }
+                                    #[cfg(test)]
+                                    StatsMessage::GetSequences(tx) => {
+                                        let _ = tx.send(stats.sequences.summary());
+                                    }
+                                    #[cfg(test)]
+                                    StatsMessage::GetLatency(tx) => {
+                                        let _ = tx.send(stats.total_latency.summary());
+                                    }
                                 }
                             }
                         }
@@ -68,29 +161,11 @@ impl StatsSvc {
                     }
 
                     _ = interval.tick() => {
-                        let elapsed = stats.start_time.elapsed().as_secs_f64();
                         if stats.intv_events > 0 {
-                            let events_per_sec = stats.intv_events as f64 / elapsed;
-                            let fmt_eps = human_events(events_per_sec);
-                            let raw_bytes_per_sec = stats.intv_raw_bytes as f64 / elapsed;
-                            let fmt_raw_bps = human_bytes(raw_bytes_per_sec);
-                            let decomp_bytes_per_sec = stats.intv_decomp_bytes as f64 / elapsed;
-                            let fmt_decomp_bps = human_bytes(decomp_bytes_per_sec);
-
-                            if stats.intv_raw_bytes != stats.intv_decomp_bytes {
-                                let ratio = stats.intv_decomp_bytes as f64 / stats.intv_raw_bytes as f64;
-                                let fmt_total_raw = human_bytes(stats.total_raw_bytes as f64);
-                                let fmt_total_decomp = human_bytes(stats.total_decomp_bytes as f64);
-                                println!(
-                                    "Total events: {}, Total raw: {}, Total decomp: {} | {} EPS, {}/s raw, {}/s decomp ({:.1}x ratio)",
-                                    stats.total_events, fmt_total_raw, fmt_total_decomp, fmt_eps, fmt_raw_bps, fmt_decomp_bps, ratio
-                                );
-                            } else {
-                                let fmt_total_bytes = human_bytes(stats.total_raw_bytes as f64);
-                                println!(
-                                    "Total events: {}, Total bytes: {} | {} EPS, {}/s average",
-                                    stats.total_events, fmt_total_bytes, fmt_eps, fmt_raw_bps
-                                );
+                            stats.peak_eps = stats.peak_eps.max(stats.interval_eps());
+                            match format {
+                                StatsFormat::Text => stats.print_interval(),
+                                StatsFormat::Json => println!("{}", serde_json::to_string(&stats.interval_report()).unwrap()),
                             }
                         }
                         // reset interval start time
@@ -98,6 +173,10 @@ impl StatsSvc {
                         stats.intv_raw_bytes = 0;
                         stats.intv_decomp_bytes = 0;
                         stats.intv_events = 0;
+                        stats.intv_latency.clear();
+                        for listener in &mut stats.listeners {
+                            listener.last = listener.metrics.counts();
+                        }
                     }
                 }
             }
@@ -125,6 +204,38 @@ impl StatsSvc {
         });
     }
 
+    /// Record an event carrying an emitter sequence number
+    pub async fn record_sequence(&self, stream: &str, seq: u64) {
+        let _ = self
+            .tx
+            .send(StatsMessage::Sequence {
+                stream: stream.to_string(),
+                seq,
+            })
+            .await;
+    }
+
+    /// Record the end-to-end latency of one event
+    pub async fn record_latency(&self, micros: u64) {
+        let _ = self.tx.send(StatsMessage::Latency(micros)).await;
+    }
+
+    /// Count an event that failed validation
+    pub async fn record_invalid(&self, reason: &'static str) {
+        let _ = self.tx.send(StatsMessage::Invalid(reason)).await;
+    }
+
+    pub async fn summary(&self) -> Option<StatsSummary> {
+        let (tx, rx) = oneshot::channel();
+        self.tx.send(StatsMessage::Summary(tx)).await.ok()?;
+        rx.await.ok()
+    }
+
+    /// Print the `limit` busiest peers across all listeners
+    pub async fn print_top_peers(&self, limit: usize) {
+        let _ = self.tx.send(StatsMessage::TopPeers(limit)).await;
+    }
+
     pub async fn reset(&self) {
         self.tx.send(StatsMessage::Reset).await.unwrap();
     }
@@ -135,6 +246,20 @@ impl StatsSvc {
         self.tx.send(StatsMessage::GetStats(tx)).await.unwrap();
         rx.await.unwrap()
     }
+
+    #[cfg(test)]
+    pub async fn get_sequences(&self) -> SequenceSummary {
+        let (tx, rx) = oneshot::channel();
+        self.tx.send(StatsMessage::GetSequences(tx)).await.unwrap();
+        rx.await.unwrap()
+    }
+
+    #[cfg(test)]
+    pub async fn get_latency(&self) -> LatencySummary {
+        let (tx, rx) = oneshot::channel();
+        self.tx.send(StatsMessage::GetLatency(tx)).await.unwrap();
+        rx.await.unwrap()
+    }
 }
 
 #[derive(Debug)]
@@ -144,9 +269,21 @@ enum StatsMessage {
         raw_bytes: usize,
         decomp_bytes: usize,
     },
+    Sequence {
+        stream: String,
+        seq: u64,
+    },
+    Latency(u64),
+    Invalid(&'static str),
+    Summary(oneshot::Sender<StatsSummary>),
+    TopPeers(usize),
     Reset,
     #[cfg(test)]
     GetStats(oneshot::Sender<(usize, usize, usize)>),
+    #[cfg(test)]
+    GetSequences(oneshot::Sender<SequenceSummary>),
+    #[cfg(test)]
+    GetLatency(oneshot::Sender<LatencySummary>),
 }
 
 impl AbsorberStats {
@@ -159,8 +296,462 @@ impl AbsorberStats {
             total_decomp_bytes: 0,
             intv_decomp_bytes: 0,
             start_time: Instant::now(),
+            run_start: Instant::now(),
+            invalid_events: 0,
+            invalid_reasons: BTreeMap::new(),
+            peak_eps: 0.0,
+            sequences: SequenceTracker::default(),
+            total_latency: LatencyHistogram::default(),
+            intv_latency: LatencyHistogram::default(),
+            listeners: Vec::new(),
+        }
+    }
+
+    fn interval_eps(&self) -> f64 {
+        self.intv_events as f64 / self.start_time.elapsed().as_secs_f64()
+    }
+
+    fn print_interval(&self) {
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        let fmt_eps = human_events(self.interval_eps());
+        let raw_bytes_per_sec = self.intv_raw_bytes as f64 / elapsed;
+        let fmt_raw_bps = human_bytes(raw_bytes_per_sec);
+        let decomp_bytes_per_sec = self.intv_decomp_bytes as f64 / elapsed;
+        let fmt_decomp_bps = human_bytes(decomp_bytes_per_sec);
+
+        if self.intv_raw_bytes != self.intv_decomp_bytes {
+            let ratio = self.intv_decomp_bytes as f64 / self.intv_raw_bytes as f64;
+            let fmt_total_raw = human_bytes(self.total_raw_bytes as f64);
+            let fmt_total_decomp = human_bytes(self.total_decomp_bytes as f64);
+            println!(
+                "Total events: {}, Total raw: {}, Total decomp: {} | {} EPS, {}/s raw, {}/s decomp ({:.1}x ratio)",
+                self.total_events, fmt_total_raw, fmt_total_decomp, fmt_eps, fmt_raw_bps, fmt_decomp_bps, ratio
+            );
+        } else {
+            let fmt_total_bytes = human_bytes(self.total_raw_bytes as f64);
+            println!(
+                "Total events: {}, Total bytes: {} | {} EPS, {}/s average",
+                self.total_events, fmt_total_bytes, fmt_eps, fmt_raw_bps
+            );
+        }
+        if !self.sequences.is_empty() {
+            let SequenceSummary {
+                streams,
+                received,
+                missing,
+                duplicates,
+                out_of_order,
+            } = self.sequences.summary();
+            println!(
+                "Sequenced streams: {}, received: {}, missing: {}, duplicates: {}, out of order: {}",
+                streams, received, missing, duplicates, out_of_order
+            );
+        }
+        if !self.intv_latency.is_empty() {
+            println!("{}", LatencyLine(self.intv_latency.summary()));
+        }
+        if self.listeners.len() > 1 {
+            for listener in self.listener_reports() {
+                println!("{listener}");
+            }
+        }
+    }
+
+    /// Each listener's totals since the last reset, with rates over the current interval
+    fn listener_reports(&self) -> Vec<ListenerReport> {
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        self.listeners
+            .iter()
+            .map(|listener| {
+                let now = listener.metrics.counts();
+                let interval = now.since(&listener.last);
+                let total = now.since(&listener.base);
+                ListenerReport {
+                    listener: listener.name.to_string(),
+                    total_events: total.events,
+                    total_raw_bytes: total.raw_bytes,
+                    invalid_events: total.invalid_events,
+                    active_connections: total.active_connections,
+                    interval_events: interval.events,
+                    eps: interval.events as f64 / elapsed,
+                    raw_bytes_per_sec: interval.raw_bytes as f64 / elapsed,
+                }
+            })
+            .collect()
+    }
+
+    /// The busiest peers since the last reset, by events
+    fn peer_table(&self, limit: usize) -> PeerTable {
+        let duration_secs = self.run_start.elapsed().as_secs_f64();
+        let mut peers: Vec<_> = self
+            .listeners
+            .iter()
+            .flat_map(|listener| {
+                listener.metrics.peer_counts().into_iter().filter_map(|(addr, now)| {
+                    let counts = match listener.peer_base.get(&addr) {
+                        Some(base) => now.since(base),
+                        None => now,
+                    };
+                    let active = counts.events + counts.invalid_events + counts.connections + counts.active_connections;
+                    (active > 0).then(|| (listener.name.to_string(), addr, counts))
+                })
+            })
+            .collect();
+        peers.sort_by(|(l1, a1, c1), (l2, a2, c2)| c2.events.cmp(&c1.events).then(l1.cmp(l2)).then(a1.cmp(a2)));
+        let total_events: u64 = peers.iter().map(|(_, _, counts)| counts.events).sum();
+        let total_peers = peers.len();
+        let peers = peers
+            .into_iter()
+            .take(limit)
+            .map(|(listener, addr, counts)| PeerReport {
+                listener,
+                peer: addr.to_string(),
+                events: counts.events,
+                share: match total_events {
+                    0 => 0.0,
+                    total => counts.events as f64 * 100.0 / total as f64,
+                },
+                bytes: counts.bytes,
+                eps: if duration_secs > 0.0 {
+                    counts.events as f64 / duration_secs
+                } else {
+                    0.0
+                },
+                invalid_events: counts.invalid_events,
+                connections: counts.connections,
+                active_connections: counts.active_connections,
+                average_connection_secs: match counts.closed_connections {
+                    0 => None,
+                    n => Some(counts.closed_connection_micros as f64 / n as f64 / 1_000_000.0),
+                },
+            })
+            .collect();
+        PeerTable {
+            kind: "peers",
+            timestamp: jiff::Timestamp::now().to_string(),
+            total_peers,
+            total_events,
+            peers,
+        }
+    }
+
+    /// Each listener's totals and averages since the last reset
+    fn listener_summaries(&self, duration_secs: f64) -> Vec<ListenerSummary> {
+        let per_sec = |n: u64| if duration_secs > 0.0 { n as f64 / duration_secs } else { 0.0 };
+        self.listeners
+            .iter()
+            .map(|listener| {
+                let total = listener.metrics.counts().since(&listener.base);
+                ListenerSummary {
+                    listener: listener.name.to_string(),
+                    total_events: total.events,
+                    total_raw_bytes: total.raw_bytes,
+                    total_decomp_bytes: total.decomp_bytes,
+                    invalid_events: total.invalid_events,
+                    average_eps: per_sec(total.events),
+                    average_raw_bytes_per_sec: per_sec(total.raw_bytes),
+                }
+            })
+            .collect()
+    }
+
+    fn interval_report(&self) -> IntervalReport {
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        IntervalReport {
+            kind: "interval",
+            timestamp: jiff::Timestamp::now().to_string(),
+            total_events: self.total_events,
+            total_raw_bytes: self.total_raw_bytes,
+            total_decomp_bytes: self.total_decomp_bytes,
+            invalid_events: self.invalid_events,
+            interval_events: self.intv_events,
+            eps: self.interval_eps(),
+            raw_bytes_per_sec: self.intv_raw_bytes as f64 / elapsed,
+            decomp_bytes_per_sec: self.intv_decomp_bytes as f64 / elapsed,
+            sequences: (!self.sequences.is_empty()).then(|| self.sequences.summary()),
+            latency_micros: (!self.intv_latency.is_empty()).then(|| self.intv_latency.summary()),
+            listeners: self.listener_reports(),
         }
     }
+
+    fn summary(&self) -> StatsSummary {
+        let duration_secs = self.run_start.elapsed().as_secs_f64();
+        let per_sec = |n: usize| if duration_secs > 0.0 { n as f64 / duration_secs } else { 0.0 };
+        StatsSummary {
+            kind: "summary",
+            duration_secs,
+            total_events: self.total_events,
+            total_raw_bytes: self.total_raw_bytes,
+            total_decomp_bytes: self.total_decomp_bytes,
+            invalid_events: self.invalid_events,
+            invalid_reasons: self.invalid_reasons.clone(),
+            average_eps: per_sec(self.total_events),
+            // A run shorter than one update interval never had its interval folded into the peak
+            peak_eps: match self.intv_events {
+                0 => self.peak_eps,
+                _ => self.peak_eps.max(self.interval_eps()),
+            },
+            average_raw_bytes_per_sec: per_sec(self.total_raw_bytes),
+            average_decomp_bytes_per_sec: per_sec(self.total_decomp_bytes),
+            average_event_bytes: match self.total_events {
+                0 => 0.0,
+                n => self.total_decomp_bytes as f64 / n as f64,
+            },
+            sequences: (!self.sequences.is_empty()).then(|| self.sequences.summary()),
+            latency_micros: (!self.total_latency.is_empty()).then(|| self.total_latency.summary()),
+            listeners: self.listener_summaries(duration_secs),
+            assertion: None,
+        }
+    }
+}
+
+/// One line of `--stats-format json` output, printed each interval that saw events
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IntervalReport {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    timestamp: String,
+    total_events: usize,
+    total_raw_bytes: usize,
+    total_decomp_bytes: usize,
+    invalid_events: usize,
+    interval_events: usize,
+    eps: f64,
+    raw_bytes_per_sec: f64,
+    decomp_bytes_per_sec: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequences: Option<SequenceSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_micros: Option<LatencySummary>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    listeners: Vec<ListenerReport>,
+}
+
+/// One listener's share of an interval
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ListenerReport {
+    listener: String,
+    total_events: u64,
+    total_raw_bytes: u64,
+    invalid_events: u64,
+    active_connections: u64,
+    interval_events: u64,
+    eps: f64,
+    raw_bytes_per_sec: f64,
+}
+
+impl std::fmt::Display for ListenerReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "  {}: {} events, {} | {} EPS, {}/s, {} invalid, {} open connections",
+            self.listener,
+            self.total_events,
+            human_bytes(self.total_raw_bytes as f64),
+            human_events(self.eps),
+            human_bytes(self.raw_bytes_per_sec),
+            self.invalid_events,
+            self.active_connections
+        )
+    }
+}
+
+/// One listener's totals for the whole run
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ListenerSummary {
+    pub(crate) listener: String,
+    pub(crate) total_events: u64,
+    pub(crate) total_raw_bytes: u64,
+    pub(crate) total_decomp_bytes: u64,
+    pub(crate) invalid_events: u64,
+    pub(crate) average_eps: f64,
+    pub(crate) average_raw_bytes_per_sec: f64,
+}
+
+/// The busiest peers, printed on the `top` command
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PeerTable {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    timestamp: String,
+    total_peers: usize,
+    total_events: u64,
+    peers: Vec<PeerReport>,
+}
+
+/// One remote address on one listener, since the last reset
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PeerReport {
+    listener: String,
+    peer: String,
+    events: u64,
+    /// Percentage of all events received from peers
+    share: f64,
+    /// Bytes of valid events, after decompression
+    bytes: u64,
+    eps: f64,
+    invalid_events: u64,
+    connections: u64,
+    active_connections: u64,
+    /// Average time connected, over the connections that have closed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    average_connection_secs: Option<f64>,
+}
+
+impl std::fmt::Display for PeerTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.peers.is_empty() {
+            return write!(f, "No peers yet");
+        }
+        let listener_width = self
+            .peers
+            .iter()
+            .map(|p| p.listener.len())
+            .chain(["Listener".len()])
+            .max()
+            .unwrap_or(0);
+        let peer_width = self.peers.iter().map(|p| p.peer.len()).chain(["Peer".len()]).max().unwrap_or(0);
+        writeln!(f, "Top {} of {} peers by events:", self.peers.len(), self.total_peers)?;
+        write!(
+            f,
+            "{:listener_width$}  {:peer_width$}  {:>10}  {:>6}  {:>10}  {:>8}  {:>7}  {:>5}  {:>4}  {:>8}",
+            "Listener", "Peer", "Events", "Share", "Bytes", "EPS", "Invalid", "Conns", "Open", "Avg conn"
+        )?;
+        for peer in &self.peers {
+            let average_connection = match peer.average_connection_secs {
+                Some(secs) => format!("{secs:.1}s"),
+                None => "-".to_string(),
+            };
+            write!(
+                f,
+                "\n{:listener_width$}  {:peer_width$}  {:>10}  {:>5.1}%  {:>10}  {:>8}  {:>7}  {:>5}  {:>4}  {:>8}",
+                peer.listener,
+                peer.peer,
+                peer.events,
+                peer.share,
+                human_bytes(peer.bytes as f64),
+                human_events(peer.eps.round()),
+                peer.invalid_events,
+                peer.connections,
+                peer.active_connections,
+                average_connection
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Totals for the whole run, reported when the absorber exits
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StatsSummary {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    pub(crate) duration_secs: f64,
+    pub(crate) total_events: usize,
+    pub(crate) total_raw_bytes: usize,
+    pub(crate) total_decomp_bytes: usize,
+    pub(crate) invalid_events: usize,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) invalid_reasons: BTreeMap<&'static str, usize>,
+    pub(crate) average_eps: f64,
+    pub(crate) peak_eps: f64,
+    pub(crate) average_raw_bytes_per_sec: f64,
+    pub(crate) average_decomp_bytes_per_sec: f64,
+    /// Average decompressed size of a valid event
+    pub(crate) average_event_bytes: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) sequences: Option<SequenceSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) latency_micros: Option<LatencySummary>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) listeners: Vec<ListenerSummary>,
+    /// Set when the absorber was checking expectations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) assertion: Option<AssertionResult>,
+}
+
+impl std::fmt::Display for StatsSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Summary: {} events, {} raw, {} decomp in {:.1}s",
+            self.total_events,
+            human_bytes(self.total_raw_bytes as f64),
+            human_bytes(self.total_decomp_bytes as f64),
+            self.duration_secs
+        )?;
+        writeln!(
+            f,
+            "Average: {} EPS, {}/s raw, {}/s decomp, {} per event | Peak: {} EPS",
+            human_events(self.average_eps),
+            human_bytes(self.average_raw_bytes_per_sec),
+            human_bytes(self.average_decomp_bytes_per_sec),
+            human_bytes(self.average_event_bytes),
+            human_events(self.peak_eps)
+        )?;
+        write!(f, "Invalid events: {}", self.invalid_events)?;
+        if !self.invalid_reasons.is_empty() {
+            let reasons: Vec<_> = self.invalid_reasons.iter().map(|(reason, n)| format!("{reason}: {n}")).collect();
+            write!(f, " ({})", reasons.join(", "))?;
+        }
+        if let Some(SequenceSummary {
+            streams,
+            received,
+            missing,
+            duplicates,
+            out_of_order,
+        }) = self.sequences
+        {
+            write!(
+                f,
+                "\nSequenced streams: {}, received: {}, missing: {}, duplicates: {}, out of order: {}",
+                streams, received, missing, duplicates, out_of_order
+            )?;
+        }
+        if let Some(latency) = self.latency_micros {
+            write!(f, "\n{}", LatencyLine(latency))?;
+        }
+        if self.listeners.len() > 1 {
+            for listener in &self.listeners {
+                write!(
+                    f,
+                    "\n  {}: {} events, {} raw | {} EPS, {}/s average, {} invalid",
+                    listener.listener,
+                    listener.total_events,
+                    human_bytes(listener.total_raw_bytes as f64),
+                    human_events(listener.average_eps),
+                    human_bytes(listener.average_raw_bytes_per_sec),
+                    listener.invalid_events
+                )?;
+            }
+        }
+        if let Some(assertion) = &self.assertion {
+            write!(f, "\n{assertion}")?;
+        }
+        Ok(())
+    }
+}
+
+struct LatencyLine(LatencySummary);
+
+impl std::fmt::Display for LatencyLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let LatencySummary { p50, p90, p99, max, .. } = self.0;
+        write!(
+            f,
+            "Latency p50: {}, p90: {}, p99: {}, max: {}",
+            human_latency(p50),
+            human_latency(p90),
+            human_latency(p99),
+            human_latency(max)
+        )
+    }
 }
 
 #[cfg(test)]
@@ -197,4 +788,152 @@ mod tests {
         assert_eq!(stats.total_raw_bytes, 150);
         assert_eq!(stats.total_decomp_bytes, 300);
     }
+
+    #[test]
+    fn summary_reports_totals_averages_and_peak() {
+        let mut stats = AbsorberStats::new();
+        stats.total_events = 4;
+        stats.total_raw_bytes = 100;
+        stats.total_decomp_bytes = 400;
+        stats.invalid_events = 2;
+        stats.invalid_reasons = BTreeMap::from([("bad_pri", 1), ("bad_timestamp", 1)]);
+        stats.peak_eps = 1234.5;
+        stats.total_latency.record(250);
+
+        let summary = stats.summary();
+        assert_eq!(summary.total_events, 4);
+        assert_eq!(summary.invalid_events, 2);
+        assert_eq!(summary.peak_eps, 1234.5);
+        assert_eq!(summary.average_event_bytes, 100.0);
+        assert!(summary.average_eps > 0.0);
+        assert!(summary.sequences.is_none());
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["type"], "summary");
+        assert_eq!(json["totalDecompBytes"], 400);
+        assert_eq!(json["latencyMicros"]["samples"], 1);
+        assert_eq!(json["invalidReasons"]["bad_timestamp"], 1);
+        assert!(json.get("sequences").is_none());
+        assert!(summary.to_string().contains("Invalid events: 2 (bad_pri: 1, bad_timestamp: 1)"));
+    }
+
+    #[test]
+    fn summary_peak_includes_a_run_shorter_than_one_interval() {
+        let mut stats = AbsorberStats::new();
+        stats.start_time = Instant::now() - Duration::from_secs(2);
+        stats.run_start = stats.start_time;
+        stats.total_events = 10;
+        stats.intv_events = 10;
+
+        let summary = stats.summary();
+        assert!((summary.average_eps - 5.0).abs() < 0.1);
+        assert!((summary.peak_eps - 5.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn interval_report_is_one_json_line() {
+        let mut stats = AbsorberStats::new();
+        stats.total_events = 10;
+        stats.intv_events = 10;
+        stats.sequences.record("a", 0);
+
+        let line = serde_json::to_string(&stats.interval_report()).unwrap();
+        assert!(!line.contains('\n'));
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["type"], "interval");
+        assert_eq!(json["intervalEvents"], 10);
+        assert_eq!(json["sequences"]["received"], 1);
+        assert!(json.get("latencyMicros").is_none());
+    }
+
+    #[test]
+    fn breaks_stats_down_per_listener() {
+        let (tcp, udp) = (Arc::new(ListenerMetrics::default()), Arc::new(ListenerMetrics::default()));
+        let mut stats = AbsorberStats::new();
+        stats.listeners = vec![
+            ListenerStats::new("tcp://0.0.0.0:9514".into(), tcp.clone()),
+            ListenerStats::new("udp://0.0.0.0:9514".into(), udp.clone()),
+        ];
+        tcp.record_events(30, 3000);
+        tcp.record_raw_bytes(3000);
+        udp.record_raw_bytes(500);
+        udp.record_invalid();
+        stats.listeners[0].last = tcp.counts();
+        tcp.record_events(10, 1000);
+
+        let json = serde_json::to_value(stats.interval_report()).unwrap();
+        assert_eq!(json["listeners"][0]["listener"], "tcp://0.0.0.0:9514");
+        assert_eq!(json["listeners"][0]["totalEvents"], 40);
+        assert_eq!(json["listeners"][0]["intervalEvents"], 10);
+        assert_eq!(json["listeners"][1]["invalidEvents"], 1);
+        assert_eq!(json["listeners"][1]["totalRawBytes"], 500);
+
+        // A reset starts each listener's totals again
+        for listener in &mut stats.listeners {
+            listener.base = listener.metrics.counts();
+        }
+        udp.record_events(5, 50);
+        let summary = stats.summary();
+        let totals: Vec<_> = summary.listeners.iter().map(|l| (l.listener.as_str(), l.total_events)).collect();
+        assert_eq!(totals, [("tcp://0.0.0.0:9514", 0), ("udp://0.0.0.0:9514", 5)]);
+        assert!(summary.to_string().contains("\n  udp://0.0.0.0:9514: 5 events, 0 B raw"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ranks_peers_by_events() {
+        let (tcp, udp) = (Arc::new(ListenerMetrics::default()), Arc::new(ListenerMetrics::default()));
+        let mut stats = AbsorberStats::new();
+        stats.listeners = vec![
+            ListenerStats::new("tcp://0.0.0.0:9514".into(), tcp.clone()),
+            ListenerStats::new("udp://0.0.0.0:9514".into(), udp.clone()),
+        ];
+        let (busy, quiet): (IpAddr, IpAddr) = ("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap());
+        for _ in 0..2 {
+            let connection = tcp.connection(Some(tcp.peer(busy)));
+            tcp.peer(busy).record_events(300, 30_000);
+            tokio::time::advance(Duration::from_secs(3)).await;
+            drop(connection);
+        }
+        let _open = tcp.connection(Some(tcp.peer(quiet)));
+        tcp.peer(quiet).record_events(100, 10_000);
+        udp.peer(quiet).record_events(200, 20_000);
+        udp.peer(quiet).record_invalid();
+
+        let table = stats.peer_table(2);
+        assert_eq!((table.total_peers, table.total_events), (3, 900));
+        let ranked: Vec<_> = table
+            .peers
+            .iter()
+            .map(|p| (p.listener.as_str(), p.peer.as_str(), p.events))
+            .collect();
+        assert_eq!(
+            ranked,
+            [("tcp://0.0.0.0:9514", "10.0.0.1", 600), ("udp://0.0.0.0:9514", "10.0.0.2", 200)]
+        );
+        let top = &table.peers[0];
+        assert_eq!(
+            (top.connections, top.active_connections, top.average_connection_secs),
+            (2, 0, Some(3.0))
+        );
+        assert_eq!(top.eps, 100.0);
+        assert!((top.share - 66.7).abs() < 0.1);
+        let lines: Vec<_> = table.to_string().lines().map(str::to_string).collect();
+        assert_eq!(lines[0], "Top 2 of 3 peers by events:");
+        assert!(
+            lines[2].ends_with("600   66.7%    58.6 KiB       100        0      2     0      3.0s"),
+            "{}",
+            lines[2]
+        );
+
+        // Peers only count what they sent after a reset
+        for listener in &mut stats.listeners {
+            listener.peer_base = listener.metrics.peer_counts().into_iter().collect();
+        }
+        let json = serde_json::to_value(stats.peer_table(10)).unwrap();
+        assert_eq!(json["type"], "peers");
+        assert_eq!(json["totalPeers"], 1);
+        assert_eq!(json["peers"][0]["peer"], "10.0.0.2");
+        assert_eq!(json["peers"][0]["activeConnections"], 1);
+        assert!(json["peers"][0].get("averageConnectionSecs").is_none());
+    }
 }
@@ -0,0 +1,501 @@
+use super::Invalid;
+
+/// RFC 3164 caps the whole packet at 1024 bytes
+const MAX_3164_LEN: usize = 1024;
+const MAX_TAG_LEN: usize = 32;
+const MAX_SD_NAME_LEN: usize = 32;
+const NILVALUE: &[u8] = b"-";
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+const MONTHS: [&[u8]; 12] = [
+    b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov", b"Dec",
+];
+/// Longest day of each month, allowing for leap years since RFC 3164 timestamps have no year
+const MONTH_DAYS: [u8; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// Consumes a message field by field
+struct Cursor<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn new(message: &'a [u8]) -> Self {
+        Self { rest: message }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.rest.first().copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.rest = &self.rest[1..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let (taken, rest) = self.rest.split_at_checked(len)?;
+        self.rest = rest;
+        Some(taken)
+    }
+
+    fn take_while(&mut self, pred: impl Fn(u8) -> bool) -> &'a [u8] {
+        let len = self.rest.iter().position(|&b| !pred(b)).unwrap_or(self.rest.len());
+        self.take(len).unwrap_or_default()
+    }
+
+    /// Exactly `len` ASCII digits
+    fn number(&mut self, len: usize) -> Option<u32> {
+        let digits = self.rest.get(..len)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        self.rest = &self.rest[len..];
+        Some(digits.iter().fold(0, |n, d| n * 10 + u32::from(d - b'0')))
+    }
+
+    /// Everything up to the next SP
+    fn field(&mut self) -> &'a [u8] {
+        self.take_while(|b| b != b' ')
+    }
+
+    fn space_after(&mut self, field: &str) -> Result<(), Invalid> {
+        match self.eat(b' ') {
+            true => Ok(()),
+            false => Err(Invalid::new("truncated", format!("expected SP after {field}"))),
+        }
+    }
+}
+
+fn lossy(bytes: &[u8]) -> std::borrow::Cow<'_, str> {
+    String::from_utf8_lossy(bytes)
+}
+
+fn is_print_us_ascii(b: u8) -> bool {
+    (33..=126).contains(&b)
+}
+
+/// The line ending belongs to the framing, not the message
+fn trim_line_end(message: &[u8]) -> &[u8] {
+    let message = message.strip_suffix(b"\n").unwrap_or(message);
+    message.strip_suffix(b"\r").unwrap_or(message)
+}
+
+/// `<PRIVAL>`, where PRIVAL is 0 to 191. RFC 3164 also forbids leading zeros.
+fn pri(cur: &mut Cursor, leading_zeros: bool) -> Result<(), Invalid> {
+    if !cur.eat(b'<') {
+        return Err(Invalid::new("bad_pri", "message doesn't start with '<'"));
+    }
+    let digits = cur.take_while(|b| b.is_ascii_digit());
+    if digits.is_empty() || digits.len() > 3 {
+        return Err(Invalid::new(
+            "bad_pri",
+            format!("PRIVAL must be 1 to 3 digits, got {:?}", lossy(digits)),
+        ));
+    }
+    if !cur.eat(b'>') {
+        return Err(Invalid::new("bad_pri", "PRIVAL isn't closed with '>'"));
+    }
+    if !leading_zeros && digits.len() > 1 && digits[0] == b'0' {
+        return Err(Invalid::new("bad_pri", format!("PRIVAL {} has a leading zero", lossy(digits))));
+    }
+    let prival: u32 = digits.iter().fold(0, |n, d| n * 10 + u32::from(d - b'0'));
+    if prival > 191 {
+        return Err(Invalid::new("pri_out_of_range", format!("PRIVAL {prival} is over 191")));
+    }
+    Ok(())
+}
+
+/// RFC 3164: `<PRI>Mmm dd hh:mm:ss HOSTNAME TAG...`
+pub(super) fn validate_3164(message: &[u8]) -> Result<(), Invalid> {
+    let message = trim_line_end(message);
+    if message.len() > MAX_3164_LEN {
+        return Err(Invalid::new(
+            "too_long",
+            format!("{} bytes is over the {MAX_3164_LEN} byte limit", message.len()),
+        ));
+    }
+    let mut cur = Cursor::new(message);
+    pri(&mut cur, false)?;
+    timestamp_3164(&mut cur)?;
+    cur.space_after("TIMESTAMP")?;
+
+    let hostname = cur.field();
+    if hostname.is_empty() {
+        return Err(Invalid::new("bad_hostname", "HOSTNAME is empty"));
+    }
+    if !hostname.iter().all(|&b| is_print_us_ascii(b)) {
+        return Err(Invalid::new(
+            "bad_hostname",
+            format!("HOSTNAME {:?} isn't printable ASCII", lossy(hostname)),
+        ));
+    }
+    cur.space_after("HOSTNAME")?;
+
+    // The TAG runs until the first non-alphanumeric character, which starts the CONTENT
+    let tag = cur.take_while(|b| b.is_ascii_alphanumeric());
+    if tag.len() > MAX_TAG_LEN {
+        return Err(Invalid::new(
+            "tag_too_long",
+            format!("TAG is {} characters, over the {MAX_TAG_LEN} limit", tag.len()),
+        ));
+    }
+    Ok(())
+}
+
+/// `Mmm dd hh:mm:ss`, with single-digit days padded by a space rather than a zero
+fn timestamp_3164(cur: &mut Cursor) -> Result<(), Invalid> {
+    let ts = cur.rest.get(..15).unwrap_or(cur.rest);
+    let bad = |detail: &str| Invalid::new("bad_timestamp", format!("{detail}: {:?}", lossy(ts)));
+
+    let month = cur.take(3).and_then(|m| MONTHS.iter().position(|&name| name == m));
+    let Some(month) = month else {
+        return Err(bad("expected a month abbreviation like 'Jan'"));
+    };
+    if !cur.eat(b' ') {
+        return Err(bad("expected SP after the month"));
+    }
+    let day = match cur.take(2) {
+        Some([b' ', d @ b'1'..=b'9']) => d - b'0',
+        Some([t @ b'1'..=b'3', d @ b'0'..=b'9']) => (t - b'0') * 10 + (d - b'0'),
+        _ => return Err(bad("day must be 10 to 31, or a space and 1 to 9")),
+    };
+    if day > MONTH_DAYS[month] {
+        return Err(bad("day is past the end of the month"));
+    }
+    if !cur.eat(b' ') {
+        return Err(bad("expected SP after the day"));
+    }
+    time_of_day(cur).ok_or_else(|| bad("expected hh:mm:ss"))
+}
+
+/// `hh:mm:ss`, without leap seconds
+fn time_of_day(cur: &mut Cursor) -> Option<()> {
+    let hour = cur.number(2)?;
+    cur.eat(b':').then_some(())?;
+    let minute = cur.number(2)?;
+    cur.eat(b':').then_some(())?;
+    let second = cur.number(2)?;
+    (hour < 24 && minute < 60 && second < 60).then_some(())
+}
+
+/// RFC 5424: `<PRI>VERSION SP TIMESTAMP SP HOSTNAME SP APP-NAME SP PROCID SP MSGID SP STRUCTURED-DATA [SP MSG]`
+pub(super) fn validate_5424(message: &[u8]) -> Result<(), Invalid> {
+    let mut cur = Cursor::new(trim_line_end(message));
+    pri(&mut cur, true)?;
+
+    let version = cur.take_while(|b| b.is_ascii_digit());
+    match version {
+        b"1" => {}
+        [] => return Err(Invalid::new("bad_version", "VERSION is missing")),
+        [b'0', ..] => return Err(Invalid::new("bad_version", format!("VERSION {} starts with 0", lossy(version)))),
+        _ if version.len() > 3 => return Err(Invalid::new("bad_version", format!("VERSION {} is too long", lossy(version)))),
+        _ => return Err(Invalid::new("unsupported_version", format!("VERSION {} isn't 1", lossy(version)))),
+    }
+    cur.space_after("VERSION")?;
+
+    timestamp_5424(cur.field())?;
+    cur.space_after("TIMESTAMP")?;
+    header_field(&mut cur, "HOSTNAME", "bad_hostname", 255)?;
+    header_field(&mut cur, "APP-NAME", "bad_app_name", 48)?;
+    header_field(&mut cur, "PROCID", "bad_procid", 128)?;
+    header_field(&mut cur, "MSGID", "bad_msgid", 32)?;
+
+    structured_data(&mut cur)?;
+    if cur.rest.is_empty() {
+        return Ok(());
+    }
+    if !cur.eat(b' ') {
+        return Err(Invalid::new(
+            "bad_structured_data",
+            "expected SP or end of message after STRUCTURED-DATA",
+        ));
+    }
+    // MSG is any octets, unless it starts with a BOM promising UTF-8
+    match cur.rest.strip_prefix(BOM) {
+        Some(text) if std::str::from_utf8(text).is_err() => Err(Invalid::new("bad_msg", "MSG starts with a BOM but isn't valid UTF-8")),
+        _ => Ok(()),
+    }
+}
+
+/// NILVALUE, or `max_len` printable ASCII characters at most, followed by SP
+fn header_field(cur: &mut Cursor, name: &str, reason: &'static str, max_len: usize) -> Result<(), Invalid> {
+    let value = cur.field();
+    if value.is_empty() {
+        return Err(Invalid::new(reason, format!("{name} is empty")));
+    }
+    if value.len() > max_len {
+        return Err(Invalid::new(
+            reason,
+            format!("{name} is {} characters, over the {max_len} limit", value.len()),
+        ));
+    }
+    if !value.iter().all(|&b| is_print_us_ascii(b)) {
+        return Err(Invalid::new(reason, format!("{name} {:?} isn't printable ASCII", lossy(value))));
+    }
+    cur.space_after(name)
+}
+
+/// NILVALUE, or an RFC 3339 timestamp with an upper case `T` and `Z`, at most microsecond
+/// precision and no leap seconds
+fn timestamp_5424(ts: &[u8]) -> Result<(), Invalid> {
+    if ts == NILVALUE {
+        return Ok(());
+    }
+    let bad = |detail: &str| Invalid::new("bad_timestamp", format!("{detail}: {:?}", lossy(ts)));
+    let mut cur = Cursor::new(ts);
+
+    let date = (|| {
+        let year = cur.number(4)?;
+        cur.eat(b'-').then_some(())?;
+        let month = cur.number(2)?;
+        cur.eat(b'-').then_some(())?;
+        let day = cur.number(2)?;
+        Some((year, month, day))
+    })();
+    let Some((year, month, day)) = date else {
+        return Err(bad("expected a YYYY-MM-DD date"));
+    };
+    if jiff::civil::Date::new(year as i16, month as i8, day as i8).is_err() {
+        return Err(bad("date doesn't exist"));
+    }
+    if !cur.eat(b'T') {
+        return Err(bad("expected 'T' between date and time"));
+    }
+    time_of_day(&mut cur).ok_or_else(|| bad("expected hh:mm:ss"))?;
+    if cur.eat(b'.') {
+        let fraction = cur.take_while(|b| b.is_ascii_digit());
+        if fraction.is_empty() || fraction.len() > 6 {
+            return Err(bad("fractional seconds must be 1 to 6 digits"));
+        }
+    }
+
+    let offset = match cur.peek() {
+        Some(b'Z') => {
+            cur.eat(b'Z');
+            Some(())
+        }
+        Some(b'+' | b'-') => {
+            cur.take(1);
+            (|| {
+                let hours = cur.number(2)?;
+                cur.eat(b':').then_some(())?;
+                let minutes = cur.number(2)?;
+                (hours < 24 && minutes < 60).then_some(())
+            })()
+        }
+        _ => None,
+    };
+    if offset.is_none() || !cur.rest.is_empty() {
+        return Err(bad("expected 'Z' or a +hh:mm offset to end the timestamp"));
+    }
+    Ok(())
+}
+
+/// NILVALUE, or one or more `[SD-ID *(SP PARAM-NAME="PARAM-VALUE")]` elements
+fn structured_data(cur: &mut Cursor) -> Result<(), Invalid> {
+    if cur.eat(b'-') {
+        return Ok(());
+    }
+    if cur.peek() != Some(b'[') {
+        return Err(Invalid::new("bad_structured_data", "expected '[' or '-' to start STRUCTURED-DATA"));
+    }
+    let mut ids = Vec::new();
+    while cur.eat(b'[') {
+        let id = sd_name(cur, "SD-ID")?;
+        sd_id(id)?;
+        if ids.contains(&id) {
+            return Err(Invalid::new(
+                "duplicate_sd_id",
+                format!("SD-ID {} appears more than once", lossy(id)),
+            ));
+        }
+        ids.push(id);
+        while !cur.eat(b']') {
+            if !cur.eat(b' ') {
+                return Err(Invalid::new(
+                    "bad_structured_data",
+                    format!("expected SP or ']' in SD-ELEMENT {}", lossy(id)),
+                ));
+            }
+            let name = sd_name(cur, "PARAM-NAME")?;
+            if !cur.eat(b'=') || !cur.eat(b'"') {
+                return Err(Invalid::new(
+                    "bad_structured_data",
+                    format!("expected =\" after PARAM-NAME {}", lossy(name)),
+                ));
+            }
+            param_value(cur)?;
+        }
+    }
+    Ok(())
+}
+
+/// 1 to 32 printable ASCII characters, except `=`, `]` and `"`
+fn sd_name<'a>(cur: &mut Cursor<'a>, kind: &str) -> Result<&'a [u8], Invalid> {
+    let name = cur.take_while(|b| is_print_us_ascii(b) && !matches!(b, b'=' | b']' | b'"'));
+    if name.is_empty() || name.len() > MAX_SD_NAME_LEN {
+        return Err(Invalid::new(
+            "bad_structured_data",
+            format!("{kind} must be 1 to {MAX_SD_NAME_LEN} characters, got {:?}", lossy(name)),
+        ));
+    }
+    Ok(name)
+}
+
+/// Names with an `@` must be `name@<private enterprise number>`, e.g. `exampleSDID@32473`
+fn sd_id(id: &[u8]) -> Result<(), Invalid> {
+    let Some(at) = id.iter().position(|&b| b == b'@') else {
+        return Ok(());
+    };
+    let (name, enterprise) = (&id[..at], &id[at + 1..]);
+    let enterprise_ok = !enterprise.is_empty()
+        && enterprise
+            .split(|&b| b == b'.')
+            .all(|part| !part.is_empty() && part.iter().all(u8::is_ascii_digit));
+    if name.is_empty() || !enterprise_ok {
+        return Err(Invalid::new(
+            "bad_structured_data",
+            format!("SD-ID {} isn't name@<enterprise number>", lossy(id)),
+        ));
+    }
+    Ok(())
+}
+
+/// UTF-8 up to the closing `"`, where `"`, `\` and `]` must be escaped with `\`
+fn param_value(cur: &mut Cursor) -> Result<(), Invalid> {
+    let rest = cur.rest;
+    let mut idx = 0;
+    loop {
+        match rest.get(idx) {
+            None => return Err(Invalid::new("bad_structured_data", "PARAM-VALUE isn't closed with '\"'")),
+            Some(b'"') => break,
+            Some(b']') => return Err(Invalid::new("bad_structured_data", "unescaped ']' in PARAM-VALUE")),
+            // Any other escaped character is kept as is, backslash included
+            Some(b'\\') if matches!(rest.get(idx + 1), Some(b'"' | b'\\' | b']')) => idx += 2,
+            Some(_) => idx += 1,
+        }
+    }
+    if std::str::from_utf8(&rest[..idx]).is_err() {
+        return Err(Invalid::new("bad_structured_data", "PARAM-VALUE isn't valid UTF-8"));
+    }
+    cur.rest = &rest[idx + 1..];
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::generators::{EventGenerator, Syslog3164EventGenerator, Syslog5424EventGenerator};
+
+    fn reason_3164(message: &str) -> Option<&'static str> {
+        validate_3164(message.as_bytes()).err().map(|invalid| invalid.reason)
+    }
+
+    fn reason_5424(message: &str) -> Option<&'static str> {
+        validate_5424(message.as_bytes()).err().map(|invalid| invalid.reason)
+    }
+
+    #[test]
+    fn accepts_generated_events() {
+        let mut rfc3164 = Syslog3164EventGenerator::new();
+        let mut rfc5424 = Syslog5424EventGenerator::new(false);
+        for _ in 0..50 {
+            let mut buf = Vec::new();
+            rfc3164.generate_into(&mut buf);
+            assert_eq!(validate_3164(&buf), Ok(()), "{}", lossy(&buf));
+            buf.clear();
+            rfc5424.generate_into(&mut buf);
+            assert_eq!(validate_5424(&buf), Ok(()), "{}", lossy(&buf));
+        }
+    }
+
+    #[test]
+    fn validates_rfc3164() {
+        for (message, reason) in [
+            ("<34>Oct 11 22:14:15 mymachine su: 'su root' failed for lonvick on /dev/pts/8", None),
+            ("<0>Oct  1 22:14:15 10.0.0.1 kernel: boot", None),
+            ("<13>Feb 29 00:00:00 host", Some("truncated")),
+            ("<13>Feb 29 00:00:00 host ", None),
+            ("13>Oct 11 22:14:15 host app: hi", Some("bad_pri")),
+            ("<034>Oct 11 22:14:15 host app: hi", Some("bad_pri")),
+            ("<192>Oct 11 22:14:15 host app: hi", Some("pri_out_of_range")),
+            ("<34>2022-10-11T22:14:15Z host app: hi", Some("bad_timestamp")),
+            ("<34>Oct 01 22:14:15 host app: hi", Some("bad_timestamp")),
+            ("<34>Apr 31 22:14:15 host app: hi", Some("bad_timestamp")),
+            ("<34>Oct 11 24:14:15 host app: hi", Some("bad_timestamp")),
+            ("<34>Oct 11 22:14:15  app: hi", Some("bad_hostname")),
+            (
+                "<34>Oct 11 22:14:15 host abcdefghijklmnopqrstuvwxyz0123456789: hi",
+                Some("tag_too_long"),
+            ),
+        ] {
+            assert_eq!(reason_3164(message), reason, "{message}");
+        }
+        let long = format!("<34>Oct 11 22:14:15 host app: {}", "x".repeat(1024));
+        assert_eq!(reason_3164(&long), Some("too_long"));
+    }
+
+    #[test]
+    fn validates_rfc5424_header() {
+        for (message, reason) in [
+            (
+                "<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - 'su root' failed",
+                None,
+            ),
+            (
+                "<165>1 2003-08-24T05:14:15.000003-07:00 192.0.2.1 myproc 8710 - - %% It's time",
+                None,
+            ),
+            ("<0>1 - - - - - -", None),
+            ("<34>1 - - - - - -\r\n", None),
+            ("<34> 2003-10-11T22:14:15Z host app - - -", Some("bad_version")),
+            ("<34>2 2003-10-11T22:14:15Z host app - - -", Some("unsupported_version")),
+            ("<200>1 2003-10-11T22:14:15Z host app - - -", Some("pri_out_of_range")),
+            ("<34>1 2003-10-11T22:14:15.0000003Z host app - - -", Some("bad_timestamp")),
+            ("<34>1 2003-10-11t22:14:15Z host app - - -", Some("bad_timestamp")),
+            ("<34>1 2003-02-30T22:14:15Z host app - - -", Some("bad_timestamp")),
+            ("<34>1 2003-10-11T23:59:60Z host app - - -", Some("bad_timestamp")),
+            ("<34>1 2003-10-11T22:14:15 host app - - -", Some("bad_timestamp")),
+            ("<34>1 2003-10-11T22:14:15Z host  - - -", Some("bad_app_name")),
+            ("<34>1 - - - - abcdefghijklmnopqrstuvwxyz0123456789 -", Some("bad_msgid")),
+            ("<34>1 - - - - -", Some("truncated")),
+        ] {
+            assert_eq!(reason_5424(message), reason, "{message}");
+        }
+    }
+
+    #[test]
+    fn validates_rfc5424_structured_data_and_msg() {
+        for (message, reason) in [
+            (
+                r#"<34>1 - - - - - [exampleSDID@32473 iut="3" eventSource="Application"][examplePriority@32473 class="high"]"#,
+                None,
+            ),
+            (r#"<34>1 - - - - - [meta escaped="a \"quoted\" \] \\ value" other="\x"] msg"#, None),
+            (r#"<34>1 - - - - - [a b="1"][a c="2"]"#, Some("duplicate_sd_id")),
+            (r#"<34>1 - - - - - [a b="x]"]"#, Some("bad_structured_data")),
+            (r#"<34>1 - - - - - [a b="open]"#, Some("bad_structured_data")),
+            (r#"<34>1 - - - - - [a b=1]"#, Some("bad_structured_data")),
+            (r#"<34>1 - - - - - [a  b="1"]"#, Some("bad_structured_data")),
+            (r#"<34>1 - - - - - [bad@ b="1"]"#, Some("bad_structured_data")),
+            (r#"<34>1 - - - - - [a b="1"]msg"#, Some("bad_structured_data")),
+            ("<34>1 - - - - - - msg", None),
+            ("<34>1 - - - - - msg", Some("bad_structured_data")),
+            ("<34>1 - - - - - -msg", Some("bad_structured_data")),
+        ] {
+            assert_eq!(reason_5424(message), reason, "{message}");
+        }
+
+        let mut message = b"<34>1 - - - - - - \xEF\xBB\xBFcaf\xC3\xA9".to_vec();
+        assert_eq!(validate_5424(&message), Ok(()));
+        message.truncate(message.len() - 1);
+        assert_eq!(validate_5424(&message).unwrap_err().reason, "bad_msg");
+        // Without a BOM, MSG can be any octets
+        assert_eq!(validate_5424(b"<34>1 - - - - - - caf\xC3"), Ok(()));
+    }
+}
@@ -1,15 +1,21 @@
 use std::sync::Arc;
 
 use async_compression::tokio::bufread::{GzipDecoder, Lz4Decoder, ZstdDecoder};
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
 use tokio::{
     io::{AsyncRead, AsyncReadExt as _, BufReader},
     net::TcpListener,
+    sync::Semaphore,
 };
 use tokio_rustls::TlsAcceptor;
 use tokio_snappy::SnappyIO;
 
-use super::{AbsorberInner, ConnOptions, CountingReader, StatsSvc, extract_message, get_cert};
+use super::{
+    AbsorberInner, ConnOptions, CountingReader, Source, StatsSvc,
+    faults::{InjectedReset, Reset as _},
+    framing::Framer,
+    get_cert,
+};
 use crate::{absorber::process_message, config::MessageType};
 
 pub struct TcpAbsorber {
@@ -18,68 +24,76 @@ pub struct TcpAbsorber {
 }
 
 impl TcpAbsorber {
-    pub async fn build(opts: ConnOptions, message_type: MessageType) -> Self {
-        Self { message_type, opts }
+    pub async fn build(opts: ConnOptions) -> Self {
+        Self {
+            message_type: opts.message_type.clone(),
+            opts,
+        }
     }
 
-    pub(super) async fn run(self, stats: StatsSvc) -> anyhow::Result<()> {
+    pub(super) async fn run(self, stats: StatsSvc, source: Source) -> anyhow::Result<()> {
         let ConnOptions { addr, cert_type, .. } = self.opts;
         let listener = TcpListener::bind((addr.host.as_str(), addr.port))
             .await
             .expect("Could not bind to TCP address & port");
 
-        let cert_key = get_cert(&cert_type, self.opts.mtls).await?;
+        let cert_key = get_cert(&cert_type, self.opts.mtls, self.opts.client_ca.as_deref()).await?;
         let acceptor = if let Some(cert_key) = cert_key {
-            let key = cert_key.key();
-            let builder = rustls::ServerConfig::builder();
-
-            let builder = if self.opts.mtls {
-                if let Some(roots) = cert_key.root_cert() {
-                    let mut store = rustls::RootCertStore::empty();
-                    for root in roots {
-                        store.add(root).expect("Failed to add root cert");
-                    }
-                    let verifier = rustls::server::WebPkiClientVerifier::builder(Arc::new(store))
-                        .build()
-                        .expect("Failed to build client verifier");
-                    builder.with_client_cert_verifier(verifier)
-                } else {
-                    panic!("mTLS enabled but no root cert available");
-                }
-            } else {
-                builder.with_no_client_auth()
-            };
-
-            let config = builder.with_single_cert(cert_key.cert(), key)?;
+            let config = cert_key.server_config(self.opts.mtls)?;
             Some(TlsAcceptor::from(Arc::new(config)))
         } else {
             None
         };
 
+        let faults = self.opts.tcp_faults;
+        let slots = faults.max_connections.map(|max| Arc::new(Semaphore::new(max)));
         loop {
             let message_type = self.message_type.clone();
-            let (socket, _) = listener.accept().await?;
+            let (socket, remote_addr) = listener.accept().await?;
+            let slot = match &slots {
+                Some(slots) => match slots.clone().try_acquire_owned() {
+                    Ok(slot) => Some(slot),
+                    Err(_) => {
+                        warn!("Refusing TCP connection from {remote_addr}: too many open connections");
+                        source.metrics.record_fault();
+                        socket.prepare_reset();
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            let socket = faults.wrap(socket);
             let stats = stats.clone();
             let acceptor = acceptor.clone();
+            let source = source.clone();
 
             tokio::spawn(async move {
-                let remote_addr = socket.peer_addr().unwrap();
+                let _slot = slot;
                 debug!("Accepted TCP connection from: {}", remote_addr);
+                let source = source.with_peer(remote_addr);
+                let _connection = source.connection();
 
-                if let Some(tls_acceptor) = acceptor {
+                let result = if let Some(tls_acceptor) = acceptor {
                     match tls_acceptor.accept(socket).await {
                         Ok(tls_stream) => {
                             info!("TLS handshake successful with {}", remote_addr);
-                            if let Err(e) = handle_tcp_connection(tls_stream, &stats, &message_type).await {
-                                eprintln!("Error handling TLS TCP connection: {}", e);
-                            }
+                            handle_tcp_connection(tls_stream, &stats, &message_type, &source).await
                         }
                         Err(err) => {
                             error!("TLS handshake failed with {}: {:?}", remote_addr, err);
+                            return;
                         }
                     }
-                } else if let Err(e) = handle_tcp_connection(socket, &stats, &message_type).await {
-                    eprintln!("Error handling plain TCP connection: {}", e);
+                } else {
+                    handle_tcp_connection(socket, &stats, &message_type, &source).await
+                };
+                match result {
+                    Err(e) if InjectedReset::is(&e) => {
+                        info!("{e} from {remote_addr}");
+                        source.metrics.record_fault();
+                    }
+                    Err(e) => eprintln!("Error handling TCP connection from {remote_addr}: {e}"),
+                    Ok(()) => {}
                 }
             });
         }
@@ -89,9 +103,10 @@ pub(crate) async fn handle_tcp_connection(
     socket: impl tokio::io::AsyncRead + Unpin + Send + 'static,
     stats: &StatsSvc,
     message_type: &MessageType,
+    source: &Source,
 ) -> tokio::io::Result<()> {
     use tokio::io::AsyncBufReadExt as _;
-    let counting_reader = CountingReader::new(socket, stats.clone());
+    let counting_reader = CountingReader::new(socket, stats.clone(), source.metrics.clone());
     let mut reader = BufReader::new(counting_reader);
 
     // Peek for magic bytes to detect compression
@@ -119,39 +134,42 @@ pub(crate) async fn handle_tcp_connection(
     if is_gzip {
         debug!("Detected gzipped stream, decompressing...");
         let decoder = GzipDecoder::new(reader);
-        process_decompressed_stream(decoder, stats, message_type).await?;
+        process_decompressed_stream(decoder, stats, message_type, source).await?;
     } else if is_zstd {
         debug!("Detected zstd stream, decompressing...");
         let decoder = ZstdDecoder::new(reader);
-        process_decompressed_stream(decoder, stats, message_type).await?;
+        process_decompressed_stream(decoder, stats, message_type, source).await?;
     } else if is_lz4 {
         debug!("Detected lz4 stream, decompressing...");
         let decoder = Lz4Decoder::new(reader);
-        process_decompressed_stream(decoder, stats, message_type).await?;
+        process_decompressed_stream(decoder, stats, message_type, source).await?;
     } else if is_snappy {
         debug!("Detected snappy stream, decompressing...");
         let decoder = SnappyIO::new(reader);
-        process_decompressed_stream(decoder, stats, message_type).await?;
+        process_decompressed_stream(decoder, stats, message_type, source).await?;
     } else {
+        let mut framer = Framer::new(message_type);
         let mut buf = Vec::new();
         loop {
             match reader.read_buf(&mut buf).await {
                 Ok(0) => break,
                 Ok(_) => {
-                    while let Some(message) = extract_message(&mut buf, false) {
-                        trace!("Received message: {:?}", String::from_utf8_lossy(&message));
-                        process_message(&message, stats, message_type).await;
+                    while let Some(frame) = framer.next_frame(&mut buf, false) {
+                        trace!("Received message: {:?}", String::from_utf8_lossy(&frame.message));
+                        process_message(&frame.message, frame.frame_len, stats, message_type, source).await;
                     }
                 }
                 Err(e) => {
-                    error!("Read error: {}", e);
+                    if !InjectedReset::is(&e) {
+                        error!("Read error: {}", e);
+                    }
                     return Err(e);
                 }
             }
         }
         // Final check for remaining messages in the buffer after reader EOF
-        if let Some(message) = extract_message(&mut buf, true) {
-            process_message(&message, stats, message_type).await;
+        while let Some(frame) = framer.next_frame(&mut buf, true) {
+            process_message(&frame.message, frame.frame_len, stats, message_type, source).await;
         }
     }
 
@@ -163,26 +181,30 @@ async fn process_decompressed_stream(
     mut decoder: impl AsyncRead + Unpin,
     stats: &StatsSvc,
     message_type: &MessageType,
+    source: &Source,
 ) -> tokio::io::Result<()> {
+    let mut framer = Framer::new(message_type);
     let mut buf = Vec::new();
     loop {
         match decoder.read_buf(&mut buf).await {
             Ok(0) => break,
             Ok(_) => {
-                while let Some(message) = extract_message(&mut buf, false) {
-                    trace!("Received decompressed message: {:?}", String::from_utf8_lossy(&message));
-                    process_message(&message, stats, message_type).await;
+                while let Some(frame) = framer.next_frame(&mut buf, false) {
+                    trace!("Received decompressed message: {:?}", String::from_utf8_lossy(&frame.message));
+                    process_message(&frame.message, frame.frame_len, stats, message_type, source).await;
                 }
             }
             Err(e) => {
-                error!("Decompression error: {}", e);
+                if !InjectedReset::is(&e) {
+                    error!("Decompression error: {}", e);
+                }
                 return Err(e);
             }
         }
     }
     // Final check for remaining messages in the buffer after decoder EOF
-    if let Some(message) = extract_message(&mut buf, true) {
-        process_message(&message, stats, message_type).await;
+    while let Some(frame) = framer.next_frame(&mut buf, true) {
+        process_message(&frame.message, frame.frame_len, stats, message_type, source).await;
     }
     Ok(())
 }
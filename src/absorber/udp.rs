@@ -1,30 +1,45 @@
+use std::{collections::HashMap, net::SocketAddr};
+
 use tokio::net::UdpSocket;
 
-use super::{AbsorberInner, ConnOptions, StatsSvc, process_message};
+use super::{AbsorberInner, ConnOptions, Source, StatsSvc, process_message};
 use crate::config::MessageType;
 
+/// Senders the UDP absorber keeps peer metrics handles for, clearing them all when a new one would go over
+const PEER_CACHE_SIZE: usize = 1024;
+
 pub struct UdpAbsorber {
     listener: UdpSocket,
     message_type: MessageType,
 }
 
 impl UdpAbsorber {
-    pub async fn build(opts: ConnOptions, message_type: MessageType) -> Self {
+    pub async fn build(opts: ConnOptions) -> Self {
         let address = opts.addr.host;
         let port = opts.addr.port;
         let listener = UdpSocket::bind((address.as_str(), port))
             .await
             .expect("Could not bind to UDP address & port.");
-        Self { listener, message_type }
+        Self {
+            listener,
+            message_type: opts.message_type,
+        }
     }
 
-    pub(super) async fn run(self, stats: StatsSvc) -> anyhow::Result<()> {
+    pub(super) async fn run(self, stats: StatsSvc, source: Source) -> anyhow::Result<()> {
         let mut buf = [0; 65535];
+        // Senders' peer metrics, so a datagram doesn't need the listener's peer map
+        let mut peers: HashMap<SocketAddr, Source> = HashMap::new();
         loop {
-            let (len, _) = self.listener.recv_from(&mut buf).await?;
+            let (len, peer) = self.listener.recv_from(&mut buf).await?;
             let message = &buf[..len];
             stats.increment(0, len, 0).await;
-            process_message(message, &stats.clone(), &self.message_type).await;
+            source.metrics.record_raw_bytes(len as u64);
+            if peers.len() >= PEER_CACHE_SIZE && !peers.contains_key(&peer) {
+                peers.clear();
+            }
+            let peer_source = peers.entry(peer).or_insert_with(|| source.with_peer(peer));
+            process_message(message, len, &stats.clone(), &self.message_type, peer_source).await;
         }
     }
 }
@@ -1,8 +1,10 @@
+use std::path::PathBuf;
+
 use clap::ValueEnum;
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
 
-use super::{FullConfig, ListenAddress, MessageType, cli::Commands};
+use super::{FullConfig, ListenAddress, MessageType, StatsFormat, cli::Commands, emitter::DEFAULT_HEC_TOKEN};
 
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
 #[serde(rename_all = "camelCase")]
@@ -13,10 +15,54 @@ pub enum HttpAuth {
     /// Basic HTTP authentication (base64 encoded username:password)
     Basic,
     /// Bearer token authentication (e.g., JWT)
+    #[value(alias = "bearer")]
+    #[serde(alias = "bearer")]
     Token,
+    /// Splunk HEC token authentication (`Authorization: Splunk <token>`)
+    Splunk,
+}
+
+/// Where a TLS listener's certificate comes from
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CertSource {
+    /// A publicly trusted certificate
+    PublicCa,
+    /// A bare self-signed certificate
+    SelfSigned,
+    /// A server certificate signed by a generated private CA
+    PrivateCa,
 }
 
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureFormat {
+    /// Events as received, one file per listener
+    #[default]
+    Raw,
+    /// One JSON object per event with the listener, peer address and receive time
+    Ndjson,
+}
+
+impl CaptureFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CaptureFormat::Raw => "log",
+            CaptureFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+impl std::fmt::Display for CaptureFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureFormat::Raw => "raw".fmt(f),
+            CaptureFormat::Ndjson => "ndjson".fmt(f),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AbsorberConfig {
     pub listen_addresses: Vec<ListenAddress>,
@@ -28,11 +74,83 @@ pub struct AbsorberConfig {
     pub self_signed: bool,
     pub private_ca: bool,
     pub mtls: bool,
+    /// PEM server certificate chain to serve instead of a generated or downloaded one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert: Option<PathBuf>,
+    /// PEM private key for `cert`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
+    /// PEM CA certs to verify mTLS client certificates against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_ca: Option<PathBuf>,
+    /// Issue indexer acknowledgement IDs for Splunk HEC requests
+    pub hec_ack: bool,
     pub auth: HttpAuth,
     pub token: String,
+    /// Directory to write received events to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture: Option<PathBuf>,
+    pub capture_format: CaptureFormat,
+    /// Start a new capture file once the current one reaches this size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture_max_bytes: Option<u64>,
+    pub stats_format: StatsFormat,
+    /// File to write the JSON summary to on exit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary_file: Option<PathBuf>,
+    /// Address to serve Prometheus metrics on, e.g. `127.0.0.1:9100`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<String>,
+    /// Stop once exactly this many valid events have arrived, and fail if the count is off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expect_events: Option<u64>,
+    /// Stop once this many decompressed bytes of valid events have arrived, and fail if the count is off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expect_bytes: Option<u64>,
+    /// Stop once no events have arrived for this many milliseconds, after the first one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<u64>,
+    /// Stop after this many milliseconds regardless
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Percentage of HTTP requests to answer with 503 Service Unavailable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_503: Option<f64>,
+    /// Percentage of HTTP requests to answer with 429 Too Many Requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_429: Option<f64>,
+    /// Percentage of HTTP requests to close the connection on, part way through the body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_disconnect: Option<f64>,
+    /// Seconds to send in `Retry-After` with injected 503 and 429 responses
+    pub retry_after: u64,
+    /// Milliseconds to hold back every HTTP response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_delay: Option<u64>,
+    /// Reject HTTP requests declaring a bigger body with 413 Payload Too Large
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<u64>,
+    /// Bytes per second to read from each TCP connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_rate: Option<u64>,
+    /// Stop reading TCP connections for `pause_for` milliseconds after every this many milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_every: Option<u64>,
+    /// Milliseconds to stop reading for, every `pause_every` milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_for: Option<u64>,
+    /// Reset TCP connections this many milliseconds after they're accepted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_after: Option<u64>,
+    /// Reset TCP connections once this many bytes have been read from them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_after_bytes: Option<u64>,
+    /// Refuse TCP connections past this many open at once, with a reset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<usize>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PartialAbsorberConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -53,8 +171,60 @@ pub struct PartialAbsorberConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mtls: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_ca: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hec_ack: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub auth: Option<HttpAuth>,
     pub token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture_format: Option<CaptureFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture_max_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats_format: Option<StatsFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expect_events: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expect_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_503: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_429: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_disconnect: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_delay: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_rate: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_every: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_for: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_after: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_after_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<usize>,
 }
 
 impl Default for AbsorberConfig {
@@ -68,8 +238,34 @@ impl Default for AbsorberConfig {
             self_signed: false,
             private_ca: false,
             mtls: false,
+            cert: None,
+            key: None,
+            client_ca: None,
+            hec_ack: false,
             auth: HttpAuth::None,
             token: String::new(),
+            capture: None,
+            capture_format: CaptureFormat::Raw,
+            capture_max_bytes: None,
+            stats_format: StatsFormat::Text,
+            summary_file: None,
+            metrics: None,
+            expect_events: None,
+            expect_bytes: None,
+            idle_timeout: None,
+            timeout: None,
+            fail_503: None,
+            fail_429: None,
+            fail_disconnect: None,
+            retry_after: 1,
+            response_delay: None,
+            max_body_bytes: None,
+            read_rate: None,
+            pause_every: None,
+            pause_for: None,
+            reset_after: None,
+            reset_after_bytes: None,
+            max_connections: None,
         }
     }
 }
@@ -85,8 +281,34 @@ impl AbsorberConfig {
             self_signed,
             private_ca,
             mtls,
+            cert,
+            key,
+            client_ca,
+            hec_ack,
             auth,
             token,
+            capture,
+            capture_format,
+            capture_max_bytes,
+            stats_format,
+            summary_file,
+            metrics,
+            expect_events,
+            expect_bytes,
+            idle_timeout,
+            timeout,
+            fail_503,
+            fail_429,
+            fail_disconnect,
+            retry_after,
+            response_delay,
+            max_body_bytes,
+            read_rate,
+            pause_every,
+            pause_for,
+            reset_after,
+            reset_after_bytes,
+            max_connections,
         } = other;
 
         if let Some(listen_addresses) = listen_addresses {
@@ -113,15 +335,113 @@ impl AbsorberConfig {
         if let Some(mtls) = mtls {
             self.mtls = mtls;
         }
+        if let Some(cert) = cert {
+            self.cert = Some(cert);
+        }
+        if let Some(key) = key {
+            self.key = Some(key);
+        }
+        if let Some(client_ca) = client_ca {
+            self.client_ca = Some(client_ca);
+        }
+        if let Some(hec_ack) = hec_ack {
+            self.hec_ack = hec_ack;
+        }
         if let Some(auth) = auth {
             self.auth = auth;
         }
 
         self.token = token;
 
+        if let Some(capture) = capture {
+            self.capture = Some(capture);
+        }
+        if let Some(capture_format) = capture_format {
+            self.capture_format = capture_format;
+        }
+        if let Some(capture_max_bytes) = capture_max_bytes {
+            self.capture_max_bytes = Some(capture_max_bytes);
+        }
+        if let Some(stats_format) = stats_format {
+            self.stats_format = stats_format;
+        }
+        if let Some(summary_file) = summary_file {
+            self.summary_file = Some(summary_file);
+        }
+        if let Some(metrics) = metrics {
+            self.metrics = Some(metrics);
+        }
+        if let Some(expect_events) = expect_events {
+            self.expect_events = Some(expect_events);
+        }
+        if let Some(expect_bytes) = expect_bytes {
+            self.expect_bytes = Some(expect_bytes);
+        }
+        if let Some(idle_timeout) = idle_timeout {
+            self.idle_timeout = Some(idle_timeout);
+        }
+        if let Some(timeout) = timeout {
+            self.timeout = Some(timeout);
+        }
+        if let Some(fail_503) = fail_503 {
+            self.fail_503 = Some(fail_503);
+        }
+        if let Some(fail_429) = fail_429 {
+            self.fail_429 = Some(fail_429);
+        }
+        if let Some(fail_disconnect) = fail_disconnect {
+            self.fail_disconnect = Some(fail_disconnect);
+        }
+        if let Some(retry_after) = retry_after {
+            self.retry_after = retry_after;
+        }
+        if let Some(response_delay) = response_delay {
+            self.response_delay = Some(response_delay);
+        }
+        if let Some(max_body_bytes) = max_body_bytes {
+            self.max_body_bytes = Some(max_body_bytes);
+        }
+        if let Some(read_rate) = read_rate {
+            self.read_rate = Some(read_rate);
+        }
+        if let Some(pause_every) = pause_every {
+            self.pause_every = Some(pause_every);
+        }
+        if let Some(pause_for) = pause_for {
+            self.pause_for = Some(pause_for);
+        }
+        if let Some(reset_after) = reset_after {
+            self.reset_after = Some(reset_after);
+        }
+        if let Some(reset_after_bytes) = reset_after_bytes {
+            self.reset_after_bytes = Some(reset_after_bytes);
+        }
+        if let Some(max_connections) = max_connections {
+            self.max_connections = Some(max_connections);
+        }
+
         self
     }
 
+    /// Check settings that only make sense together
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match (&self.cert, &self.key) {
+            (Some(_), None) => anyhow::bail!("--cert requires --key"),
+            (None, Some(_)) => anyhow::bail!("--key requires --cert"),
+            _ => {}
+        }
+        // Each request gets at most one fault, so the percentages share the same 100
+        let faults = [self.fail_503, self.fail_429, self.fail_disconnect].map(Option::unwrap_or_default);
+        if faults.iter().any(|pct| !(0.0..=100.0).contains(pct)) {
+            anyhow::bail!("--fail-503, --fail-429 and --fail-disconnect must each be from 0 to 100");
+        }
+        let total: f64 = faults.iter().sum();
+        if total > 100.0 {
+            anyhow::bail!("--fail-503, --fail-429 and --fail-disconnect add up to {total}%, over 100%");
+        }
+        Ok(())
+    }
+
     pub fn merge_from(self, other: Option<FullConfig>) -> Self {
         if let Some(full_config) = other
             && let Some(absorber_config) = full_config.absorber
@@ -132,9 +452,9 @@ impl AbsorberConfig {
     }
 }
 
-const USERNAME: &str = "AzureDiamond";
-const PASSWORD: &str = "hunter2";
-const TOKEN: &str = "this_is_a_fucking_token";
+pub const USERNAME: &str = "AzureDiamond";
+pub const PASSWORD: &str = "hunter2";
+pub const TOKEN: &str = "this_is_a_fucking_token";
 
 fn token_for(auth: &HttpAuth) -> String {
     use base64::prelude::*;
@@ -150,6 +470,23 @@ fn token_for(auth: &HttpAuth) -> String {
             info!("Using Token: {}", TOKEN);
             format!("Bearer {}", TOKEN)
         }
+        HttpAuth::Splunk => {
+            info!("Using Splunk HEC token: {}", DEFAULT_HEC_TOKEN);
+            format!("Splunk {}", DEFAULT_HEC_TOKEN)
+        }
+    }
+}
+
+/// The `Authorization` value a listener expects: the given secret (`user:password` for basic auth)
+/// under the auth scheme, or the default credentials without one
+pub(crate) fn credentials_for(auth: &HttpAuth, secret: Option<&str>) -> String {
+    use base64::prelude::*;
+    match (auth, secret) {
+        (HttpAuth::None, _) => String::new(),
+        (_, None) => token_for(auth),
+        (HttpAuth::Basic, Some(secret)) => format!("Basic {}", BASE64_STANDARD.encode(secret)),
+        (HttpAuth::Token, Some(secret)) => format!("Bearer {secret}"),
+        (HttpAuth::Splunk, Some(secret)) => format!("Splunk {secret}"),
     }
 }
 
@@ -164,7 +501,33 @@ impl From<Option<Commands>> for PartialAbsorberConfig {
             self_signed,
             private_ca,
             mtls,
+            cert,
+            key,
+            client_ca,
+            hec_ack,
             auth,
+            capture,
+            capture_format,
+            capture_max_bytes,
+            stats_format,
+            summary_file,
+            metrics,
+            expect_events,
+            expect_bytes,
+            idle_timeout,
+            timeout,
+            fail_503,
+            fail_429,
+            fail_disconnect,
+            retry_after,
+            response_delay,
+            max_body_bytes,
+            read_rate,
+            pause_every,
+            pause_for,
+            reset_after,
+            reset_after_bytes,
+            max_connections,
         }) = value
         {
             let listen_addresses = listen_addresses
@@ -185,8 +548,34 @@ impl From<Option<Commands>> for PartialAbsorberConfig {
                 self_signed,
                 private_ca,
                 mtls,
+                cert,
+                key,
+                client_ca,
+                hec_ack,
                 auth,
                 token: token_for(&auth_type),
+                capture,
+                capture_format,
+                capture_max_bytes,
+                stats_format,
+                summary_file,
+                metrics,
+                expect_events,
+                expect_bytes,
+                idle_timeout,
+                timeout,
+                fail_503,
+                fail_429,
+                fail_disconnect,
+                retry_after,
+                response_delay,
+                max_body_bytes,
+                read_rate,
+                pause_every,
+                pause_for,
+                reset_after,
+                reset_after_bytes,
+                max_connections,
             };
         }
         warn!("Tried to get a PartialAbsorberConfig from non-Absorber command: {:?}", value);
@@ -205,8 +594,34 @@ impl From<AbsorberConfig> for PartialAbsorberConfig {
             self_signed: Some(value.self_signed),
             private_ca: Some(value.private_ca),
             mtls: Some(value.mtls),
+            cert: value.cert,
+            key: value.key,
+            client_ca: value.client_ca,
+            hec_ack: Some(value.hec_ack),
             auth: Some(value.auth),
             token: value.token,
+            capture: value.capture,
+            capture_format: Some(value.capture_format),
+            capture_max_bytes: value.capture_max_bytes,
+            stats_format: Some(value.stats_format),
+            summary_file: value.summary_file,
+            metrics: value.metrics,
+            expect_events: value.expect_events,
+            expect_bytes: value.expect_bytes,
+            idle_timeout: value.idle_timeout,
+            timeout: value.timeout,
+            fail_503: value.fail_503,
+            fail_429: value.fail_429,
+            fail_disconnect: value.fail_disconnect,
+            retry_after: Some(value.retry_after),
+            response_delay: value.response_delay,
+            max_body_bytes: value.max_body_bytes,
+            read_rate: value.read_rate,
+            pause_every: value.pause_every,
+            pause_for: value.pause_for,
+            reset_after: value.reset_after,
+            reset_after_bytes: value.reset_after_bytes,
+            max_connections: value.max_connections,
         }
     }
 }
@@ -3,7 +3,11 @@ use std::path::PathBuf;
 use clap::{ArgAction, Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 
-use super::{MessageType, Profile, Protocol, absorber::HttpAuth};
+use super::{
+    Compression, HttpMethod, MessageType, Profile, Protocol, RateScope, ReconnectMode, StatsFormat,
+    absorber::{CaptureFormat, HttpAuth},
+};
+use crate::schedule::RateSchedule;
 
 #[derive(Clone, Debug, Deserialize, Parser, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -13,6 +17,11 @@ pub struct CliArgs {
     #[arg(short, long)]
     pub file: Option<PathBuf>,
 
+    /// Scenario file of named streams to run at once, each on top of the other emitter settings
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<PathBuf>,
+
     /// Built-in profile to use as a base before file and CLI overrides
     #[arg(long, value_enum)]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -28,8 +37,8 @@ pub struct CliArgs {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub port: Option<u16>,
 
-    /// Rate in events per second
-    #[arg(short, long)]
+    /// Rate in events per second, or 'unlimited' (or 0) to send as fast as possible
+    #[arg(short, long, value_parser = parse_rate)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rate: Option<u64>,
 
@@ -68,6 +77,28 @@ pub struct CliArgs {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cycle_delay: Option<u64>,
 
+    /// Stop after this long instead of after --cycles, e.g. 500ms, 30s, 5m or 1h. Plain numbers are milliseconds
+    #[arg(long, value_parser = parse_duration)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+
+    /// Vary the rate over the run instead of using --rate, e.g. ramp:1000:50000:10m, steps:30s=1000,1m=5000,
+    /// sine:500:5000:24h or poisson:1000:20000:1m:5s. A rate of 0 sends nothing, unlike --rate 0, so
+    /// constant:0 is rejected in favor of --rate unlimited
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_schedule: Option<RateSchedule>,
+
+    /// Pace on generated bytes instead of events, e.g. 50MB/s, 10MiB/s or 500GB/day. Replaces --rate
+    #[arg(long, value_parser = parse_bandwidth, conflicts_with = "rate_schedule")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<u64>,
+
+    /// Whether the rate is a budget shared by all emitters, or applies to each emitter separately
+    #[arg(long, value_enum)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_scope: Option<RateScope>,
+
     /// Splunk HEC token used for emitted HEC payloads
     #[arg(long = "hec-token")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -78,6 +109,122 @@ pub struct CliArgs {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hec_batch_size: Option<u64>,
 
+    /// Template file for --message-type template, one event template per line
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<PathBuf>,
+
+    /// Embed a stream ID and sequence number in every event, so an absorber can report loss, duplicates and reordering
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<bool>,
+
+    /// Stamp every event with its send time, so an absorber can report end-to-end latency. Requires synchronized clocks
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<bool>,
+
+    /// Compress emitted payloads. HTTP bodies get a Content-Encoding header; TCP streams are compressed end to end
+    #[arg(long, value_enum)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+
+    /// PEM file of CA certs to trust for TLS instead of the system roots
+    #[arg(long = "tls-ca-file")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_ca_file: Option<PathBuf>,
+
+    /// Skip TLS server certificate verification
+    #[arg(long = "tls-insecure", num_args = 0..=1, default_missing_value = "true")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_insecure: Option<bool>,
+
+    /// PEM client certificate to present for mTLS. Requires --tls-client-key
+    #[arg(long = "tls-client-cert", requires = "tls_client_key")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_cert: Option<PathBuf>,
+
+    /// PEM private key for the mTLS client certificate. Requires --tls-client-cert
+    #[arg(long = "tls-client-key", requires = "tls_client_cert")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_key: Option<PathBuf>,
+
+    /// Server name to send as SNI and verify the server cert against, if different from --host
+    #[arg(long = "tls-server-name")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_server_name: Option<String>,
+
+    /// Behavior when a TCP/TLS connection drops: fail, buffer until reconnected, or drop events.
+    /// Not available for UDP and HTTP emitters
+    #[arg(long, value_enum)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect: Option<ReconnectMode>,
+
+    /// Maximum reconnect attempts before giving up, 0 to retry forever
+    #[arg(long = "reconnect-attempts")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_attempts: Option<u64>,
+
+    /// Initial delay between reconnect attempts in milliseconds, doubled after each failure
+    #[arg(long = "reconnect-backoff")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_backoff: Option<u64>,
+
+    /// Maximum delay between reconnect attempts in milliseconds
+    #[arg(long = "reconnect-max-backoff")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_max_backoff: Option<u64>,
+
+    /// Bytes of events to hold while disconnected with --reconnect buffer, dropping the oldest beyond that
+    #[arg(long = "reconnect-buffer")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_buffer: Option<u64>,
+
+    /// URL path for HTTP emitters. Defaults to /services/collector/event for splunk-hec and / otherwise
+    #[arg(long = "http-path")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_path: Option<String>,
+
+    /// HTTP method for emitted requests
+    #[arg(long = "http-method", value_enum)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_method: Option<HttpMethod>,
+
+    /// Extra HTTP header as 'Name: value', can be specified multiple times
+    #[arg(long = "http-header", value_parser = parse_header)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_headers: Option<Vec<(String, String)>>,
+
+    /// Content-Type for emitted HTTP requests. Defaults to one matching the message type
+    #[arg(long = "http-content-type")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_content_type: Option<String>,
+
+    /// Auth scheme for HTTP emitters. Defaults to splunk for splunk-hec and none otherwise
+    #[arg(long = "http-auth", value_enum)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_auth: Option<HttpAuth>,
+
+    /// 'user:password' for basic auth, or the token for token/splunk auth
+    #[arg(long = "http-credentials")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_credentials: Option<String>,
+
+    /// Serve emitter Prometheus metrics at http://<addr>/metrics, e.g. 127.0.0.1:9101
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<String>,
+
+    /// Interval for emitter stats in milliseconds, 0 to only log totals at the end
+    #[arg(long = "update-interval")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_interval: Option<u64>,
+
+    /// Print interval stats as text, or as JSON lines
+    #[arg(long = "stats-format", value_enum)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats_format: Option<StatsFormat>,
+
     /// Control output verbosity
     #[arg(short, action = clap::ArgAction::Count)]
     pub verbose: u8,
@@ -86,6 +233,8 @@ pub struct CliArgs {
     pub command: Option<Commands>,
 }
 
+// Parsed once at startup, so the size of the absorber's flags doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, Deserialize, Serialize, Subcommand)]
 #[serde(rename_all = "camelCase")]
 pub enum Commands {
@@ -96,7 +245,9 @@ pub enum Commands {
         #[serde(skip_serializing_if = "Option::is_none")]
         update_interval: Option<u64>,
 
-        /// Listen addresses for absorber (format: host:port:protocol, can be specified multiple times)
+        /// Listen addresses for absorber (format: protocol://host:port, can be specified multiple times).
+        /// Add `?type=...&tls=...&cert=...&mtls=...&auth=...&token=...&http2=...` to override the
+        /// absorber-wide settings for one listener
         #[arg(long = "listen")]
         #[serde(skip_serializing_if = "Option::is_none")]
         listen_addresses: Option<Vec<String>>,
@@ -128,15 +279,146 @@ pub enum Commands {
         #[serde(skip_serializing_if = "Option::is_none")]
         private_ca: Option<bool>,
 
-        /// Require client certificates signed by the internal CA. Only valid when using --private-ca
+        /// Require client certificates signed by the internal CA, or by --client-ca if given
         #[arg(long = "mtls", action = ArgAction::SetTrue)]
         #[serde(skip_serializing_if = "Option::is_none")]
         mtls: Option<bool>,
 
+        /// PEM server certificate chain to serve for TLS listeners, instead of a generated or
+        /// downloaded one. Requires --key
+        #[arg(long, requires = "key", conflicts_with_all = ["self_signed", "private_ca"])]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cert: Option<PathBuf>,
+
+        /// PEM private key for --cert
+        #[arg(long, requires = "cert")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        key: Option<PathBuf>,
+
+        /// PEM CA certs to verify client certificates against with --mtls
+        #[arg(long = "client-ca")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        client_ca: Option<PathBuf>,
+
+        /// Issue indexer acknowledgement IDs for Splunk HEC requests, which then need a channel
+        #[arg(long = "hec-ack", action = ArgAction::SetTrue)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        hec_ack: Option<bool>,
+
         /// Auth mechanism for HTTP server
         #[arg(long, value_enum)]
         #[serde(skip_serializing_if = "Option::is_none")]
         auth: Option<HttpAuth>,
+
+        /// Write received events to files in this directory
+        #[arg(long)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        capture: Option<PathBuf>,
+
+        /// Capture events as received, or as NDJSON with listener, peer and receive time
+        #[arg(long = "capture-format", value_enum)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        capture_format: Option<CaptureFormat>,
+
+        /// Start a new capture file once the current one reaches this many bytes
+        #[arg(long = "capture-max-bytes")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        capture_max_bytes: Option<u64>,
+
+        /// Print interval stats and the exit summary as text, or as JSON lines
+        #[arg(long = "stats-format", value_enum)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stats_format: Option<StatsFormat>,
+
+        /// Also write the exit summary as JSON to this file
+        #[arg(long = "summary-file")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        summary_file: Option<PathBuf>,
+
+        /// Serve Prometheus metrics at http://<addr>/metrics, e.g. 127.0.0.1:9100
+        #[arg(long)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        metrics: Option<String>,
+
+        /// Stop once this many valid events have arrived, exiting non-zero if the final count differs
+        #[arg(long = "expect-events")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        expect_events: Option<u64>,
+
+        /// Stop once this many bytes of valid events have arrived, exiting non-zero if the final count differs
+        #[arg(long = "expect-bytes")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        expect_bytes: Option<u64>,
+
+        /// Stop once no events have arrived for this many milliseconds after the first, then check expectations
+        #[arg(long = "idle-timeout")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        idle_timeout: Option<u64>,
+
+        /// Stop after this many milliseconds regardless, then check expectations
+        #[arg(long)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timeout: Option<u64>,
+
+        /// Percentage of HTTP requests to answer with 503 Service Unavailable
+        #[arg(long = "fail-503", value_parser = parse_percent)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fail_503: Option<f64>,
+
+        /// Percentage of HTTP requests to answer with 429 Too Many Requests
+        #[arg(long = "fail-429", value_parser = parse_percent)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fail_429: Option<f64>,
+
+        /// Percentage of HTTP requests to close the connection on, part way through the body
+        #[arg(long = "fail-disconnect", value_parser = parse_percent)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fail_disconnect: Option<f64>,
+
+        /// Seconds to send in Retry-After with injected 503 and 429 responses [default: 1]
+        #[arg(long = "retry-after")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        retry_after: Option<u64>,
+
+        /// Milliseconds to hold back every HTTP response
+        #[arg(long = "response-delay")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        response_delay: Option<u64>,
+
+        /// Reject HTTP requests with a body over this many bytes with 413 Payload Too Large
+        #[arg(long = "max-body-bytes")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_body_bytes: Option<u64>,
+
+        /// Bytes per second to read from each TCP connection
+        #[arg(long = "read-rate")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        read_rate: Option<u64>,
+
+        /// Stop reading TCP connections for --pause-for milliseconds after every this many milliseconds
+        #[arg(long = "pause-every", requires = "pause_for")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pause_every: Option<u64>,
+
+        /// Milliseconds to stop reading TCP connections for, every --pause-every milliseconds
+        #[arg(long = "pause-for", requires = "pause_every")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pause_for: Option<u64>,
+
+        /// Reset TCP connections this many milliseconds after accepting them
+        #[arg(long = "reset-after")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reset_after: Option<u64>,
+
+        /// Reset TCP connections once this many bytes have been read from them
+        #[arg(long = "reset-after-bytes")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reset_after_bytes: Option<u64>,
+
+        /// Refuse TCP connections past this many open at once, with a reset
+        #[arg(long = "max-connections")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_connections: Option<usize>,
     },
 
     /// Write the default config to expected path, if one does not already exist
@@ -162,3 +444,83 @@ pub enum Commands {
         output: Option<PathBuf>,
     },
 }
+
+fn parse_rate(value: &str) -> Result<u64, String> {
+    match value {
+        "unlimited" => Ok(0),
+        _ => value
+            .parse()
+            .map_err(|_| format!("Invalid rate '{value}', expected events per second or 'unlimited'")),
+    }
+}
+
+/// Milliseconds from a number with an optional `ms`, `s`, `m` or `h` suffix
+pub(crate) fn parse_duration(value: &str) -> Result<u64, String> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let scale = match unit {
+        "" | "ms" => 1,
+        "s" => 1000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => 0,
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .filter(|_| scale > 0)
+        .and_then(|number| number.checked_mul(scale))
+        .ok_or_else(|| format!("Invalid duration '{value}', expected e.g. 500ms, 30s, 5m or 1h"))
+}
+
+/// Bytes per second from a size with an optional unit and period, e.g. `50MB/s`, `10MiB`, `500GB/day`.
+/// Units are case-sensitive: `B` is bytes and `b` is bits, so `100Mb/s` is 12.5MB/s.
+fn parse_bandwidth(value: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid bandwidth '{value}', expected e.g. 50MB/s, 10MiB/s, 100Mb/s or 500GB/day");
+    let (size, period) = value.split_once('/').unwrap_or((value, "s"));
+    let split = size.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let scale: f64 = match unit {
+        "" | "B" => 1.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        "b" => 1.0 / 8.0,
+        "kb" | "Kb" => 1e3 / 8.0,
+        "Mb" => 1e6 / 8.0,
+        "Gb" => 1e9 / 8.0,
+        "Tb" => 1e12 / 8.0,
+        _ => return Err(invalid()),
+    };
+    let seconds: f64 = match period {
+        "s" => 1.0,
+        "m" | "min" => 60.0,
+        "h" => 3600.0,
+        "d" | "day" => 86_400.0,
+        _ => return Err(invalid()),
+    };
+    match number.parse::<f64>() {
+        Ok(number) if number * scale / seconds >= 1.0 => Ok((number * scale / seconds).round() as u64),
+        Ok(_) => Err(format!("Bandwidth '{value}' is below 1 byte per second")),
+        Err(_) => Err(invalid()),
+    }
+}
+
+fn parse_percent(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(pct) if (0.0..=100.0).contains(&pct) => Ok(pct),
+        _ => Err(format!("Invalid percentage '{value}', expected a number from 0 to 100")),
+    }
+}
+
+fn parse_header(value: &str) -> Result<(String, String), String> {
+    let (name, value) = value
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid header '{value}', expected 'Name: value'"))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
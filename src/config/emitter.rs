@@ -1,9 +1,18 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
 use serde::{Deserialize, Serialize};
 
-use super::{FullConfig, MessageType, Profile, Protocol, cli::CliArgs};
+use super::{
+    Compression, FullConfig, HttpMethod, MessageType, Profile, Protocol, RateScope, ReconnectMode, StatsFormat, absorber::HttpAuth,
+    cli::CliArgs,
+};
+use crate::schedule::RateSchedule;
 
 pub const DEFAULT_HEC_TOKEN: &str = "protoglot-hec-token";
 pub const DEFAULT_HEC_BATCH_SIZE: u64 = 100;
+pub const DEFAULT_RECONNECT_BACKOFF: u64 = 100;
+pub const DEFAULT_RECONNECT_MAX_BACKOFF: u64 = 30_000;
+pub const DEFAULT_RECONNECT_BUFFER: u64 = 16 * 1024 * 1024;
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,8 +27,72 @@ pub struct EmitterConfig {
     pub events_per_cycle: u64,
     pub num_cycles: u64,
     pub cycle_delay: u64,
+    /// Stop after this many milliseconds, repeating cycles until then instead of stopping after `num_cycles`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    /// Vary the rate over the run, replacing `rate`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_schedule: Option<RateSchedule>,
+    /// Split the rate between all emitters, or give each emitter the full rate
+    pub rate_scope: RateScope,
+    /// Bytes per second to pace on instead of `rate`, counted before compression
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<u64>,
     pub hec_token: String,
     pub hec_batch_size: u64,
+    /// Template file for the `template` message type, one event template per line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<PathBuf>,
+    /// Embed a per-emitter stream ID and sequence number in every event
+    pub sequence: bool,
+    /// Stamp every event with its send time, so an absorber can report end-to-end latency
+    pub latency: bool,
+    /// Compress each HTTP body, or the whole TCP stream
+    pub compression: Compression,
+    /// PEM bundle of CAs to trust instead of the system roots
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_ca_file: Option<PathBuf>,
+    /// Skip server certificate verification entirely
+    pub tls_insecure: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_cert: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_key: Option<PathBuf>,
+    /// Name to send as SNI and verify the server cert against, instead of `host`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_server_name: Option<String>,
+    pub reconnect: ReconnectMode,
+    /// Reconnect attempts before giving up, 0 means retry forever
+    pub reconnect_attempts: u64,
+    /// Initial delay between reconnect attempts in milliseconds, doubled after each failure
+    pub reconnect_backoff: u64,
+    /// Upper bound for the reconnect delay in milliseconds
+    pub reconnect_max_backoff: u64,
+    /// Bytes of events `ReconnectMode::Buffer` holds while disconnected, before dropping the oldest
+    pub reconnect_buffer: u64,
+    /// URL path for HTTP emitters, defaulting to one suited to the message type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_path: Option<String>,
+    pub http_method: HttpMethod,
+    /// Extra headers sent with every HTTP request
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub http_headers: BTreeMap<String, String>,
+    /// Defaults to a content type matching the message type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_content_type: Option<String>,
+    /// Defaults to Splunk auth with `hec_token` for `splunk-hec`, and no auth otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_auth: Option<HttpAuth>,
+    /// `user:password` for Basic auth, or the token for Bearer/Splunk auth. Defaults to the
+    /// credentials the absorber expects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_credentials: Option<String>,
+    /// Address to serve Prometheus metrics on, e.g. `127.0.0.1:9101`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<String>,
+    /// Milliseconds between live stats lines, or 0 for none
+    pub update_interval: u64,
+    pub stats_format: StatsFormat,
 }
 
 impl Default for EmitterConfig {
@@ -35,8 +108,35 @@ impl Default for EmitterConfig {
             events_per_cycle: 10000,
             num_cycles: 1,
             cycle_delay: 10000,
+            duration: None,
+            rate_schedule: None,
+            rate_scope: RateScope::Global,
+            bandwidth: None,
             hec_token: DEFAULT_HEC_TOKEN.to_string(),
             hec_batch_size: DEFAULT_HEC_BATCH_SIZE,
+            template: None,
+            sequence: false,
+            latency: false,
+            compression: Compression::None,
+            tls_ca_file: None,
+            tls_insecure: false,
+            tls_client_cert: None,
+            tls_client_key: None,
+            tls_server_name: None,
+            reconnect: ReconnectMode::Off,
+            reconnect_attempts: 0,
+            reconnect_backoff: DEFAULT_RECONNECT_BACKOFF,
+            reconnect_max_backoff: DEFAULT_RECONNECT_MAX_BACKOFF,
+            reconnect_buffer: DEFAULT_RECONNECT_BUFFER,
+            http_path: None,
+            http_method: HttpMethod::Post,
+            http_headers: BTreeMap::new(),
+            http_content_type: None,
+            http_auth: None,
+            http_credentials: None,
+            metrics: None,
+            update_interval: 5000,
+            stats_format: StatsFormat::Text,
         }
     }
 }
@@ -73,15 +173,110 @@ impl EmitterConfig {
         if let Some(other) = other.cycle_delay {
             self.cycle_delay = other;
         }
+        if let Some(other) = other.duration {
+            self.duration = Some(other);
+        }
+        if let Some(other) = other.rate_schedule {
+            self.rate_schedule = Some(other);
+        }
+        if let Some(other) = other.rate_scope {
+            self.rate_scope = other;
+        }
+        if let Some(other) = other.bandwidth {
+            self.bandwidth = Some(other);
+        }
         if let Some(other) = other.hec_token {
             self.hec_token = other;
         }
         if let Some(other) = other.hec_batch_size {
             self.hec_batch_size = other;
         }
+        if let Some(other) = other.template {
+            self.template = Some(other);
+        }
+        if let Some(other) = other.sequence {
+            self.sequence = other;
+        }
+        if let Some(other) = other.latency {
+            self.latency = other;
+        }
+        if let Some(other) = other.compression {
+            self.compression = other;
+        }
+        if let Some(other) = other.tls_ca_file {
+            self.tls_ca_file = Some(other);
+        }
+        if let Some(other) = other.tls_insecure {
+            self.tls_insecure = other;
+        }
+        if let Some(other) = other.tls_client_cert {
+            self.tls_client_cert = Some(other);
+        }
+        if let Some(other) = other.tls_client_key {
+            self.tls_client_key = Some(other);
+        }
+        if let Some(other) = other.tls_server_name {
+            self.tls_server_name = Some(other);
+        }
+        if let Some(other) = other.reconnect {
+            self.reconnect = other;
+        }
+        if let Some(other) = other.reconnect_attempts {
+            self.reconnect_attempts = other;
+        }
+        if let Some(other) = other.reconnect_backoff {
+            self.reconnect_backoff = other;
+        }
+        if let Some(other) = other.reconnect_max_backoff {
+            self.reconnect_max_backoff = other;
+        }
+        if let Some(other) = other.reconnect_buffer {
+            self.reconnect_buffer = other;
+        }
+        if let Some(other) = other.http_path {
+            self.http_path = Some(other);
+        }
+        if let Some(other) = other.http_method {
+            self.http_method = other;
+        }
+        if let Some(other) = other.http_headers {
+            self.http_headers.extend(other);
+        }
+        if let Some(other) = other.http_content_type {
+            self.http_content_type = Some(other);
+        }
+        if let Some(other) = other.http_auth {
+            self.http_auth = Some(other);
+        }
+        if let Some(other) = other.http_credentials {
+            self.http_credentials = Some(other);
+        }
+        if let Some(other) = other.metrics {
+            self.metrics = Some(other);
+        }
+        if let Some(other) = other.update_interval {
+            self.update_interval = other;
+        }
+        if let Some(other) = other.stats_format {
+            self.stats_format = other;
+        }
         self
     }
 
+    /// Check settings that only make sense together
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.message_type == MessageType::Template && self.template.is_none() {
+            anyhow::bail!("--message-type template requires --template <file>");
+        }
+        if self.reconnect != ReconnectMode::Off && !matches!(self.protocol, Protocol::Tcp | Protocol::Tcps) {
+            anyhow::bail!("--reconnect only applies to TCP and TLS emitters, not {}", self.protocol);
+        }
+        if self.bandwidth.is_some() && self.rate_schedule.is_some() {
+            anyhow::bail!("--bandwidth cannot be combined with --rate-schedule");
+        }
+        Ok(())
+    }
+
     pub fn merge_from(self, other: Option<FullConfig>) -> Self {
         if let Some(full_config) = other
             && let Some(emitter_config) = full_config.emitter
@@ -164,8 +359,35 @@ impl From<CliArgs> for PartialEmitterConfig {
             events_per_cycle: value.events_per_cycle,
             num_cycles: value.num_cycles,
             cycle_delay: value.cycle_delay,
+            duration: value.duration,
+            rate_schedule: value.rate_schedule,
+            rate_scope: value.rate_scope,
+            bandwidth: value.bandwidth,
             hec_token: value.hec_token,
             hec_batch_size: value.hec_batch_size,
+            template: value.template,
+            sequence: value.sequence,
+            latency: value.latency,
+            compression: value.compression,
+            tls_ca_file: value.tls_ca_file,
+            tls_insecure: value.tls_insecure,
+            tls_client_cert: value.tls_client_cert,
+            tls_client_key: value.tls_client_key,
+            tls_server_name: value.tls_server_name,
+            reconnect: value.reconnect,
+            reconnect_attempts: value.reconnect_attempts,
+            reconnect_backoff: value.reconnect_backoff,
+            reconnect_max_backoff: value.reconnect_max_backoff,
+            reconnect_buffer: value.reconnect_buffer,
+            http_path: value.http_path,
+            http_method: value.http_method,
+            http_headers: value.http_headers.map(BTreeMap::from_iter),
+            http_content_type: value.http_content_type,
+            http_auth: value.http_auth,
+            http_credentials: value.http_credentials,
+            metrics: value.metrics,
+            update_interval: value.update_interval,
+            stats_format: value.stats_format,
         }
     }
 }
@@ -194,9 +416,63 @@ pub struct PartialEmitterConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cycle_delay: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_schedule: Option<RateSchedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_scope: Option<RateScope>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub hec_token: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hec_batch_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_ca_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_insecure: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_cert: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_key: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_server_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect: Option<ReconnectMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_attempts: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_backoff: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_max_backoff: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_buffer: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_method: Option<HttpMethod>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_headers: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_auth: Option<HttpAuth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_credentials: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_interval: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats_format: Option<StatsFormat>,
 }
 
 impl From<EmitterConfig> for PartialEmitterConfig {
@@ -212,8 +488,35 @@ impl From<EmitterConfig> for PartialEmitterConfig {
             events_per_cycle: Some(value.events_per_cycle),
             num_cycles: Some(value.num_cycles),
             cycle_delay: Some(value.cycle_delay),
+            duration: value.duration,
+            rate_schedule: value.rate_schedule,
+            rate_scope: Some(value.rate_scope),
+            bandwidth: value.bandwidth,
             hec_token: Some(value.hec_token),
             hec_batch_size: Some(value.hec_batch_size),
+            template: value.template,
+            sequence: Some(value.sequence),
+            latency: Some(value.latency),
+            compression: Some(value.compression),
+            tls_ca_file: value.tls_ca_file,
+            tls_insecure: Some(value.tls_insecure),
+            tls_client_cert: value.tls_client_cert,
+            tls_client_key: value.tls_client_key,
+            tls_server_name: value.tls_server_name,
+            reconnect: Some(value.reconnect),
+            reconnect_attempts: Some(value.reconnect_attempts),
+            reconnect_backoff: Some(value.reconnect_backoff),
+            reconnect_max_backoff: Some(value.reconnect_max_backoff),
+            reconnect_buffer: Some(value.reconnect_buffer),
+            http_path: value.http_path,
+            http_method: Some(value.http_method),
+            http_headers: Some(value.http_headers),
+            http_content_type: value.http_content_type,
+            http_auth: value.http_auth,
+            http_credentials: value.http_credentials,
+            metrics: value.metrics,
+            update_interval: Some(value.update_interval),
+            stats_format: Some(value.stats_format),
         }
     }
 }
@@ -1,28 +1,36 @@
 pub mod absorber;
 pub mod cli;
 pub mod emitter;
+pub mod scenario;
 mod types;
 
 use std::{
+    collections::BTreeMap,
     net::{SocketAddr, ToSocketAddrs},
     path::{Path, PathBuf},
     str::FromStr,
 };
 
-use absorber::{AbsorberConfig, PartialAbsorberConfig};
+use absorber::{AbsorberConfig, CertSource, HttpAuth, PartialAbsorberConfig};
+use clap::ValueEnum;
 use cli::{CliArgs, Commands};
 use directories::ProjectDirs;
 pub use emitter::EmitterConfig;
 use emitter::PartialEmitterConfig;
 use log::{debug, info, trace};
-use serde::{Deserialize, Serialize};
-pub use types::{MessageType, Profile, Protocol};
+use scenario::Scenario;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+pub use types::{Compression, HttpMethod, MessageType, Profile, Protocol, RateScope, ReconnectMode, StatsFormat};
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AppSettings {
     pub mode: AppMode,
+    /// Emitter settings, or with a scenario, the settings its streams share
     pub emitter: Option<EmitterConfig>,
+    /// Scenario streams by name, each run instead of `emitter`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub streams: BTreeMap<String, EmitterConfig>,
     pub absorber: Option<AbsorberConfig>,
 }
 
@@ -47,6 +55,61 @@ pub struct ListenAddress {
     pub host: String,
     pub port: u16,
     pub protocol: Protocol,
+    /// Settings for this listener only, over the absorber-wide ones
+    #[serde(flatten)]
+    pub options: ListenerOptions,
+}
+
+/// Per-listener overrides, given as a query on the listen address like `?type=syslog5424&tls=true`
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenerOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<MessageType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert: Option<CertSource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtls: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth: Option<HttpAuth>,
+    /// The token, or `user:password` for basic auth, instead of the built-in credentials
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http2: Option<bool>,
+}
+
+impl ListenerOptions {
+    fn parse_query(query: &str) -> anyhow::Result<Self> {
+        let mut options = Self::default();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid listener option '{pair}', expected KEY=VALUE"))?;
+            let flag = || {
+                value
+                    .parse::<bool>()
+                    .map_err(|_| anyhow::anyhow!("Invalid value '{value}' for listener option '{key}', expected true or false"))
+            };
+            match key {
+                "type" => options.message_type = Some(MessageType::try_from(value).or_else(|_| choice("message type", value))?),
+                "tls" => options.tls = Some(flag()?),
+                "cert" => options.cert = Some(choice("cert source", value)?),
+                "mtls" => options.mtls = Some(flag()?),
+                "auth" => options.auth = Some(choice("auth type", value)?),
+                "token" => options.token = Some(value.to_string()),
+                "http2" => options.http2 = Some(flag()?),
+                _ => anyhow::bail!("Unknown listener option '{key}', expected type, tls, cert, mtls, auth, token or http2"),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn choice<T: ValueEnum>(kind: &str, value: &str) -> anyhow::Result<T> {
+    T::from_str(value, true).map_err(|_| anyhow::anyhow!("Invalid {kind} '{value}'"))
 }
 
 impl ToSocketAddrs for ListenAddress {
@@ -63,6 +126,17 @@ impl Default for ListenAddress {
             host: "0.0.0.0".to_string(),
             port: 4242,
             protocol: Protocol::Tcp,
+            options: ListenerOptions::default(),
+        }
+    }
+}
+
+impl std::fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "{}://[{}]:{}", self.protocol, self.host, self.port)
+        } else {
+            write!(f, "{}://{}:{}", self.protocol, self.host, self.port)
         }
     }
 }
@@ -71,7 +145,8 @@ impl TryFrom<&str> for ListenAddress {
     type Error = anyhow::Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        // ex: "tcp://127.0.0.1:4242"
+        // ex: "tcp://127.0.0.1:4242" or "https://0.0.0.0:8088?type=splunk-hec&auth=token"
+        let (value, query) = value.split_once('?').unwrap_or((value, ""));
         let parts: Vec<&str> = value.split("://").collect();
         if parts.len() != 2 {
             return Err(anyhow::anyhow!("Invalid listen address format"));
@@ -84,7 +159,14 @@ impl TryFrom<&str> for ListenAddress {
         let host = host_part.trim_start_matches('[').trim_end_matches(']').to_string();
         let port = port_part.parse()?;
 
-        Ok(Self { host, port, protocol })
+        let options = ListenerOptions::parse_query(query)?;
+
+        Ok(Self {
+            host,
+            port,
+            protocol,
+            options,
+        })
     }
 }
 
@@ -97,6 +179,7 @@ impl AppSettings {
                 trace!("Starting absorber");
                 Self {
                     emitter: None,
+                    streams: BTreeMap::new(),
                     absorber: Self::load_absorber_config(args)?.absorber,
                     mode: AppMode::Absorber,
                 }
@@ -115,17 +198,14 @@ impl AppSettings {
                 )?;
                 Self {
                     emitter: None,
+                    streams: BTreeMap::new(),
                     absorber: None,
                     mode: AppMode::Config,
                 }
             }
             None => {
                 info!("No command specified, starting emitter");
-                Self {
-                    absorber: None,
-                    emitter: Self::load_emitter_config(args)?.emitter,
-                    mode: AppMode::Emitter,
-                }
+                Self::load_emitter_config(args)?
             }
         })
     }
@@ -164,12 +244,24 @@ impl AppSettings {
         }
 
         // overwrite with cli args that are present
+        let scenario = args.scenario.clone();
         let cli_args: PartialEmitterConfig = args.into();
-        let config = config.merge(cli_args);
+        let shared = config.clone();
+        let config = config.merge(cli_args.clone());
+
+        // run each scenario stream instead, with its own settings between the shared ones and the cli args
+        let streams = match scenario {
+            Some(path) => Scenario::load(&path)?.resolve(&shared, &cli_args)?,
+            None => {
+                config.validate()?;
+                BTreeMap::new()
+            }
+        };
 
         Ok(AppSettings {
             mode: AppMode::Emitter,
             emitter: Some(config),
+            streams,
             absorber: None,
         })
     }
@@ -193,16 +285,22 @@ impl AppSettings {
         debug!("CLI args: {:?}", cli_args);
         let config = config.merge(cli_args);
         debug!("Config after CLI args: {:?}", config);
+        config.validate()?;
 
         Ok(AppSettings {
             mode: AppMode::Absorber,
             emitter: None,
+            streams: BTreeMap::new(),
             absorber: Some(config),
         })
     }
 }
 
 fn load_config_file(file_path: &Path) -> anyhow::Result<FullConfig> {
+    load_config_file_as(file_path)
+}
+
+fn load_config_file_as<T: DeserializeOwned>(file_path: &Path) -> anyhow::Result<T> {
     if file_path.exists() {
         info!(file = file_path.to_str(); "Using specified file");
         Ok(serde_json5::from_str(&std::fs::read_to_string(file_path)?)?)
@@ -560,6 +658,104 @@ mod test_load_absorber {
             } if message_type == MessageType::NdJson && listen_addresses.is_empty()
         );
     }
+
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn cert_and_key_must_be_set_together() {
+        std::fs::write("./my_config.json5", r#"{ absorber: { token: "", cert: "server.pem" } }"#).unwrap();
+        let args = CliArgs::parse_from(["protoglot", "--file", "./my_config.json5", "absorber"]);
+        let err = AppSettings::load_absorber_config(args).unwrap_err();
+        assert_eq!(err.to_string(), "--cert requires --key");
+
+        let args = [
+            "protoglot",
+            "--file",
+            "./my_config.json5",
+            "absorber",
+            "--cert",
+            "a.pem",
+            "--key",
+            "a.key",
+        ];
+        let found = AppSettings::load_absorber_config(CliArgs::parse_from(args))
+            .unwrap()
+            .absorber
+            .unwrap();
+        assert_eq!(found.key, Some(PathBuf::from("a.key")));
+
+        std::fs::write("./my_config.json5", r#"{ absorber: { token: "", key: "server.key" } }"#).unwrap();
+        let args = CliArgs::parse_from(["protoglot", "--file", "./my_config.json5", "absorber"]);
+        let err = AppSettings::load_absorber_config(args).unwrap_err();
+        assert_eq!(err.to_string(), "--key requires --cert");
+    }
+
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn fault_percentages_must_not_add_up_to_over_100() {
+        let args = ["protoglot", "absorber", "--fail-503", "60", "--fail-disconnect", "60"];
+        let err = AppSettings::load_absorber_config(CliArgs::parse_from(args)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--fail-503, --fail-429 and --fail-disconnect add up to 120%, over 100%"
+        );
+
+        let args = [
+            "protoglot",
+            "absorber",
+            "--fail-503",
+            "50",
+            "--fail-429",
+            "30",
+            "--fail-disconnect",
+            "20",
+        ];
+        assert!(AppSettings::load_absorber_config(CliArgs::parse_from(args)).is_ok());
+    }
+
+    #[test]
+    fn parses_per_listener_options() {
+        let args = [
+            "protoglot",
+            "absorber",
+            "--listen",
+            "tcp://0.0.0.0:9514?type=syslog5424",
+            "--listen",
+            "https://[::1]:8088?type=splunk-hec&auth=splunk&token=abc&cert=private-ca&mtls=true&http2=false",
+            "--listen",
+            "udp://0.0.0.0:9514",
+        ];
+        let config: PartialAbsorberConfig = CliArgs::parse_from(args.iter()).command.into();
+        let addresses = config.listen_addresses.unwrap();
+
+        assert_eq!(addresses[0].options.message_type, Some(MessageType::Syslog5424));
+        assert_eq!(
+            addresses[1].options,
+            ListenerOptions {
+                message_type: Some(MessageType::SplunkHec),
+                tls: None,
+                cert: Some(CertSource::PrivateCa),
+                mtls: Some(true),
+                auth: Some(HttpAuth::Splunk),
+                token: Some("abc".to_string()),
+                http2: Some(false),
+            }
+        );
+        assert_eq!(addresses[1].to_string(), "https://[::1]:8088");
+        assert_eq!(addresses[2].options, ListenerOptions::default());
+
+        let json = serde_json::to_value(&addresses[0]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"host": "0.0.0.0", "port": 9514, "protocol": "tcp", "messageType": "syslog5424"})
+        );
+        assert_eq!(serde_json::from_value::<ListenAddress>(json).unwrap(), addresses[0]);
+
+        for bad in [
+            "tcp://0.0.0.0:9514?kind=ndjson",
+            "tcp://0.0.0.0:9514?type=xml",
+            "tcp://0.0.0.0:9514?tls=yes",
+        ] {
+            assert!(ListenAddress::try_from(bad).is_err(), "{bad}");
+        }
+    }
 }
 
 #[cfg(test)]
@@ -746,6 +942,189 @@ mod test_load_emitter {
         assert_eq!(found.hec_batch_size, 250);
     }
 
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn parses_unlimited_rate_and_duration() {
+        let args = CliArgs::parse_from(["protoglot", "--rate", "unlimited", "--duration", "5m"]);
+
+        let found: EmitterConfig = AppSettings::load_emitter_config(args).unwrap().emitter.unwrap();
+        assert_eq!(found.rate, 0);
+        assert_eq!(found.duration, Some(300_000));
+
+        for duration in ["10", "1.5s", "5d", "s", "18446744073709551615h"] {
+            let parsed = CliArgs::try_parse_from(["protoglot", "--duration", duration]);
+            assert_eq!(parsed.is_ok(), duration == "10", "{duration}");
+        }
+    }
+
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn parses_bandwidth() {
+        let bandwidth = |value: &str| {
+            let args = CliArgs::try_parse_from(["protoglot", "--bandwidth", value]).ok()?;
+            AppSettings::load_emitter_config(args).unwrap().emitter.unwrap().bandwidth
+        };
+
+        assert_eq!(bandwidth("50MB/s"), Some(50_000_000));
+        assert_eq!(bandwidth("10MiB"), Some(10_485_760));
+        assert_eq!(bandwidth("1.5kB/s"), Some(1500));
+        assert_eq!(bandwidth("100Mb/s"), Some(12_500_000));
+        assert_eq!(bandwidth("8kb"), Some(1000));
+        assert_eq!(bandwidth("864GB/day"), Some(10_000_000));
+        assert_eq!(bandwidth("2048"), Some(2048));
+        for invalid in ["50Mbps", "10MB/week", "MB/s", "0", "1B/h", "10mb/s", "10mib", "4b/s"] {
+            assert_eq!(bandwidth(invalid), None, "{invalid}");
+        }
+        let both = ["protoglot", "--bandwidth", "1MB/s", "--rate-schedule", "ramp:1:2:3s"];
+        assert!(CliArgs::try_parse_from(both).is_err());
+
+        // The cli conflict doesn't cover a bandwidth that comes from the config file
+        let config_dir = ProjectDirs::from("com", "ansonvandoren", "protoglot").unwrap();
+        let config_path = config_dir.config_dir().join("config.json5");
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        std::fs::write(&config_path, r#"{ emitter: { bandwidth: 1000 } }"#).unwrap();
+        let args = CliArgs::parse_from(["protoglot", "--rate-schedule", "ramp:1:2:3s"]);
+        let err = AppSettings::load_emitter_config(args).unwrap_err();
+        assert_eq!(err.to_string(), "--bandwidth cannot be combined with --rate-schedule");
+    }
+
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn parses_rate_schedule() {
+        let args = CliArgs::parse_from(["protoglot", "--rate-schedule", "steps:30s=1000,1m=5000"]);
+
+        let found: EmitterConfig = AppSettings::load_emitter_config(args).unwrap().emitter.unwrap();
+        let schedule = found.rate_schedule.clone().unwrap();
+        assert_eq!(schedule.to_string(), "steps:30s=1000,1m=5000");
+        let json = serde_json::to_value(&found).unwrap();
+        assert_eq!(json["rateSchedule"], "steps:30s=1000,1m=5000");
+        assert_eq!(serde_json::from_value::<EmitterConfig>(json).unwrap(), found);
+
+        assert!(CliArgs::try_parse_from(["protoglot", "--rate-schedule", "ramp:1000"]).is_err());
+    }
+
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn merges_tls_cli_opts() {
+        let args = [
+            "protoglot",
+            "--protocol",
+            "tcps",
+            "--tls-ca-file",
+            "/tmp/protoglot/ca_cert.pem",
+            "--tls-client-cert",
+            "/tmp/protoglot/client_cert.pem",
+            "--tls-client-key",
+            "/tmp/protoglot/client_key.pem",
+            "--tls-server-name",
+            "localhost",
+            "--tls-insecure",
+        ];
+        let args = CliArgs::parse_from(args.iter());
+
+        let config = AppSettings::load_emitter_config(args).unwrap();
+
+        let found: EmitterConfig = config.emitter.unwrap();
+        assert_eq!(found.tls_ca_file, Some(PathBuf::from("/tmp/protoglot/ca_cert.pem")));
+        assert_eq!(found.tls_client_cert, Some(PathBuf::from("/tmp/protoglot/client_cert.pem")));
+        assert_eq!(found.tls_client_key, Some(PathBuf::from("/tmp/protoglot/client_key.pem")));
+        assert_eq!(found.tls_server_name.as_deref(), Some("localhost"));
+        assert!(found.tls_insecure);
+    }
+
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn merges_http_cli_opts() {
+        let args = [
+            "protoglot",
+            "--protocol",
+            "http",
+            "--http-path",
+            "/_bulk",
+            "--http-method",
+            "put",
+            "--http-header",
+            "X-Team: pipelines",
+            "--http-header",
+            "X-Env:dev",
+            "--http-auth",
+            "bearer",
+            "--http-credentials",
+            "abc123",
+        ];
+        let args = CliArgs::parse_from(args.iter());
+
+        let config = AppSettings::load_emitter_config(args).unwrap();
+
+        let found: EmitterConfig = config.emitter.unwrap();
+        assert_eq!(found.http_path.as_deref(), Some("/_bulk"));
+        assert_eq!(found.http_method, HttpMethod::Put);
+        assert_eq!(found.http_headers["X-Team"], "pipelines");
+        assert_eq!(found.http_headers["X-Env"], "dev");
+        assert_eq!(found.http_auth, Some(absorber::HttpAuth::Token));
+        assert_eq!(found.http_credentials.as_deref(), Some("abc123"));
+    }
+
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn template_message_type_requires_template_file() {
+        let args = CliArgs::parse_from(["protoglot", "--message-type", "template"]);
+        assert!(AppSettings::load_emitter_config(args).is_err());
+
+        let args = CliArgs::parse_from(["protoglot", "--message-type", "template", "--template", "events.tmpl"]);
+        let found = AppSettings::load_emitter_config(args).unwrap().emitter.unwrap();
+        assert_eq!(found.message_type, MessageType::Template);
+        assert_eq!(found.template, Some(PathBuf::from("events.tmpl")));
+    }
+
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn merges_reconnect_cli_opts() {
+        let args = [
+            "protoglot",
+            "--reconnect",
+            "drop",
+            "--reconnect-attempts",
+            "5",
+            "--reconnect-backoff",
+            "250",
+            "--reconnect-buffer",
+            "4096",
+        ];
+        let args = CliArgs::parse_from(args.iter());
+
+        let config = AppSettings::load_emitter_config(args).unwrap();
+
+        let found: EmitterConfig = config.emitter.unwrap();
+        assert_eq!(found.reconnect, ReconnectMode::Drop);
+        assert_eq!(found.reconnect_attempts, 5);
+        assert_eq!(found.reconnect_backoff, 250);
+        assert_eq!(found.reconnect_max_backoff, EmitterConfig::default().reconnect_max_backoff);
+        assert_eq!(found.reconnect_buffer, 4096);
+
+        for protocol in ["udp", "http", "https"] {
+            let args = CliArgs::parse_from(["protoglot", "--protocol", protocol, "--reconnect", "buffer"]);
+            let err = AppSettings::load_emitter_config(args).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("--reconnect only applies to TCP and TLS emitters, not {protocol}")
+            );
+        }
+    }
+
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn tls_insecure_from_file_is_kept_without_cli_flag() {
+        let config = PartialEmitterConfig {
+            tls_insecure: Some(true),
+            ..Default::default()
+        };
+        let config = FullConfig {
+            absorber: None,
+            emitter: Some(config),
+        };
+        std::fs::write(PathBuf::from("./my_config.json5"), serde_json::to_string(&config).unwrap()).unwrap();
+
+        let args = ["protoglot", "--file", "./my_config.json5"];
+        let args = CliArgs::parse_from(args.iter());
+
+        let config = AppSettings::load_emitter_config(args).unwrap();
+
+        assert!(config.emitter.unwrap().tls_insecure);
+    }
+
     #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
     fn profile_is_runnable_without_additional_options() {
         let args = ["protoglot", "--profile", "splunk-hec"];
@@ -764,4 +1143,54 @@ mod test_load_emitter {
         assert_eq!(found.num_cycles, 1);
         assert_eq!(found.cycle_delay, 0);
     }
+
+    #[sealed_test(env = [("XDG_CONFIG_HOME", "./.config"), ("HOME", "./")])]
+    fn resolves_scenario_streams_over_shared_settings() {
+        let scenario = r#"{
+            // comments are fine, like in config files
+            streams: {
+                syslog: { protocol: "udp", port: 9514, messageType: "syslog3164", rate: 5000 },
+                hec: { profile: "splunk-hec", protocol: "https", rateSchedule: "ramp:100:1000:1m" },
+                ndjson: { protocol: "tcp", port: 9515, messageType: "ndjson", numEmitters: 2 },
+            },
+        }"#;
+        std::fs::write("./scenario.json5", scenario).unwrap();
+        let args = [
+            "protoglot",
+            "--scenario",
+            "./scenario.json5",
+            "--host",
+            "10.0.0.5",
+            "--duration",
+            "1m",
+        ];
+
+        let config = AppSettings::load_emitter_config(CliArgs::parse_from(args)).unwrap();
+
+        assert_eq!(config.streams.keys().collect::<Vec<_>>(), ["hec", "ndjson", "syslog"]);
+        let syslog = &config.streams["syslog"];
+        assert_eq!((syslog.host.as_str(), syslog.port, syslog.rate), ("10.0.0.5", 9514, 5000));
+        assert_eq!(syslog.protocol, Protocol::Udp);
+        assert_eq!(syslog.duration, Some(60_000));
+        // Profiles apply on top of the shared settings, then the stream's own fields, then the cli args
+        let hec = &config.streams["hec"];
+        assert_eq!((hec.host.as_str(), hec.port), ("10.0.0.5", 8088));
+        assert_eq!(hec.protocol, Protocol::Https);
+        assert_eq!(hec.message_type, MessageType::SplunkHec);
+        assert!(hec.rate_schedule.is_some());
+        assert_eq!(config.streams["ndjson"].num_emitters, 2);
+        assert_eq!(config.emitter.unwrap().host, "10.0.0.5");
+
+        std::fs::write("./scenario.json5", r#"{ streams: { bad: { messageType: "template" } } }"#).unwrap();
+        let err = AppSettings::load_emitter_config(CliArgs::parse_from(args)).unwrap_err();
+        assert_eq!(err.to_string(), "Stream 'bad': --message-type template requires --template <file>");
+        std::fs::write("./scenario.json5", r#"{ streams: { json: { statsFormat: "json" } } }"#).unwrap();
+        let err = AppSettings::load_emitter_config(CliArgs::parse_from(args)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Stream 'json': statsFormat applies to all streams, set it outside the scenario"
+        );
+        std::fs::write("./scenario.json5", r#"{ streams: {} }"#).unwrap();
+        assert!(AppSettings::load_emitter_config(CliArgs::parse_from(args)).is_err());
+    }
 }
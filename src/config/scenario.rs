@@ -0,0 +1,60 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Profile, emitter::PartialEmitterConfig, load_config_file_as};
+use crate::config::EmitterConfig;
+
+/// Several named emitter streams to run side by side, each with its own target, message type and rate
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scenario {
+    pub streams: BTreeMap<String, StreamConfig>,
+}
+
+/// One stream of a scenario: an optional profile, then any emitter settings
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    #[serde(flatten)]
+    pub settings: PartialEmitterConfig,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let scenario: Self = load_config_file_as(path)?;
+        if scenario.streams.is_empty() {
+            anyhow::bail!("Scenario '{}' has no streams", path.display());
+        }
+        Ok(scenario)
+    }
+
+    /// Each stream's full config: the shared settings, then the stream's profile, then its own settings,
+    /// then the command line flags, which win over everything
+    pub fn resolve(self, shared: &EmitterConfig, cli: &PartialEmitterConfig) -> anyhow::Result<BTreeMap<String, EmitterConfig>> {
+        self.streams
+            .into_iter()
+            .map(|(name, stream)| {
+                // Stats and metrics are reported once for all streams together
+                let settings = &stream.settings;
+                let global_only = [
+                    ("updateInterval", settings.update_interval.is_some()),
+                    ("statsFormat", settings.stats_format.is_some()),
+                    ("metrics", settings.metrics.is_some()),
+                ];
+                if let Some((field, _)) = global_only.iter().find(|(_, set)| *set) {
+                    anyhow::bail!("Stream '{name}': {field} applies to all streams, set it outside the scenario");
+                }
+                let mut config = shared.clone();
+                if let Some(profile) = stream.profile {
+                    config = config.merge(PartialEmitterConfig::for_profile(profile));
+                }
+                let config = config.merge(stream.settings).merge(cli.clone());
+                config.validate().map_err(|err| anyhow::anyhow!("Stream '{name}': {err}"))?;
+                Ok((name, config))
+            })
+            .collect()
+    }
+}
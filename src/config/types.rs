@@ -71,6 +71,8 @@ pub enum MessageType {
     NdJson,
     #[serde(rename = "splunk-hec", alias = "splunkhec", alias = "splunkHec")]
     SplunkHec,
+    /// Newline-delimited events rendered from a user-supplied template file
+    Template,
 }
 
 impl TryFrom<&str> for MessageType {
@@ -83,6 +85,7 @@ impl TryFrom<&str> for MessageType {
             "syslog5424-octet" => Ok(Self::Syslog5424Octet),
             "ndjson" => Ok(Self::NdJson),
             "splunk-hec" | "splunkhec" | "splunkHec" => Ok(Self::SplunkHec),
+            "template" => Ok(Self::Template),
             _ => Err(anyhow::anyhow!("Unknown message type '{value}'")),
         }
     }
@@ -96,7 +99,81 @@ impl std::fmt::Display for MessageType {
             MessageType::Syslog5424Octet => "syslog5424-octet",
             MessageType::NdJson => "ndjson",
             MessageType::SplunkHec => "splunk-hec",
+            MessageType::Template => "template",
         };
         s.fmt(f)
     }
 }
+
+/// What an emitter does when its connection drops mid-run
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ReconnectMode {
+    /// Fail the emitter on the first send error
+    #[default]
+    Off,
+    /// Keep pacing and hold events, up to --reconnect-buffer bytes, until the connection is back
+    Buffer,
+    /// Keep pacing and discard events until the connection is back
+    Drop,
+}
+
+/// Whether `--rate` limits all emitters together or each one separately
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RateScope {
+    /// One rate budget split between all emitters
+    #[default]
+    Global,
+    /// Every emitter sends at the full rate
+    PerEmitter,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    #[serde(alias = "post")]
+    Post,
+    #[serde(alias = "put")]
+    Put,
+    #[serde(alias = "patch")]
+    Patch,
+}
+
+/// Payload compression applied by emitters
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+    Lz4,
+    Snappy,
+    Brotli,
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Lz4 => "lz4",
+            Compression::Snappy => "snappy",
+            Compression::Brotli => "brotli",
+        };
+        s.fmt(f)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, for scripts and CI
+    Json,
+}
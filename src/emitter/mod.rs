@@ -0,0 +1,857 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+mod stats_svc;
+
+use human_bytes::human_bytes;
+use log::{info, warn};
+pub use stats_svc::StatsSvc;
+use tokio::time::Instant;
+
+use crate::{
+    config::ReconnectMode,
+    generators::EventGenerator,
+    metrics::{MetricType, MetricsWriter},
+    schedule::{RateSchedule, RateShaper},
+    stats::LatencyHistogram,
+    transports::Transport,
+};
+
+pub struct EmitterConfig {
+    pub pacer: SharedPacer,         // rate limit, possibly shared with other emitters
+    pub num_cycles: u64,            // number of cycles to send, 0 means run forever
+    pub events_per_cycle: u64,      // number of events per cycles
+    pub cycle_delay: u64,           // delay between cycles in milliseconds
+    pub batch_size: u64,            // number of events per transport send
+    pub duration: Option<Duration>, // stop after this long, ignoring num_cycles
+    pub reconnect: Option<ReconnectPolicy>,
+}
+
+pub type SharedPacer = Arc<Mutex<Pacer>>;
+
+/// Hands out send times following a rate schedule. Emitters sharing one split its rate between them.
+pub struct Pacer {
+    shaper: RateShaper,
+    unit: PaceUnit,
+    start: Option<Instant>,
+    next_tick: Instant,
+}
+
+/// What a pacer's rate counts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaceUnit {
+    Events,
+    /// Generated bytes, before compression
+    Bytes,
+}
+
+/// When a batch may be sent, from [`Pacer::reserve`]
+enum Pace {
+    /// Send now, without waiting
+    Unlimited,
+    /// Send now, then wait until `tick` before sending more
+    Until { tick: Instant, events_per_sec: f64 },
+}
+
+impl Pacer {
+    pub fn shared(schedule: RateSchedule, unit: PaceUnit) -> SharedPacer {
+        Arc::new(Mutex::new(Self {
+            shaper: RateShaper::new(schedule),
+            unit,
+            start: None,
+            next_tick: Instant::now(),
+        }))
+    }
+
+    /// Whether the schedule is at 0 for now, so nothing should be sent
+    fn is_idle(&mut self) -> bool {
+        let rate = self.rate_at(Instant::now());
+        !self.shaper.is_unlimited() && rate < 1.0
+    }
+
+    /// Claim the time for a batch that has been generated
+    fn reserve(&mut self, events: u64, bytes: u64) -> Pace {
+        let now = Instant::now();
+        let rate = self.rate_at(now);
+        if self.shaper.is_unlimited() {
+            return Pace::Unlimited;
+        }
+        if rate < 1.0 {
+            // Went idle since the caller checked, which only happens when shared. Send without using up time.
+            return Pace::Until {
+                tick: self.next_tick.max(now),
+                events_per_sec: 1.0,
+            };
+        }
+        let units = match self.unit {
+            PaceUnit::Events => events,
+            PaceUnit::Bytes => bytes.max(1),
+        };
+        self.next_tick += Duration::from_secs_f64(units as f64 / rate);
+        Pace::Until {
+            tick: self.next_tick,
+            events_per_sec: rate * events as f64 / units as f64,
+        }
+    }
+
+    /// The rate due at `now`. The schedule starts with the first call.
+    fn rate_at(&mut self, now: Instant) -> f64 {
+        let start = *self.start.get_or_insert_with(|| {
+            self.next_tick = now;
+            now
+        });
+        self.shaper.rate_at(now - start)
+    }
+
+    /// Pace from `now` on, rather than catching up on time spent not sending
+    fn skip_to(&mut self, now: Instant) {
+        self.next_tick = self.next_tick.max(now);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    pub mode: ReconnectMode,
+    pub max_attempts: u64, // 0 means retry forever
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Bytes of batches `ReconnectMode::Buffer` holds while disconnected
+    pub buffer_bytes: u64,
+}
+
+impl ReconnectPolicy {
+    pub fn new(mode: ReconnectMode, max_attempts: u64, initial_backoff_ms: u64, max_backoff_ms: u64, buffer_bytes: u64) -> Option<Self> {
+        match mode {
+            ReconnectMode::Off => None,
+            mode => Some(Self {
+                mode,
+                max_attempts,
+                initial_backoff: Duration::from_millis(initial_backoff_ms),
+                max_backoff: Duration::from_millis(max_backoff_ms.max(initial_backoff_ms)),
+                buffer_bytes,
+            }),
+        }
+    }
+}
+
+/// Reconnect progress while the transport is disconnected
+struct Backoff {
+    attempts: u64,
+    delay: Duration,
+    next_attempt: Instant,
+}
+
+impl Backoff {
+    fn new(policy: &ReconnectPolicy) -> Self {
+        Self {
+            attempts: 0,
+            delay: policy.initial_backoff,
+            next_attempt: Instant::now() + policy.initial_backoff,
+        }
+    }
+
+    fn failed(&mut self, policy: &ReconnectPolicy, err: tokio::io::Error) -> tokio::io::Result<()> {
+        self.attempts += 1;
+        if policy.max_attempts > 0 && self.attempts >= policy.max_attempts {
+            return Err(tokio::io::Error::other(format!(
+                "giving up after {} reconnect attempts: {err}",
+                self.attempts
+            )));
+        }
+        self.delay = (self.delay * 2).min(policy.max_backoff);
+        self.next_attempt = Instant::now() + self.delay;
+        Ok(())
+    }
+}
+
+/// Live copies of an emitter's totals, plus failed sends, for the `/metrics` endpoint
+#[derive(Debug, Default)]
+pub struct EmitterMetrics {
+    events: AtomicU64,
+    bytes: AtomicU64,
+    send_errors: AtomicU64,
+    reconnects: AtomicU64,
+    dropped_events: AtomicU64,
+    /// Transport send times in microseconds, since the stats service last took them
+    send_latency: Mutex<LatencyHistogram>,
+}
+
+type Counter = fn(&EmitterMetrics) -> &AtomicU64;
+
+/// Render every emitter's counters, labelled by emitter index and transport
+pub fn render_metrics(emitters: &[(String, Arc<EmitterMetrics>)]) -> String {
+    let mut writer = MetricsWriter::default();
+    let families: [(&str, &str, Counter); 5] = [
+        ("protoglot_emitter_events_total", "Events sent.", |m| &m.events),
+        ("protoglot_emitter_bytes_total", "Bytes sent, before compression.", |m| &m.bytes),
+        ("protoglot_emitter_send_errors_total", "Sends that failed.", |m| &m.send_errors),
+        ("protoglot_emitter_reconnects_total", "Successful reconnects.", |m| &m.reconnects),
+        (
+            "protoglot_emitter_dropped_events_total",
+            "Events dropped while disconnected.",
+            |m| &m.dropped_events,
+        ),
+    ];
+    let ids: Vec<_> = (0..emitters.len()).map(|idx| idx.to_string()).collect();
+    let labels: Vec<_> = emitters
+        .iter()
+        .zip(&ids)
+        .map(|((transport, _), id)| [("emitter", id.as_str()), ("transport", transport.as_str())])
+        .collect();
+    for (name, help, counter) in families {
+        let samples = emitters
+            .iter()
+            .zip(&labels)
+            .map(|((_, metrics), labels)| (&labels[..], counter(metrics).load(Ordering::Relaxed)));
+        writer.family(name, help, MetricType::Counter, samples);
+    }
+    writer.finish()
+}
+
+pub struct Emitter<T: Transport, G: EventGenerator> {
+    pub transport: T,
+    pub generator: G,
+    pub config: EmitterConfig,
+    cycles_sent: u64,
+    backoff: Option<Backoff>,
+    /// Batches waiting for the connection to come back, with their event counts
+    held: VecDeque<(Vec<u8>, u64)>,
+    held_bytes: u64,
+    pub total_events: u64,
+    pub total_bytes: u64,
+    pub reconnects: u64,
+    pub dropped_events: u64,
+    pub metrics: Arc<EmitterMetrics>,
+}
+
+impl<T, G> Emitter<T, G>
+where
+    T: Transport + Send + 'static + std::fmt::Display,
+    G: EventGenerator + Send + 'static,
+{
+    pub async fn run(&mut self) -> tokio::io::Result<()> {
+        let start_time = Instant::now();
+        let deadline = self.config.duration.map(|duration| start_time + duration);
+        let mut buf = Vec::with_capacity(1024);
+        // Events sent since the last sleep, so sleeps cover several batches at high rates
+        let mut unslept = 0;
+
+        let batch_size = self.config.batch_size.max(1);
+
+        'run: while self.more_cycles() {
+            let mut events_sent_this_cycle = 0;
+            while events_sent_this_cycle < self.config.events_per_cycle {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    info!(emitter=self.transport.to_string(); "Duration reached, stopping");
+                    break 'run;
+                }
+                let events_in_batch = batch_size.min(self.config.events_per_cycle - events_sent_this_cycle);
+                if self.pacer().is_idle() {
+                    // Check back shortly for the schedule to pick up again
+                    let wake = Instant::now() + Duration::from_millis(100);
+                    tokio::time::sleep_until(deadline.map_or(wake, |deadline| deadline.min(wake))).await;
+                    self.pacer().skip_to(Instant::now());
+                    continue;
+                }
+                buf.clear();
+                for _ in 0..events_in_batch {
+                    self.generator.generate_into(&mut buf);
+                }
+                let pace = self.pacer().reserve(events_in_batch, buf.len() as u64);
+                self.send_batch(&buf, events_in_batch).await?;
+
+                events_sent_this_cycle += events_in_batch;
+                unslept += events_in_batch;
+                match pace {
+                    // Unpaced sends may never wait on I/O, so let other tasks run now and then
+                    Pace::Unlimited if unslept >= 128 => {
+                        unslept = 0;
+                        tokio::task::yield_now().await;
+                    }
+                    Pace::Until { tick, events_per_sec } if unslept >= sleep_batch_size(events_per_sec) => {
+                        unslept = 0;
+                        tokio::time::sleep_until(deadline.map_or(tick, |deadline| deadline.min(tick))).await;
+                    }
+                    Pace::Unlimited | Pace::Until { .. } => {}
+                }
+            }
+            self.cycles_sent += 1;
+
+            if self.config.cycle_delay > 0 && self.more_cycles() {
+                let wake = Instant::now() + Duration::from_millis(self.config.cycle_delay);
+                tokio::time::sleep_until(deadline.map_or(wake, |deadline| deadline.min(wake))).await;
+                self.pacer().skip_to(Instant::now());
+            }
+        }
+        // Deliver what was held back while disconnected before finishing, up to the deadline
+        if let Some(policy) = self.config.reconnect.clone()
+            && !self.held.is_empty()
+        {
+            self.flush_held(&policy, true, deadline).await?;
+        }
+
+        let duration = start_time.elapsed();
+        let duration_secs = duration.as_secs_f64();
+        let events_per_sec = self.total_events as f64 / duration_secs;
+        info!(emitter=self.transport.to_string(); "{:.0} events/s average", events_per_sec);
+        let bytes_per_sec = self.total_bytes as f64 / duration_secs;
+        let formatted_bytes = human_bytes(bytes_per_sec);
+        info!(emitter=self.transport.to_string(); "{}/s average", formatted_bytes);
+        if let Some(compressed) = self.transport.compressed_bytes() {
+            let ratio = self.total_bytes as f64 / compressed.max(1) as f64;
+            info!(emitter=self.transport.to_string(); "{} compressed to {} ({:.1}x)", human_bytes(self.total_bytes as f64), human_bytes(compressed as f64), ratio);
+        }
+        if self.reconnects > 0 || self.dropped_events > 0 {
+            info!(emitter=self.transport.to_string(); "{} reconnects, {} events dropped", self.reconnects, self.dropped_events);
+        }
+        Ok(())
+    }
+
+    fn pacer(&self) -> MutexGuard<'_, Pacer> {
+        self.config.pacer.lock().expect("pacer lock poisoned")
+    }
+
+    fn more_cycles(&self) -> bool {
+        self.config.duration.is_some() || self.config.num_cycles == 0 || self.cycles_sent < self.config.num_cycles
+    }
+
+    /// Send one batch, reconnecting per the configured policy. Batches that can't be sent yet are
+    /// held with `ReconnectMode::Buffer` and discarded with `ReconnectMode::Drop`.
+    async fn send_batch(&mut self, buf: &[u8], events: u64) -> tokio::io::Result<()> {
+        let Some(policy) = self.config.reconnect.clone() else {
+            if let Err(err) = self.timed_send(buf).await {
+                self.metrics.send_errors.fetch_add(1, Ordering::Relaxed);
+                return Err(err);
+            }
+            self.record_sent(buf.len() as u64, events);
+            return Ok(());
+        };
+        if policy.mode == ReconnectMode::Buffer {
+            if self.held.is_empty() && self.backoff.is_none() {
+                match self.timed_send(buf).await {
+                    Ok(()) => {
+                        self.record_sent(buf.len() as u64, events);
+                        return Ok(());
+                    }
+                    Err(err) => self.disconnected(&policy, err),
+                }
+            }
+            self.hold(buf.to_vec(), events, &policy);
+            return self.flush_held(&policy, false, None).await;
+        }
+
+        loop {
+            if self.backoff.is_none() {
+                match self.timed_send(buf).await {
+                    Ok(()) => {
+                        self.record_sent(buf.len() as u64, events);
+                        return Ok(());
+                    }
+                    Err(err) => self.disconnected(&policy, err),
+                }
+            }
+            let next_attempt = self.backoff.as_ref().expect("disconnected without backoff state").next_attempt;
+            if Instant::now() < next_attempt {
+                self.drop_events(events);
+                return Ok(());
+            }
+            self.reconnect(&policy).await?;
+        }
+    }
+
+    /// Queue a batch behind any held ones. While disconnected, the oldest batches are dropped to
+    /// stay within the policy's buffer size.
+    fn hold(&mut self, batch: Vec<u8>, events: u64, policy: &ReconnectPolicy) {
+        self.held_bytes += batch.len() as u64;
+        self.held.push_back((batch, events));
+        while self.backoff.is_some() && self.held_bytes > policy.buffer_bytes {
+            let Some((batch, events)) = self.held.pop_front() else { break };
+            self.held_bytes -= batch.len() as u64;
+            self.drop_events(events);
+        }
+    }
+
+    /// Send held batches in order, reconnecting as needed. Without `wait`, returns as soon as it
+    /// would have to wait for the next reconnect attempt. With a `deadline`, whatever can't be
+    /// reconnected for before it is dropped.
+    async fn flush_held(&mut self, policy: &ReconnectPolicy, wait: bool, deadline: Option<Instant>) -> tokio::io::Result<()> {
+        loop {
+            if let Some(backoff) = &self.backoff {
+                if !wait && Instant::now() < backoff.next_attempt {
+                    return Ok(());
+                }
+                if deadline.is_some_and(|deadline| backoff.next_attempt > deadline.max(Instant::now())) {
+                    let events = self.held.drain(..).map(|(_, events)| events).sum();
+                    self.held_bytes = 0;
+                    self.drop_events(events);
+                    return Ok(());
+                }
+                tokio::time::sleep_until(backoff.next_attempt).await;
+                self.reconnect(policy).await?;
+                continue;
+            }
+            let Some((batch, events)) = self.held.pop_front() else {
+                return Ok(());
+            };
+            match self.timed_send(&batch).await {
+                Ok(()) => {
+                    self.held_bytes -= batch.len() as u64;
+                    self.record_sent(batch.len() as u64, events);
+                }
+                Err(err) => {
+                    self.held.push_front((batch, events));
+                    self.disconnected(policy, err);
+                }
+            }
+        }
+    }
+
+    fn disconnected(&mut self, policy: &ReconnectPolicy, err: tokio::io::Error) {
+        self.metrics.send_errors.fetch_add(1, Ordering::Relaxed);
+        warn!(emitter=self.transport.to_string(); "Send failed, reconnecting: {}", err);
+        self.backoff = Some(Backoff::new(policy));
+    }
+
+    /// Make one reconnect attempt, failing once the policy's attempts are used up
+    async fn reconnect(&mut self, policy: &ReconnectPolicy) -> tokio::io::Result<()> {
+        let backoff = self.backoff.as_mut().expect("reconnecting without backoff state");
+        match self.transport.reconnect().await {
+            Ok(()) => {
+                info!(emitter=self.transport.to_string(); "Reconnected after {} failed attempts", backoff.attempts);
+                self.reconnects += 1;
+                self.metrics.reconnects.fetch_add(1, Ordering::Relaxed);
+                self.backoff = None;
+                Ok(())
+            }
+            Err(err) if err.kind() == tokio::io::ErrorKind::Unsupported => Err(err),
+            Err(err) => {
+                warn!(emitter=self.transport.to_string(); "Reconnect attempt {} failed: {}", backoff.attempts + 1, err);
+                backoff.failed(policy, err)
+            }
+        }
+    }
+
+    fn record_sent(&mut self, bytes: u64, events: u64) {
+        self.total_bytes += bytes;
+        self.total_events += events;
+        self.metrics.bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.metrics.events.fetch_add(events, Ordering::Relaxed);
+    }
+
+    fn drop_events(&mut self, events: u64) {
+        self.dropped_events += events;
+        self.metrics.dropped_events.fetch_add(events, Ordering::Relaxed);
+    }
+
+    /// Send on the transport, recording how long successful sends take
+    async fn timed_send(&mut self, buf: &[u8]) -> tokio::io::Result<()> {
+        let start = Instant::now();
+        self.transport.send(buf).await?;
+        let micros = start.elapsed().as_micros() as u64;
+        self.metrics.send_latency.lock().expect("latency lock poisoned").record(micros);
+        Ok(())
+    }
+
+    pub fn new(transport: T, generator: G, config: EmitterConfig) -> Self {
+        Emitter {
+            transport,
+            generator,
+            config,
+            cycles_sent: 0,
+            backoff: None,
+            held: VecDeque::new(),
+            held_bytes: 0,
+            total_events: 0,
+            total_bytes: 0,
+            reconnects: 0,
+            dropped_events: 0,
+            metrics: Arc::default(),
+        }
+    }
+}
+
+/// Events to send between sleeps, trading pacing smoothness for fewer timer wakeups
+fn sleep_batch_size(events_per_sec: f64) -> u64 {
+    if events_per_sec >= 100_000.0 {
+        128
+    } else if events_per_sec >= 10_000.0 {
+        64
+    } else if events_per_sec >= 1_000.0 {
+        16
+    } else if events_per_sec >= 100.0 {
+        4
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fmt,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+
+    struct FakeGenerator {
+        next: u64,
+    }
+
+    impl EventGenerator for FakeGenerator {
+        fn generate_into(&mut self, buf: &mut Vec<u8>) {
+            buf.extend_from_slice(format!("event-{}\n", self.next).as_bytes());
+            self.next += 1;
+        }
+    }
+
+    struct FakeTransport {
+        sends: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl Transport for FakeTransport {
+        async fn send(&mut self, data: &[u8]) -> tokio::io::Result<()> {
+            self.sends.lock().unwrap().push(data.to_vec());
+            Ok(())
+        }
+    }
+
+    impl fmt::Display for FakeTransport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "fake")
+        }
+    }
+
+    /// Drops the connection on the `fail_at`th send, then refuses `reconnect_failures` reconnects
+    struct FlakyTransport {
+        sends: Arc<Mutex<Vec<Vec<u8>>>>,
+        attempts: usize,
+        fail_at: usize,
+        reconnect_failures: usize,
+        connected: bool,
+    }
+
+    impl FlakyTransport {
+        fn new(sends: Arc<Mutex<Vec<Vec<u8>>>>, fail_at: usize, reconnect_failures: usize) -> Self {
+            Self {
+                sends,
+                attempts: 0,
+                fail_at,
+                reconnect_failures,
+                connected: true,
+            }
+        }
+    }
+
+    impl Transport for FlakyTransport {
+        async fn send(&mut self, data: &[u8]) -> tokio::io::Result<()> {
+            self.attempts += 1;
+            if self.attempts == self.fail_at {
+                self.connected = false;
+            }
+            if !self.connected {
+                return Err(tokio::io::ErrorKind::BrokenPipe.into());
+            }
+            self.sends.lock().unwrap().push(data.to_vec());
+            Ok(())
+        }
+
+        async fn reconnect(&mut self) -> tokio::io::Result<()> {
+            if self.reconnect_failures > 0 {
+                self.reconnect_failures -= 1;
+                return Err(tokio::io::ErrorKind::ConnectionRefused.into());
+            }
+            self.connected = true;
+            Ok(())
+        }
+    }
+
+    impl fmt::Display for FlakyTransport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "flaky")
+        }
+    }
+
+    fn reconnect_config(mode: ReconnectMode, max_attempts: u64, rate: u64) -> EmitterConfig {
+        EmitterConfig {
+            pacer: Pacer::shared(RateSchedule::Constant(rate), PaceUnit::Events),
+            num_cycles: 1,
+            events_per_cycle: 20,
+            cycle_delay: 0,
+            duration: None,
+            batch_size: 1,
+            reconnect: ReconnectPolicy::new(mode, max_attempts, 30, 60, 1024),
+        }
+    }
+
+    #[tokio::test]
+    async fn buffers_events_until_reconnected() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FlakyTransport::new(sends.clone(), 5, 2);
+        let generator = FakeGenerator { next: 0 };
+        let mut emitter = Emitter::new(transport, generator, reconnect_config(ReconnectMode::Buffer, 0, 1_000_000));
+
+        emitter.run().await.unwrap();
+
+        let sends = sends.lock().unwrap();
+        assert_eq!(sends.len(), 20);
+        assert_eq!(sends[4], b"event-4\n");
+        assert_eq!(emitter.total_events, 20);
+        assert_eq!(emitter.reconnects, 1);
+        assert_eq!(emitter.dropped_events, 0);
+    }
+
+    #[tokio::test]
+    async fn drops_the_oldest_held_events_beyond_the_buffer() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FlakyTransport::new(sends.clone(), 5, 2);
+        let generator = FakeGenerator { next: 0 };
+        let mut config = reconnect_config(ReconnectMode::Buffer, 0, 1_000_000);
+        // Room for the last three events, at 9 bytes each
+        config.reconnect.as_mut().unwrap().buffer_bytes = 27;
+        let mut emitter = Emitter::new(transport, generator, config);
+
+        emitter.run().await.unwrap();
+
+        let sends = sends.lock().unwrap();
+        assert_eq!(sends.len(), 7);
+        assert_eq!(sends[3], b"event-3\n");
+        assert_eq!(sends[4], b"event-17\n");
+        assert_eq!(sends[6], b"event-19\n");
+        assert_eq!(emitter.total_events, 7);
+        assert_eq!(emitter.dropped_events, 13);
+    }
+
+    #[tokio::test]
+    async fn drops_events_while_disconnected() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FlakyTransport::new(sends.clone(), 5, 0);
+        let generator = FakeGenerator { next: 0 };
+        let mut emitter = Emitter::new(transport, generator, reconnect_config(ReconnectMode::Drop, 0, 200));
+
+        emitter.run().await.unwrap();
+
+        let delivered = sends.lock().unwrap().len() as u64;
+        assert_eq!(emitter.reconnects, 1);
+        assert!(emitter.dropped_events > 0);
+        assert_eq!(delivered, emitter.total_events);
+        assert_eq!(delivered + emitter.dropped_events, 20);
+    }
+
+    #[tokio::test]
+    async fn exposes_totals_as_metrics() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FlakyTransport::new(sends.clone(), 5, 2);
+        let generator = FakeGenerator { next: 0 };
+        let mut emitter = Emitter::new(transport, generator, reconnect_config(ReconnectMode::Buffer, 0, 1_000_000));
+
+        emitter.run().await.unwrap();
+
+        let text = render_metrics(&[(emitter.transport.to_string(), emitter.metrics.clone())]);
+        for line in [
+            "protoglot_emitter_events_total{emitter=\"0\",transport=\"flaky\"} 20".to_string(),
+            format!(
+                "protoglot_emitter_bytes_total{{emitter=\"0\",transport=\"flaky\"}} {}",
+                emitter.total_bytes
+            ),
+            "protoglot_emitter_send_errors_total{emitter=\"0\",transport=\"flaky\"} 1".to_string(),
+            "protoglot_emitter_reconnects_total{emitter=\"0\",transport=\"flaky\"} 1".to_string(),
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {line:?} in\n{text}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drops_held_events_at_the_duration_if_never_reconnected() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FlakyTransport::new(sends.clone(), 5, usize::MAX);
+        let generator = FakeGenerator { next: 0 };
+        let mut config = reconnect_config(ReconnectMode::Buffer, 0, 10);
+        config.duration = Some(Duration::from_secs(1));
+        let mut emitter = Emitter::new(transport, generator, config);
+
+        let start = Instant::now();
+        emitter.run().await.unwrap();
+
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        assert_eq!(sends.lock().unwrap().len(), 4);
+        assert_eq!(emitter.total_events, 4);
+        assert_eq!(emitter.dropped_events, emitter.generator.next - 4);
+        assert!(emitter.held.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn finishes_at_the_duration_while_disconnected_in_drop_mode() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FlakyTransport::new(sends.clone(), 5, usize::MAX);
+        let generator = FakeGenerator { next: 0 };
+        let mut config = reconnect_config(ReconnectMode::Drop, 0, 10);
+        config.duration = Some(Duration::from_secs(1));
+        let mut emitter = Emitter::new(transport, generator, config);
+
+        let start = Instant::now();
+        emitter.run().await.unwrap();
+
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        assert_eq!(emitter.total_events, 4);
+        assert_eq!(emitter.dropped_events, emitter.generator.next - 4);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_reconnect_attempts() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FlakyTransport::new(sends.clone(), 3, usize::MAX);
+        let generator = FakeGenerator { next: 0 };
+        let mut emitter = Emitter::new(transport, generator, reconnect_config(ReconnectMode::Buffer, 2, 1_000_000));
+
+        assert!(emitter.run().await.is_err());
+        assert_eq!(sends.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn batches_multiple_events_per_transport_send() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FakeTransport { sends: sends.clone() };
+        let generator = FakeGenerator { next: 0 };
+        let config = EmitterConfig {
+            pacer: Pacer::shared(RateSchedule::Constant(1_000_000), PaceUnit::Events),
+            num_cycles: 1,
+            events_per_cycle: 5,
+            cycle_delay: 0,
+            duration: None,
+            batch_size: 2,
+            reconnect: None,
+        };
+        let mut emitter = Emitter::new(transport, generator, config);
+
+        emitter.run().await.unwrap();
+
+        let sends = sends.lock().unwrap();
+        assert_eq!(sends.len(), 3);
+        assert_eq!(sends[0], b"event-0\nevent-1\n");
+        assert_eq!(sends[1], b"event-2\nevent-3\n");
+        assert_eq!(sends[2], b"event-4\n");
+        assert_eq!(emitter.total_events, 5);
+    }
+
+    #[tokio::test]
+    async fn sends_unpaced_with_an_unlimited_rate() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FakeTransport { sends: sends.clone() };
+        let generator = FakeGenerator { next: 0 };
+        let config = EmitterConfig {
+            pacer: Pacer::shared(RateSchedule::Constant(0), PaceUnit::Events),
+            num_cycles: 2,
+            events_per_cycle: 1000,
+            cycle_delay: 0,
+            duration: None,
+            batch_size: 1,
+            reconnect: None,
+        };
+        let mut emitter = Emitter::new(transport, generator, config);
+
+        emitter.run().await.unwrap();
+
+        assert_eq!(emitter.total_events, 2000);
+        assert_eq!(sends.lock().unwrap().len(), 2000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_at_the_duration_instead_of_after_cycles() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FakeTransport { sends: sends.clone() };
+        let generator = FakeGenerator { next: 0 };
+        let config = EmitterConfig {
+            pacer: Pacer::shared(RateSchedule::Constant(100), PaceUnit::Events),
+            num_cycles: 1,
+            events_per_cycle: 40,
+            cycle_delay: 1000,
+            duration: Some(Duration::from_secs(3)),
+            batch_size: 1,
+            reconnect: None,
+        };
+        let mut emitter = Emitter::new(transport, generator, config);
+
+        let start = Instant::now();
+        emitter.run().await.unwrap();
+
+        // Two cycles of 0.4s with a 1s delay after each, then 0.2s into a third
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+        assert_eq!(emitter.total_events, 100);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn follows_a_rate_schedule() {
+        let sends = Arc::new(Mutex::new(Vec::new()));
+        let transport = FakeTransport { sends: sends.clone() };
+        let generator = FakeGenerator { next: 0 };
+        let config = EmitterConfig {
+            pacer: Pacer::shared("steps:1s=10,1s=0,1s=20".parse().unwrap(), PaceUnit::Events),
+            num_cycles: 1,
+            events_per_cycle: 1000,
+            cycle_delay: 0,
+            duration: Some(Duration::from_secs(3)),
+            batch_size: 1,
+            reconnect: None,
+        };
+        let mut emitter = Emitter::new(transport, generator, config);
+
+        emitter.run().await.unwrap();
+
+        // Nothing is sent while the schedule is at 0
+        assert_eq!(emitter.total_events, 30);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn splits_a_shared_rate_between_emitters() {
+        let pacer = Pacer::shared(RateSchedule::Constant(100), PaceUnit::Events);
+        let emitter = || {
+            let config = EmitterConfig {
+                pacer: pacer.clone(),
+                num_cycles: 1,
+                events_per_cycle: 1000,
+                cycle_delay: 0,
+                duration: Some(Duration::from_secs(2)),
+                batch_size: 1,
+                reconnect: None,
+            };
+            Emitter::new(FakeTransport { sends: Arc::default() }, FakeGenerator { next: 0 }, config)
+        };
+        let (mut first, mut second) = (emitter(), emitter());
+
+        let (a, b) = tokio::join!(first.run(), second.run());
+        a.unwrap();
+        b.unwrap();
+
+        let total = first.total_events + second.total_events;
+        assert!((200..=208).contains(&total), "{total}");
+        assert!(first.total_events.abs_diff(second.total_events) <= 8);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paces_on_bytes_with_a_bandwidth() {
+        let transport = FakeTransport { sends: Arc::default() };
+        // Events grow from 8 to 10 bytes as the sequence number does
+        let generator = FakeGenerator { next: 0 };
+        let config = EmitterConfig {
+            pacer: Pacer::shared(RateSchedule::Constant(1000), PaceUnit::Bytes),
+            num_cycles: 1,
+            events_per_cycle: 1000,
+            cycle_delay: 0,
+            duration: Some(Duration::from_secs(2)),
+            batch_size: 1,
+            reconnect: None,
+        };
+        let mut emitter = Emitter::new(transport, generator, config);
+
+        emitter.run().await.unwrap();
+
+        assert!((2000..2040).contains(&emitter.total_bytes), "{}", emitter.total_bytes);
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use human_bytes::human_bytes;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use super::EmitterMetrics;
+use crate::{
+    config::StatsFormat,
+    stats::{LatencyHistogram, LatencySummary, human_events, human_latency},
+};
+
+/// Totals across all emitters at one point in time
+#[derive(Clone, Copy, Debug, Default)]
+struct Totals {
+    events: u64,
+    bytes: u64,
+    send_errors: u64,
+    reconnects: u64,
+    dropped_events: u64,
+}
+
+/// Periodic stats for all emitters in the process, read from their live metrics
+pub struct StatsSvc {
+    emitters: Vec<Arc<EmitterMetrics>>,
+    last: Totals,
+    start_time: Instant,
+    latency: LatencyHistogram,
+}
+
+impl StatsSvc {
+    /// Print stats every `update_intv_millis` until the process exits
+    pub fn run(emitters: Vec<Arc<EmitterMetrics>>, update_intv_millis: u64, format: StatsFormat) {
+        let mut stats = Self::new(emitters);
+        let mut interval = tokio::time::interval(Duration::from_millis(update_intv_millis));
+        interval.reset();
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                let report = stats.interval_report();
+                if report.interval_events == 0 && report.interval_send_errors == 0 {
+                    continue;
+                }
+                match format {
+                    StatsFormat::Text => println!("{report}"),
+                    StatsFormat::Json => println!("{}", serde_json::to_string(&report).unwrap()),
+                }
+            }
+        });
+    }
+
+    fn new(emitters: Vec<Arc<EmitterMetrics>>) -> Self {
+        Self {
+            emitters,
+            last: Totals::default(),
+            start_time: Instant::now(),
+            latency: LatencyHistogram::default(),
+        }
+    }
+
+    fn totals(&self) -> Totals {
+        self.emitters.iter().fold(Totals::default(), |sum, m| Totals {
+            events: sum.events + m.events.load(Ordering::Relaxed),
+            bytes: sum.bytes + m.bytes.load(Ordering::Relaxed),
+            send_errors: sum.send_errors + m.send_errors.load(Ordering::Relaxed),
+            reconnects: sum.reconnects + m.reconnects.load(Ordering::Relaxed),
+            dropped_events: sum.dropped_events + m.dropped_events.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Stats since the last report, which starts a new interval
+    fn interval_report(&mut self) -> IntervalReport {
+        let totals = self.totals();
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        for metrics in &self.emitters {
+            let mut latency = metrics.send_latency.lock().expect("latency lock poisoned");
+            self.latency.add(&latency);
+            latency.clear();
+        }
+        let interval_events = totals.events - self.last.events;
+        let report = IntervalReport {
+            kind: "interval",
+            timestamp: jiff::Timestamp::now().to_string(),
+            emitters: self.emitters.len(),
+            total_events: totals.events,
+            total_bytes: totals.bytes,
+            total_send_errors: totals.send_errors,
+            reconnects: totals.reconnects,
+            dropped_events: totals.dropped_events,
+            interval_events,
+            interval_send_errors: totals.send_errors - self.last.send_errors,
+            eps: interval_events as f64 / elapsed,
+            bytes_per_sec: (totals.bytes - self.last.bytes) as f64 / elapsed,
+            send_latency_micros: (!self.latency.is_empty()).then(|| self.latency.summary()),
+        };
+        self.last = totals;
+        self.start_time = Instant::now();
+        self.latency.clear();
+        report
+    }
+}
+
+/// One line of emitter stats, printed each interval that saw sends
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IntervalReport {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    timestamp: String,
+    emitters: usize,
+    total_events: u64,
+    total_bytes: u64,
+    total_send_errors: u64,
+    reconnects: u64,
+    dropped_events: u64,
+    interval_events: u64,
+    interval_send_errors: u64,
+    eps: f64,
+    bytes_per_sec: f64,
+    /// How long each transport send took
+    #[serde(skip_serializing_if = "Option::is_none")]
+    send_latency_micros: Option<LatencySummary>,
+}
+
+impl std::fmt::Display for IntervalReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Sent events: {}, Sent bytes: {} | {} EPS, {}/s, {} send errors",
+            self.total_events,
+            human_bytes(self.total_bytes as f64),
+            human_events(self.eps),
+            human_bytes(self.bytes_per_sec),
+            self.interval_send_errors
+        )?;
+        if self.reconnects > 0 || self.dropped_events > 0 {
+            write!(f, ", {} reconnects, {} dropped", self.reconnects, self.dropped_events)?;
+        }
+        if let Some(LatencySummary { p50, p90, p99, max, .. }) = self.send_latency_micros {
+            write!(
+                f,
+                "\nSend latency p50: {}, p90: {}, p99: {}, max: {}",
+                human_latency(p50),
+                human_latency(p90),
+                human_latency(p99),
+                human_latency(max)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn aggregates_emitters_per_interval() {
+        let (first, second) = (Arc::new(EmitterMetrics::default()), Arc::new(EmitterMetrics::default()));
+        let mut stats = StatsSvc::new(vec![first.clone(), second.clone()]);
+        for (metrics, events) in [(&first, 300), (&second, 700)] {
+            metrics.events.fetch_add(events, Ordering::Relaxed);
+            metrics.bytes.fetch_add(events * 100, Ordering::Relaxed);
+        }
+        first.send_errors.fetch_add(2, Ordering::Relaxed);
+        first.send_latency.lock().unwrap().record(1_000);
+        second.send_latency.lock().unwrap().record(3_000);
+        tokio::time::advance(Duration::from_secs(2)).await;
+
+        let report = stats.interval_report();
+        assert_eq!(report.total_events, 1000);
+        assert_eq!(report.eps, 500.0);
+        assert_eq!(report.bytes_per_sec, 50_000.0);
+        assert_eq!(report.interval_send_errors, 2);
+        assert_eq!(report.send_latency_micros.unwrap().samples, 2);
+        let text = report.to_string();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "Sent events: 1000, Sent bytes: 97.7 KiB | 500 EPS, 48.8 KiB/s, 2 send errors",
+                "Send latency p50: 1.0ms, p90: 3.0ms, p99: 3.0ms, max: 3.0ms",
+            ]
+        );
+
+        second.events.fetch_add(100, Ordering::Relaxed);
+        tokio::time::advance(Duration::from_secs(1)).await;
+        let report = stats.interval_report();
+        assert_eq!((report.total_events, report.interval_events), (1100, 100));
+        assert_eq!(report.interval_send_errors, 0);
+        assert!(report.send_latency_micros.is_none());
+    }
+}
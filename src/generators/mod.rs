@@ -2,19 +2,29 @@ mod nd_json;
 mod splunk_hec;
 mod syslog3164;
 mod syslog5424;
+mod template;
 
+use std::{
+    io::Write as _,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context as _;
+use log::info;
 pub use nd_json::NdJsonEventGenerator;
 pub use splunk_hec::SplunkHecEventGenerator;
 pub use syslog3164::Syslog3164EventGenerator;
 pub use syslog5424::Syslog5424EventGenerator;
+pub use template::TemplateEventGenerator;
 
-use crate::config::MessageType;
+use crate::config::{EmitterConfig, MessageType};
 
 pub enum EventType {
     Syslog3164(Syslog3164EventGenerator),
     Syslog5424(Syslog5424EventGenerator),
     NdJson(NdJsonEventGenerator),
     SplunkHec(SplunkHecEventGenerator),
+    Template(TemplateEventGenerator),
 }
 
 impl EventGenerator for EventType {
@@ -24,19 +34,120 @@ impl EventGenerator for EventType {
             EventType::Syslog5424(generator) => generator.generate_into(buf),
             EventType::NdJson(generator) => generator.generate_into(buf),
             EventType::SplunkHec(generator) => generator.generate_into(buf),
+            EventType::Template(generator) => generator.generate_into(buf),
+        }
+    }
+}
+
+/// Key embedded in events next to `<stream>:<seq>`, so the absorber can detect loss, duplicates
+/// and reordering
+pub const SEQUENCE_KEY: &str = "protoglot_seq";
+/// Key embedded in events next to the send time, in microseconds since the Unix epoch
+pub const TIMESTAMP_KEY: &str = "protoglot_ts";
+
+/// A stream ID unique to one emitter, and the next sequence number to embed in its events
+pub struct Sequence {
+    stream: String,
+    next: u64,
+}
+
+impl Sequence {
+    pub fn new(stream: String) -> Self {
+        Self { stream, next: 0 }
+    }
+
+    pub fn random() -> Self {
+        Self::new(format!("{:08x}", fastrand::u32(..)))
+    }
+
+    pub fn stream(&self) -> &str {
+        &self.stream
+    }
+
+    fn next(&mut self) -> u64 {
+        let seq = self.next;
+        self.next += 1;
+        seq
+    }
+}
+
+/// Markers embedded in generated events for the absorber to pick up
+#[derive(Default)]
+pub struct Tags {
+    pub sequence: Option<Sequence>,
+    /// Stamp each event with the time it was generated, for end-to-end latency
+    pub timestamp: bool,
+}
+
+impl Tags {
+    fn is_empty(&self) -> bool {
+        self.sequence.is_none() && !self.timestamp
+    }
+
+    /// Write a text event with ` protoglot_seq=<stream>:<seq>` and ` protoglot_ts=<micros>`
+    /// appended before its trailing newline
+    fn write_text(&mut self, buf: &mut Vec<u8>, msg: &[u8]) {
+        let (msg, newline) = match msg.strip_suffix(b"\n") {
+            Some(msg) => (msg, "\n"),
+            None => (msg, ""),
+        };
+        buf.extend_from_slice(msg);
+        if let Some(sequence) = &mut self.sequence {
+            let seq = sequence.next();
+            let _ = write!(buf, " {SEQUENCE_KEY}={}:{seq}", sequence.stream);
+        }
+        if self.timestamp {
+            let _ = write!(buf, " {TIMESTAMP_KEY}={}", unix_micros());
+        }
+        buf.extend_from_slice(newline.as_bytes());
+    }
+
+    /// `"protoglot_seq":"<stream>:<seq>","protoglot_ts":<micros>`, for embedding in a JSON object.
+    /// Writes nothing if there are no tags.
+    fn write_json_fields(&mut self, buf: &mut Vec<u8>) {
+        if let Some(sequence) = &mut self.sequence {
+            let seq = sequence.next();
+            let _ = write!(buf, r#""{SEQUENCE_KEY}":"{}:{seq}""#, sequence.stream);
+        }
+        if self.timestamp {
+            if self.sequence.is_some() {
+                buf.push(b',');
+            }
+            let _ = write!(buf, r#""{TIMESTAMP_KEY}":{}"#, unix_micros());
         }
     }
 }
+
+pub fn unix_micros() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_micros() as u64)
+}
+
 pub trait EventGenerator {
     fn generate_into(&mut self, buf: &mut Vec<u8>);
 }
 
-pub fn create_generator(message_type: &MessageType) -> EventType {
-    match message_type {
-        MessageType::Syslog3164 => EventType::Syslog3164(Syslog3164EventGenerator::new()),
-        MessageType::Syslog5424 => EventType::Syslog5424(Syslog5424EventGenerator::new(false)),
-        MessageType::Syslog5424Octet => EventType::Syslog5424(Syslog5424EventGenerator::new(true)),
-        MessageType::NdJson => EventType::NdJson(NdJsonEventGenerator::new()),
-        MessageType::SplunkHec => EventType::SplunkHec(SplunkHecEventGenerator::new()),
+pub fn create_generator(config: &EmitterConfig) -> anyhow::Result<EventType> {
+    let sequence = config.sequence.then(Sequence::random);
+    if let Some(sequence) = &sequence {
+        info!(stream = sequence.stream(); "Embedding sequence numbers in events");
     }
+    let tags = Tags {
+        sequence,
+        timestamp: config.latency,
+    };
+    let generator = match config.message_type {
+        MessageType::Syslog3164 => EventType::Syslog3164(Syslog3164EventGenerator::new().with_tags(tags)),
+        MessageType::Syslog5424 => EventType::Syslog5424(Syslog5424EventGenerator::new(false).with_tags(tags)),
+        MessageType::Syslog5424Octet => EventType::Syslog5424(Syslog5424EventGenerator::new(true).with_tags(tags)),
+        MessageType::NdJson => EventType::NdJson(NdJsonEventGenerator::new().with_tags(tags)),
+        MessageType::SplunkHec => EventType::SplunkHec(SplunkHecEventGenerator::new().with_tags(tags)),
+        MessageType::Template => {
+            let path = config
+                .template
+                .as_deref()
+                .context("The template message type requires a template file")?;
+            EventType::Template(TemplateEventGenerator::from_file(path)?.with_tags(tags)?)
+        }
+    };
+    Ok(generator)
 }
@@ -1,12 +1,21 @@
-use super::EventGenerator;
+use super::{EventGenerator, Tags};
 
 pub struct NdJsonEventGenerator {
     message_index: u64,
+    tags: Tags,
 }
 
 impl NdJsonEventGenerator {
     pub fn new() -> Self {
-        Self { message_index: 0 }
+        Self {
+            message_index: 0,
+            tags: Tags::default(),
+        }
+    }
+
+    pub fn with_tags(mut self, tags: Tags) -> Self {
+        self.tags = tags;
+        self
     }
 }
 
@@ -26,6 +35,15 @@ b"{\"timestamp\":\"2025-04-27T00:12:49.031Z\",\"hostname\":\"example.com\",\"app
 impl EventGenerator for NdJsonEventGenerator {
     fn generate_into(&mut self, buf: &mut Vec<u8>) {
         self.message_index += 1;
-        buf.extend_from_slice(MESSAGES[self.message_index as usize % MESSAGES.len()]);
+        let msg = MESSAGES[self.message_index as usize % MESSAGES.len()];
+        if self.tags.is_empty() {
+            buf.extend_from_slice(msg);
+        } else {
+            // Every message is a JSON object, so the fields can go right after the opening brace
+            buf.push(b'{');
+            self.tags.write_json_fields(buf);
+            buf.push(b',');
+            buf.extend_from_slice(&msg[1..]);
+        }
     }
 }
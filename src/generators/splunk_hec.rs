@@ -1,14 +1,23 @@
 use std::io::Write as _;
 
-use super::EventGenerator;
+use super::{EventGenerator, Tags};
 
 pub struct SplunkHecEventGenerator {
     message_index: u64,
+    tags: Tags,
 }
 
 impl SplunkHecEventGenerator {
     pub fn new() -> Self {
-        Self { message_index: 0 }
+        Self {
+            message_index: 0,
+            tags: Tags::default(),
+        }
+    }
+
+    pub fn with_tags(mut self, tags: Tags) -> Self {
+        self.tags = tags;
+        self
     }
 }
 
@@ -44,11 +53,16 @@ impl EventGenerator for SplunkHecEventGenerator {
             ),
         };
 
-        let _ = writeln!(
+        let _ = write!(
             buf,
-            r#"{{"time":{time:.3},"host":"{host}","source":"{source}","sourcetype":"{sourcetype}","index":"main","fields":{{"generator":"protoglot","sequence":{idx},"variant":{}}},"event":{event}}}"#,
+            r#"{{"time":{time:.3},"host":"{host}","source":"{source}","sourcetype":"{sourcetype}","index":"main","fields":{{"generator":"protoglot","sequence":{idx},"variant":{}"#,
             idx % 5
         );
+        if !self.tags.is_empty() {
+            buf.push(b',');
+            self.tags.write_json_fields(buf);
+        }
+        let _ = writeln!(buf, r#"}},"event":{event}}}"#);
     }
 }
 
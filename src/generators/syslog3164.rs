@@ -1,4 +1,4 @@
-use super::EventGenerator;
+use super::{EventGenerator, Tags};
 
 const MESSAGES: &[&[u8]] = &[
 b"<67>Feb 16 02:00:41 crona7704 eos[8025]: We need to reboot the haptic SDD pixel!\n",
@@ -15,17 +15,31 @@ b"<190>Feb 16 02:00:41 gutkowski4676 rerum[4214]: If we index the protocol, we c
 ];
 pub struct Syslog3164EventGenerator {
     message_index: u64,
+    tags: Tags,
 }
 
 impl Syslog3164EventGenerator {
     pub fn new() -> Self {
-        Self { message_index: 0 }
+        Self {
+            message_index: 0,
+            tags: Tags::default(),
+        }
+    }
+
+    pub fn with_tags(mut self, tags: Tags) -> Self {
+        self.tags = tags;
+        self
     }
 }
 
 impl EventGenerator for Syslog3164EventGenerator {
     fn generate_into(&mut self, buf: &mut Vec<u8>) {
         self.message_index += 1;
-        buf.extend_from_slice(MESSAGES[self.message_index as usize % MESSAGES.len()]);
+        let msg = MESSAGES[self.message_index as usize % MESSAGES.len()];
+        if self.tags.is_empty() {
+            buf.extend_from_slice(msg);
+        } else {
+            self.tags.write_text(buf, msg);
+        }
     }
 }
@@ -1,10 +1,12 @@
 use std::io::Write;
 
-use super::EventGenerator;
+use super::{EventGenerator, Tags};
 
 pub struct Syslog5424EventGenerator {
     message_index: u64,
     octet_count_framing: bool,
+    tags: Tags,
+    scratch: Vec<u8>,
 }
 
 impl Syslog5424EventGenerator {
@@ -12,8 +14,15 @@ impl Syslog5424EventGenerator {
         Self {
             message_index: 0,
             octet_count_framing,
+            tags: Tags::default(),
+            scratch: Vec::new(),
         }
     }
+
+    pub fn with_tags(mut self, tags: Tags) -> Self {
+        self.tags = tags;
+        self
+    }
 }
 
 const MESSAGES: &[&[u8]] = &[
@@ -53,6 +62,11 @@ impl EventGenerator for Syslog5424EventGenerator {
     fn generate_into(&mut self, buf: &mut Vec<u8>) {
         self.message_index += 1;
         let mut msg = MESSAGES[self.message_index as usize % MESSAGES.len()];
+        if !self.tags.is_empty() {
+            self.scratch.clear();
+            self.tags.write_text(&mut self.scratch, msg);
+            msg = &self.scratch;
+        }
         if self.octet_count_framing {
             // Remove the trailing newline if present (it is in our const data)
             if msg.last() == Some(&b'\n') {
@@ -0,0 +1,362 @@
+use std::{io::Write as _, path::Path};
+
+use anyhow::Context as _;
+use jiff::{Timestamp, fmt::strtime, tz::TimeZone};
+
+use super::{EventGenerator, SEQUENCE_KEY, Sequence, TIMESTAMP_KEY, Tags};
+
+/// Renders events from user-supplied templates, one template per non-empty line of the file.
+/// Templates are parsed once up front, so generating an event is just a walk over its parts.
+pub struct TemplateEventGenerator {
+    templates: Vec<Template>,
+    message_index: u64,
+    /// Rendered by `{{stream}}`, so templates can carry their own sequence marker
+    stream: String,
+    rng: fastrand::Rng,
+}
+
+impl TemplateEventGenerator {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path).with_context(|| format!("Failed to read template file {}", path.display()))?;
+        Self::parse(&source).with_context(|| format!("Invalid template file {}", path.display()))
+    }
+
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let templates = source
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| Template::parse(line).with_context(|| format!("line {}", idx + 1)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if templates.is_empty() {
+            anyhow::bail!("no templates found");
+        }
+        Ok(Self {
+            templates,
+            message_index: 0,
+            stream: Sequence::random().stream().to_string(),
+            rng: fastrand::Rng::new(),
+        })
+    }
+
+    /// Templates place their own markers with `{{stream}}`, `{{seq}}` and `{{timestamp:epoch_us}}`,
+    /// so only the stream ID is taken from `tags`. Fails if a template is missing a marker the tags ask for.
+    pub fn with_tags(mut self, tags: Tags) -> anyhow::Result<Self> {
+        if tags.sequence.is_some()
+            && !self
+                .templates
+                .iter()
+                .all(|t| t.has_marker(SEQUENCE_KEY, |p| matches!(p, Part::Seq)) && t.parts.iter().any(|p| matches!(p, Part::Stream)))
+        {
+            anyhow::bail!("--sequence needs every template to include {SEQUENCE_KEY} with {{{{stream}}}}:{{{{seq}}}}");
+        }
+        if tags.timestamp
+            && !self
+                .templates
+                .iter()
+                .all(|t| t.has_marker(TIMESTAMP_KEY, |p| matches!(p, Part::Timestamp(TimestampFormat::EpochUs, _))))
+        {
+            anyhow::bail!("--latency needs every template to include {TIMESTAMP_KEY} with {{{{timestamp:epoch_us}}}}");
+        }
+        if let Some(sequence) = tags.sequence {
+            self.stream = sequence.stream().to_string();
+        }
+        Ok(self)
+    }
+}
+
+impl EventGenerator for TemplateEventGenerator {
+    fn generate_into(&mut self, buf: &mut Vec<u8>) {
+        let seq = self.message_index;
+        self.message_index += 1;
+        let idx = seq as usize % self.templates.len();
+        self.templates[idx].render_into(buf, seq, &self.stream, &mut self.rng);
+        buf.push(b'\n');
+    }
+}
+
+struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Whether the template writes `key` and a part rendering its value
+    fn has_marker(&self, key: &str, value: fn(&Part) -> bool) -> bool {
+        let has_key = self.parts.iter().any(|p| match p {
+            Part::Literal(text) => memchr::memmem::find(text, key.as_bytes()).is_some(),
+            _ => false,
+        });
+        has_key && self.parts.iter().any(value)
+    }
+}
+
+enum Part {
+    Literal(Vec<u8>),
+    /// Zero-based index of the event within this generator
+    Seq,
+    Stream,
+    Timestamp(TimestampFormat, TimestampCache),
+    Choice(Vec<Vec<u8>>),
+    Int(i64, i64),
+    Ipv4,
+    Uuid,
+    Hostname,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TimestampFormat {
+    /// `2024-04-27T00:12:49.030Z`
+    Rfc3339,
+    /// `Apr 27 00:12:49`
+    Rfc3164,
+    /// Seconds since the Unix epoch
+    Epoch,
+    /// Milliseconds since the Unix epoch
+    EpochMs,
+    /// Microseconds since the Unix epoch, as used for `protoglot_ts` latency markers
+    EpochUs,
+    /// Seconds since the Unix epoch with millisecond precision, as used by Splunk HEC
+    EpochFrac,
+    Strftime(String),
+}
+
+/// The last rendered timestamp, reused until the clock moves past the format's precision
+#[derive(Default)]
+struct TimestampCache {
+    key: Option<i64>,
+    rendered: Vec<u8>,
+}
+
+impl Template {
+    fn parse(line: &str) -> anyhow::Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = line;
+        while let Some(start) = rest.find("{{") {
+            // A brace right before a placeholder, like `{"n":{{seq}}}`, belongs to the literal
+            let start = start + rest[start..].bytes().skip(2).take_while(|&b| b == b'{').count();
+            literal.push_str(&rest[..start]);
+            let inner = &rest[start + 2..];
+            let end = inner
+                .find("}}")
+                .with_context(|| format!("unterminated placeholder '{{{{{inner}'"))?;
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal).into_bytes()));
+            }
+            parts.push(Part::parse(inner[..end].trim())?);
+            rest = &inner[end + 2..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal.into_bytes()));
+        }
+        Ok(Self { parts })
+    }
+
+    fn render_into(&mut self, buf: &mut Vec<u8>, seq: u64, stream: &str, rng: &mut fastrand::Rng) {
+        for part in &mut self.parts {
+            match part {
+                Part::Literal(literal) => buf.extend_from_slice(literal),
+                Part::Seq => {
+                    let _ = write!(buf, "{seq}");
+                }
+                Part::Stream => buf.extend_from_slice(stream.as_bytes()),
+                Part::Timestamp(format, cache) => format.render_into(buf, cache),
+                Part::Choice(choices) => buf.extend_from_slice(&choices[rng.usize(..choices.len())]),
+                Part::Int(lo, hi) => {
+                    let _ = write!(buf, "{}", rng.i64(*lo..=*hi));
+                }
+                Part::Ipv4 => {
+                    let _ = write!(buf, "{}.{}.{}.{}", rng.u8(1..=254), rng.u8(..), rng.u8(..), rng.u8(1..=254));
+                }
+                Part::Uuid => {
+                    let bytes = (rng.u128(..) & !(0xf000 << 64) | (0x4000 << 64)) & !(0xc << 60) | (0x8 << 60);
+                    let hex = format!("{bytes:032x}");
+                    let _ = write!(
+                        buf,
+                        "{}-{}-{}-{}-{}",
+                        &hex[..8],
+                        &hex[8..12],
+                        &hex[12..16],
+                        &hex[16..20],
+                        &hex[20..]
+                    );
+                }
+                Part::Hostname => {
+                    let word = HOSTNAME_WORDS[rng.usize(..HOSTNAME_WORDS.len())];
+                    let _ = write!(buf, "{word}-{:02}", rng.u8(1..=32));
+                }
+            }
+        }
+    }
+}
+
+impl Part {
+    fn parse(placeholder: &str) -> anyhow::Result<Self> {
+        let (name, arg) = match placeholder.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg)),
+            None => (placeholder, None),
+        };
+        let part = match (name, arg) {
+            ("seq", None) => Part::Seq,
+            ("stream", None) => Part::Stream,
+            ("timestamp", arg) => Part::Timestamp(TimestampFormat::parse(arg.unwrap_or("rfc3339"))?, TimestampCache::default()),
+            ("choice", Some(choices)) => Part::Choice(choices.split('|').map(|choice| choice.as_bytes().to_vec()).collect()),
+            ("int", Some(range)) => {
+                let (lo, hi) = range
+                    .split_once('-')
+                    .and_then(|(lo, hi)| Some((lo.trim().parse().ok()?, hi.trim().parse().ok()?)))
+                    .filter(|(lo, hi)| lo <= hi)
+                    .with_context(|| format!("invalid int range '{range}', expected e.g. 'int:1-100'"))?;
+                Part::Int(lo, hi)
+            }
+            ("ipv4" | "ip", None) => Part::Ipv4,
+            ("uuid", None) => Part::Uuid,
+            ("hostname", None) => Part::Hostname,
+            _ => anyhow::bail!("unknown placeholder '{{{{{placeholder}}}}}'"),
+        };
+        Ok(part)
+    }
+}
+
+impl TimestampFormat {
+    fn parse(format: &str) -> anyhow::Result<Self> {
+        let format = match format {
+            "rfc3339" | "iso8601" => Self::Rfc3339,
+            "rfc3164" | "syslog" => Self::Rfc3164,
+            "epoch" => Self::Epoch,
+            "epoch_ms" => Self::EpochMs,
+            "epoch_us" => Self::EpochUs,
+            "epoch_frac" => Self::EpochFrac,
+            format if format.contains('%') => {
+                strtime::format(format, &Timestamp::UNIX_EPOCH.to_zoned(TimeZone::UTC))
+                    .with_context(|| format!("invalid strftime format '{format}'"))?;
+                Self::Strftime(format.to_string())
+            }
+            _ => anyhow::bail!("unknown timestamp format '{format}'"),
+        };
+        Ok(format)
+    }
+
+    fn render_into(&self, buf: &mut Vec<u8>, cache: &mut TimestampCache) {
+        let now = Timestamp::now();
+        let key = match self {
+            Self::Epoch | Self::Rfc3164 => now.as_second(),
+            Self::EpochUs => now.as_microsecond(),
+            _ => now.as_millisecond(),
+        };
+        if cache.key != Some(key) {
+            cache.rendered.clear();
+            let truncated = Timestamp::from_millisecond(now.as_millisecond()).unwrap_or(now);
+            let _ = match self {
+                Self::Rfc3339 => write!(cache.rendered, "{truncated:.3}"),
+                Self::Rfc3164 => write!(cache.rendered, "{}", now.to_zoned(TimeZone::UTC).strftime("%b %e %H:%M:%S")),
+                Self::Epoch => write!(cache.rendered, "{}", now.as_second()),
+                Self::EpochMs => write!(cache.rendered, "{}", now.as_millisecond()),
+                Self::EpochUs => write!(cache.rendered, "{}", now.as_microsecond()),
+                Self::EpochFrac => write!(cache.rendered, "{}.{:03}", now.as_second(), now.subsec_millisecond()),
+                Self::Strftime(format) => write!(cache.rendered, "{}", now.to_zoned(TimeZone::UTC).strftime(format)),
+            };
+            cache.key = Some(key);
+        }
+        buf.extend_from_slice(&cache.rendered);
+    }
+}
+
+const HOSTNAME_WORDS: &[&str] = &[
+    "amber", "beach", "combat", "duet", "ecology", "flipper", "gemini", "ironic", "joker", "manual", "nadia", "plume", "rebel", "soprano",
+    "trilogy", "visible",
+];
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn render(source: &str, count: usize) -> Vec<String> {
+        let mut generator = TemplateEventGenerator::parse(source).unwrap();
+        (0..count)
+            .map(|_| {
+                let mut buf = Vec::new();
+                generator.generate_into(&mut buf);
+                String::from_utf8(buf).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renders_literals_and_sequence_numbers() {
+        let events = render("{\"n\":{{seq}},\"msg\":\"hi\"}\nplain {{ seq }}\n", 3);
+
+        assert_eq!(
+            events,
+            vec!["{\"n\":0,\"msg\":\"hi\"}\n", "plain 1\n", "{\"n\":2,\"msg\":\"hi\"}\n"]
+        );
+    }
+
+    #[test]
+    fn renders_random_placeholders_within_bounds() {
+        let events = render("{{choice:GET|POST}} {{int:10-12}} {{ipv4}} {{uuid}} {{hostname}}", 50);
+
+        for event in events {
+            let fields: Vec<_> = event.trim_end().split(' ').collect();
+            assert!(["GET", "POST"].contains(&fields[0]));
+            assert!((10..=12).contains(&fields[1].parse::<i64>().unwrap()));
+            assert!(fields[2].parse::<std::net::Ipv4Addr>().is_ok());
+            let uuid = fields[3];
+            assert_eq!(uuid.len(), 36);
+            assert_eq!(&uuid[14..15], "4");
+            assert!(["8", "9", "a", "b"].contains(&&uuid[19..20]));
+            assert!(fields[4].contains('-'));
+        }
+    }
+
+    #[test]
+    fn renders_timestamps() {
+        let events = render(
+            "{{timestamp}}|{{timestamp:rfc3164}}|{{timestamp:epoch}}|{{timestamp:epoch_ms}}|{{timestamp:epoch_frac}}|{{timestamp:%\
+             Y}}|{{timestamp:epoch_us}}",
+            1,
+        );
+        let fields: Vec<_> = events[0].trim_end().split('|').collect();
+
+        let rfc3339: Timestamp = fields[0].parse().unwrap();
+        assert!((Timestamp::now().as_second() - rfc3339.as_second()).abs() < 5);
+        assert_eq!(fields[1].len(), 15);
+        let epoch: i64 = fields[2].parse().unwrap();
+        assert!((epoch - rfc3339.as_second()).abs() <= 1);
+        assert_eq!(fields[3].len(), 13);
+        assert!(fields[4].parse::<f64>().is_ok());
+        assert_eq!(fields[5], rfc3339.to_zoned(TimeZone::UTC).year().to_string());
+        assert_eq!(fields[6].len(), 16);
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for source in ["{{nope}}", "{{int:5-1}}", "{{timestamp:bogus}}", "{{seq", "\n\n"] {
+            assert!(TemplateEventGenerator::parse(source).is_err(), "{source:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn rejects_tags_the_templates_cannot_carry() {
+        let sequence = || Tags {
+            sequence: Some(Sequence::new("feedbeef".to_string())),
+            ..Default::default()
+        };
+        let latency = || Tags {
+            timestamp: true,
+            ..Default::default()
+        };
+        let with_tags = |source: &str, tags: Tags| TemplateEventGenerator::parse(source).unwrap().with_tags(tags);
+
+        assert!(with_tags("plain {{seq}}", sequence()).is_err());
+        assert!(with_tags("protoglot_seq={{stream}}:{{seq}}\nplain", sequence()).is_err());
+        assert!(with_tags("protoglot_seq={{stream}}:{{seq}}", sequence()).is_ok());
+        assert!(with_tags("at {{timestamp:epoch_us}}", latency()).is_err());
+        assert!(with_tags("protoglot_ts={{timestamp:rfc3339}}", latency()).is_err());
+        assert!(with_tags("protoglot_ts={{timestamp:epoch_us}}", latency()).is_ok());
+        assert!(with_tags("plain", Tags::default()).is_ok());
+    }
+}
@@ -2,15 +2,21 @@ mod absorber;
 mod config;
 mod emitter;
 mod generators;
+mod metrics;
+mod schedule;
+mod stats;
 mod transports;
 
+use std::time::Duration;
+
 use clap::Parser as _;
-use config::{AppMode, MessageType};
-use emitter::{Emitter, EmitterConfig};
+use config::{AppMode, MessageType, RateScope};
+use emitter::{Emitter, EmitterConfig, PaceUnit, Pacer, ReconnectPolicy, StatsSvc, render_metrics};
 use generators::create_generator;
 use log::{error, info};
+use schedule::RateSchedule;
 use tokio::task::JoinSet;
-use transports::create_transport;
+use transports::{Transport as _, create_transport};
 
 use crate::{absorber::Absorber, config::AppSettings};
 
@@ -26,35 +32,81 @@ async fn main() -> anyhow::Result<()> {
         return Ok(());
     }
     if !log::log_enabled!(log::Level::Info) {
-        println!("Resolved configuration, starting... Use -v[vv] to see more logs");
+        // stderr, so stdout carries only stats, e.g. for `--stats-format json`
+        eprintln!("Resolved configuration, starting... Use -v[vv] to see more logs");
     }
     info!(config:serde; "Resolved configuration");
 
     let mut handles = JoinSet::new();
 
-    if let Some(emitter_config) = &config.emitter {
-        for _ in 0..emitter_config.num_emitters {
-            let transport = create_transport(emitter_config).await?;
-            let generator = create_generator(&emitter_config.message_type);
-            let emitter_config = EmitterConfig {
-                rate: emitter_config.rate,
-                num_cycles: emitter_config.num_cycles,
-                events_per_cycle: emitter_config.events_per_cycle,
-                cycle_delay: emitter_config.cycle_delay,
-                batch_size: match emitter_config.message_type {
-                    MessageType::SplunkHec => emitter_config.hec_batch_size,
-                    _ => 1,
-                },
+    if let Some(shared_config) = &config.emitter {
+        let mut emitter_metrics = Vec::new();
+        let streams: Vec<_> = match config.streams.is_empty() {
+            true => vec![("default", shared_config)],
+            false => config.streams.iter().map(|(name, stream)| (name.as_str(), stream)).collect(),
+        };
+        for (name, emitter_config) in streams {
+            let (schedule, unit) = match (emitter_config.bandwidth, &emitter_config.rate_schedule) {
+                (Some(bandwidth), _) => (RateSchedule::Constant(bandwidth), PaceUnit::Bytes),
+                (None, Some(schedule)) => (schedule.clone(), PaceUnit::Events),
+                (None, None) => (RateSchedule::Constant(emitter_config.rate), PaceUnit::Events),
             };
-            let mut emitter = Emitter::new(transport, generator, emitter_config);
+            let global_pacer = Pacer::shared(schedule.clone(), unit);
+            for _ in 0..emitter_config.num_emitters {
+                let transport = create_transport(emitter_config).await?;
+                let generator = create_generator(emitter_config)?;
+                let emitter_config = EmitterConfig {
+                    pacer: match emitter_config.rate_scope {
+                        RateScope::Global => global_pacer.clone(),
+                        RateScope::PerEmitter => Pacer::shared(schedule.clone(), unit),
+                    },
+                    num_cycles: emitter_config.num_cycles,
+                    events_per_cycle: emitter_config.events_per_cycle,
+                    cycle_delay: emitter_config.cycle_delay,
+                    duration: emitter_config.duration.map(Duration::from_millis),
+                    batch_size: match emitter_config.message_type {
+                        MessageType::SplunkHec => emitter_config.hec_batch_size,
+                        _ => 1,
+                    },
+                    reconnect: ReconnectPolicy::new(
+                        emitter_config.reconnect.clone(),
+                        emitter_config.reconnect_attempts,
+                        emitter_config.reconnect_backoff,
+                        emitter_config.reconnect_max_backoff,
+                        emitter_config.reconnect_buffer,
+                    ),
+                };
+                let mut emitter = Emitter::new(transport, generator, emitter_config);
+                emitter_metrics.push((emitter.transport.to_string(), emitter.metrics.clone()));
 
-            handles.spawn(async move {
-                match emitter.run().await {
-                    Ok(_) => {
-                        info!(emitter = emitter.transport.to_string(); "Emitter completed successfully");
-                        info!(total_events = emitter.total_events, total_bytes = emitter.total_bytes; "Totals");
+                let stream = name.to_string();
+                handles.spawn(async move {
+                    match emitter.run().await {
+                        Ok(_) => {
+                            info!(stream, emitter = emitter.transport.to_string(); "Emitter completed successfully");
+                            info!(
+                                total_events = emitter.total_events,
+                                total_bytes = emitter.total_bytes,
+                                compressed_bytes = emitter.transport.compressed_bytes(),
+                                reconnects = emitter.reconnects,
+                                dropped_events = emitter.dropped_events;
+                                "Totals"
+                            );
+                        }
+                        Err(err) => error!(stream; "Emitter failed: {}", err),
                     }
-                    Err(err) => error!("Emitter failed: {}", err),
+                });
+            }
+        }
+        if shared_config.update_interval > 0 {
+            let emitters = emitter_metrics.iter().map(|(_, metrics)| metrics.clone()).collect();
+            StatsSvc::run(emitters, shared_config.update_interval, shared_config.stats_format);
+        }
+        if let Some(addr) = shared_config.metrics.clone() {
+            // Not in `handles`, so finished emitters still let the process exit
+            tokio::spawn(async move {
+                if let Err(e) = metrics::serve(&addr, move || render_metrics(&emitter_metrics)).await {
+                    error!("Metrics endpoint on {addr} failed: {e}");
                 }
             });
         }
@@ -71,6 +123,6 @@ async fn main() -> anyhow::Result<()> {
 
     // wait for all emitters to complete
     handles.join_all().await;
-    println!("All emitters completed, exiting...");
+    eprintln!("All emitters completed, exiting...");
     Ok(())
 }
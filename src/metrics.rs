@@ -0,0 +1,149 @@
+use std::{convert::Infallible, fmt::Write as _, sync::Arc};
+
+use http_body_util::Full;
+use hyper::{Method, Request, Response, StatusCode, body::Bytes, header::CONTENT_TYPE, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use log::{debug, info};
+use tokio::net::TcpListener;
+
+const CONTENT_TYPE_PROMETHEUS: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Renders metrics in the Prometheus text exposition format
+#[derive(Default)]
+pub struct MetricsWriter {
+    out: String,
+}
+
+pub enum MetricType {
+    Counter,
+    Gauge,
+}
+
+impl MetricsWriter {
+    /// Write one metric family: its `HELP` and `TYPE` lines, then a sample per label set
+    pub fn family<'a>(
+        &mut self,
+        name: &str,
+        help: &str,
+        typ: MetricType,
+        samples: impl IntoIterator<Item = (&'a [(&'a str, &'a str)], u64)>,
+    ) {
+        let typ = match typ {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge",
+        };
+        let _ = writeln!(self.out, "# HELP {name} {help}");
+        let _ = writeln!(self.out, "# TYPE {name} {typ}");
+        for (labels, value) in samples {
+            self.out.push_str(name);
+            if !labels.is_empty() {
+                self.out.push('{');
+                for (idx, (key, value)) in labels.iter().enumerate() {
+                    if idx > 0 {
+                        self.out.push(',');
+                    }
+                    let _ = write!(self.out, "{key}=\"{}\"", escape_label(value));
+                }
+                self.out.push('}');
+            }
+            let _ = writeln!(self.out, " {value}");
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', "\\\"").replace('\n', r"\n")
+}
+
+/// Serve `GET /metrics` on `addr` until the task is dropped, rendering a fresh snapshot per scrape
+pub async fn serve(addr: &str, render: impl Fn() -> String + Send + Sync + 'static) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Serving Prometheus metrics on http://{}/metrics", listener.local_addr()?);
+    let render = Arc::new(render);
+    loop {
+        let (socket, remote_addr) = listener.accept().await?;
+        let render = render.clone();
+        tokio::spawn(async move {
+            let service = service_fn(|req: Request<hyper::body::Incoming>| {
+                let render = render.clone();
+                async move { Ok::<_, Infallible>(respond(&req, || render())) }
+            });
+            if let Err(err) = http1::Builder::new().serve_connection(TokioIo::new(socket), service).await {
+                debug!("Error serving metrics to {remote_addr}: {err}");
+            }
+        });
+    }
+}
+
+fn respond(req: &Request<hyper::body::Incoming>, render: impl FnOnce() -> String) -> Response<Full<Bytes>> {
+    let builder = Response::builder();
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => builder
+            .header(CONTENT_TYPE, CONTENT_TYPE_PROMETHEUS)
+            .body(Full::new(Bytes::from(render()))),
+        (_, "/metrics") => builder.status(StatusCode::METHOD_NOT_ALLOWED).body(Full::default()),
+        _ => builder.status(StatusCode::NOT_FOUND).body(Full::default()),
+    };
+    response.expect("valid metrics response")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn renders_text_exposition_format() {
+        let mut writer = MetricsWriter::default();
+        writer.family(
+            "protoglot_events_total",
+            "Events received.",
+            MetricType::Counter,
+            [
+                (&[("listener", "tcp://0.0.0.0:9514")][..], 42),
+                (&[("listener", "odd \"name\"\\\n")][..], 0),
+            ],
+        );
+        writer.family("protoglot_up", "Always 1.", MetricType::Gauge, [(&[][..], 1)]);
+
+        assert_eq!(
+            writer.finish(),
+            concat!(
+                "# HELP protoglot_events_total Events received.\n",
+                "# TYPE protoglot_events_total counter\n",
+                "protoglot_events_total{listener=\"tcp://0.0.0.0:9514\"} 42\n",
+                "protoglot_events_total{listener=\"odd \\\"name\\\"\\\\\\n\"} 0\n",
+                "# HELP protoglot_up Always 1.\n",
+                "# TYPE protoglot_up gauge\n",
+                "protoglot_up 1\n",
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_metrics_over_http() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let server = tokio::spawn({
+            let addr = addr.clone();
+            async move { serve(&addr, || "protoglot_up 1\n".to_string()).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let client = reqwest::Client::new();
+        let res = client.get(format!("http://{addr}/metrics")).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()[CONTENT_TYPE], CONTENT_TYPE_PROMETHEUS);
+        assert_eq!(res.text().await.unwrap(), "protoglot_up 1\n");
+        let res = client.get(format!("http://{addr}/other")).send().await.unwrap();
+        assert_eq!(res.status(), 404);
+
+        server.abort();
+    }
+}
@@ -0,0 +1,280 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::cli::parse_duration;
+
+/// How an emitter's target rate changes over the run, in events per second
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RateSchedule {
+    /// A fixed rate. 0 means as fast as possible, which is only reachable through `--rate unlimited`,
+    /// as 0 anywhere in a `--rate-schedule` means sending nothing.
+    Constant(u64),
+    /// Linear from one rate to another, then held
+    Ramp { from: u64, to: u64, over: Duration },
+    /// Each rate for its duration in turn, then the last one held
+    Steps(Vec<(Duration, u64)>),
+    /// A sine wave starting at its trough, e.g. with a 24h period for a daily pattern
+    Sine { min: u64, max: u64, period: Duration },
+    /// Bursts at a higher rate, starting at exponentially distributed intervals
+    Poisson {
+        base: u64,
+        burst: u64,
+        every: Duration,
+        length: Duration,
+    },
+}
+
+impl FromStr for RateSchedule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let rate = |s: &str| s.parse::<u64>().map_err(|_| format!("Invalid rate '{s}' in schedule '{value}'"));
+        let duration = |s: &str| parse_duration(s).map(Duration::from_millis);
+        let (kind, args) = value.split_once(':').unwrap_or((value, ""));
+        let parts: Vec<_> = args.split(':').collect();
+        let schedule = match (kind, parts.as_slice()) {
+            ("constant", [r]) => Self::Constant(rate(r)?),
+            ("ramp", [from, to, over]) => Self::Ramp {
+                from: rate(from)?,
+                to: rate(to)?,
+                over: duration(over)?,
+            },
+            ("steps", [steps]) => Self::Steps(
+                steps
+                    .split(',')
+                    .map(|step| {
+                        let (time, r) = step
+                            .split_once('=')
+                            .ok_or_else(|| format!("Invalid step '{step}', expected DURATION=RATE"))?;
+                        Ok((duration(time)?, rate(r)?))
+                    })
+                    .collect::<Result<_, String>>()?,
+            ),
+            ("sine", [min, max, period]) => Self::Sine {
+                min: rate(min)?,
+                max: rate(max)?,
+                period: duration(period)?,
+            },
+            ("poisson", [base, burst, every, length]) => Self::Poisson {
+                base: rate(base)?,
+                burst: rate(burst)?,
+                every: duration(every)?,
+                length: duration(length)?,
+            },
+            _ => {
+                return Err(format!(
+                    "Invalid rate schedule '{value}', expected constant:RATE, ramp:FROM:TO:DURATION, steps:DURATION=RATE,..., \
+                     sine:MIN:MAX:PERIOD or poisson:BASE:BURST:EVERY:LENGTH"
+                ));
+            }
+        };
+        match &schedule {
+            Self::Constant(0) => Err(format!(
+                "Rate schedule '{value}' would send nothing, use --rate unlimited to send as fast as possible"
+            )),
+            Self::Ramp { over: period, .. } | Self::Sine { period, .. } | Self::Poisson { every: period, .. } if period.is_zero() => {
+                Err(format!("Rate schedule '{value}' needs a non-zero duration"))
+            }
+            Self::Steps(steps) if steps.iter().any(|(time, _)| time.is_zero()) => {
+                Err(format!("Rate schedule '{value}' needs non-zero step durations"))
+            }
+            _ => Ok(schedule),
+        }
+    }
+}
+
+impl TryFrom<String> for RateSchedule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RateSchedule> for String {
+    fn from(value: RateSchedule) -> Self {
+        value.to_string()
+    }
+}
+
+/// Durations in the largest unit that keeps them whole, as `parse_duration` reads them
+struct Time(Duration);
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = self.0.as_millis();
+        match ms {
+            0 => write!(f, "0ms"),
+            _ if ms.is_multiple_of(3_600_000) => write!(f, "{}h", ms / 3_600_000),
+            _ if ms.is_multiple_of(60_000) => write!(f, "{}m", ms / 60_000),
+            _ if ms.is_multiple_of(1000) => write!(f, "{}s", ms / 1000),
+            _ => write!(f, "{ms}ms"),
+        }
+    }
+}
+
+impl fmt::Display for RateSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Constant(rate) => write!(f, "constant:{rate}"),
+            Self::Ramp { from, to, over } => write!(f, "ramp:{from}:{to}:{}", Time(*over)),
+            Self::Steps(steps) => {
+                let steps: Vec<_> = steps.iter().map(|(time, rate)| format!("{}={rate}", Time(*time))).collect();
+                write!(f, "steps:{}", steps.join(","))
+            }
+            Self::Sine { min, max, period } => write!(f, "sine:{min}:{max}:{}", Time(*period)),
+            Self::Poisson {
+                base,
+                burst,
+                every,
+                length,
+            } => {
+                write!(f, "poisson:{base}:{burst}:{}:{}", Time(*every), Time(*length))
+            }
+        }
+    }
+}
+
+/// Follows a schedule through a run, keeping the state random bursts need
+pub struct RateShaper {
+    schedule: RateSchedule,
+    next_burst: Duration,
+    burst_end: Duration,
+}
+
+impl RateShaper {
+    pub fn new(schedule: RateSchedule) -> Self {
+        let mut shaper = Self {
+            schedule,
+            next_burst: Duration::ZERO,
+            burst_end: Duration::ZERO,
+        };
+        shaper.next_burst = shaper.burst_gap();
+        shaper
+    }
+
+    /// Whether sends should go out unpaced
+    pub fn is_unlimited(&self) -> bool {
+        self.schedule == RateSchedule::Constant(0)
+    }
+
+    /// Events per second due at this point in the run
+    pub fn rate_at(&mut self, elapsed: Duration) -> f64 {
+        match &self.schedule {
+            RateSchedule::Constant(rate) => *rate as f64,
+            RateSchedule::Ramp { from, to, over } => {
+                let progress = (elapsed.as_secs_f64() / over.as_secs_f64()).min(1.0);
+                *from as f64 + (*to as f64 - *from as f64) * progress
+            }
+            RateSchedule::Steps(steps) => {
+                let mut end = Duration::ZERO;
+                for (time, rate) in steps {
+                    end += *time;
+                    if elapsed < end {
+                        return *rate as f64;
+                    }
+                }
+                steps.last().map_or(0.0, |(_, rate)| *rate as f64)
+            }
+            RateSchedule::Sine { min, max, period } => {
+                let phase = std::f64::consts::TAU * elapsed.as_secs_f64() / period.as_secs_f64();
+                *min as f64 + (*max as f64 - *min as f64) * (1.0 - phase.cos()) / 2.0
+            }
+            RateSchedule::Poisson { base, burst, length, .. } => {
+                let (base, burst, length) = (*base, *burst, *length);
+                while elapsed >= self.next_burst {
+                    self.burst_end = self.next_burst + length;
+                    self.next_burst = self.burst_end + self.burst_gap();
+                }
+                match elapsed < self.burst_end {
+                    true => burst as f64,
+                    false => base as f64,
+                }
+            }
+        }
+    }
+
+    /// Time from the end of one burst to the start of the next, exponentially distributed so
+    /// bursts form a Poisson process
+    fn burst_gap(&self) -> Duration {
+        match &self.schedule {
+            RateSchedule::Poisson { every, .. } => every.mul_f64(-(1.0 - fastrand::f64()).ln()),
+            _ => Duration::MAX,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn secs(secs: f64) -> Duration {
+        Duration::from_secs_f64(secs)
+    }
+
+    #[test]
+    fn round_trips_specs() {
+        for spec in [
+            "constant:500",
+            "ramp:1000:50000:10m",
+            "ramp:5000:0:90s",
+            "steps:30s=1000,1m=5000,1500ms=0",
+            "sine:500:5000:24h",
+            "poisson:1000:20000:1m:5s",
+        ] {
+            let schedule: RateSchedule = spec.parse().unwrap();
+            assert_eq!(schedule.to_string(), spec);
+        }
+        for bad in [
+            "ramp:1:2",
+            "steps:",
+            "steps:30s",
+            "sine:1:2:0s",
+            "poisson:1:2:3s:x",
+            "wobble:1",
+            "constant:0",
+        ] {
+            assert!(bad.parse::<RateSchedule>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn ramps_then_holds() {
+        let mut up = RateShaper::new("ramp:1000:5000:4s".parse().unwrap());
+        assert_eq!(up.rate_at(secs(0.0)), 1000.0);
+        assert_eq!(up.rate_at(secs(1.0)), 2000.0);
+        assert_eq!(up.rate_at(secs(10.0)), 5000.0);
+
+        let mut down = RateShaper::new("ramp:5000:1000:4s".parse().unwrap());
+        assert_eq!(down.rate_at(secs(3.0)), 2000.0);
+    }
+
+    #[test]
+    fn steps_through_rates() {
+        let mut shaper = RateShaper::new("steps:1s=100,2s=300".parse().unwrap());
+        let rates: Vec<_> = [0.0, 0.9, 1.0, 2.9, 3.0, 60.0].map(|t| shaper.rate_at(secs(t))).into();
+        assert_eq!(rates, [100.0, 100.0, 300.0, 300.0, 300.0, 300.0]);
+    }
+
+    #[test]
+    fn follows_a_sine_wave() {
+        let mut shaper = RateShaper::new("sine:1000:3000:4s".parse().unwrap());
+        let rates: Vec<_> = [0.0, 1.0, 2.0, 3.0, 4.0].map(|t| shaper.rate_at(secs(t)).round()).into();
+        assert_eq!(rates, [1000.0, 2000.0, 3000.0, 2000.0, 1000.0]);
+    }
+
+    #[test]
+    fn bursts_about_as_often_as_asked() {
+        fastrand::seed(42);
+        let mut shaper = RateShaper::new("poisson:100:1000:10s:1s".parse().unwrap());
+        let samples: Vec<_> = (0..100_000).map(|ms| shaper.rate_at(Duration::from_millis(ms * 10))).collect();
+        let bursting = samples.iter().filter(|&&rate| rate == 1000.0).count() as f64 / samples.len() as f64;
+        // One second of burst per eleven, on average
+        assert!((bursting - 1.0 / 11.0).abs() < 0.02, "{bursting}");
+        assert!(samples.iter().all(|&rate| rate == 100.0 || rate == 1000.0));
+    }
+}
@@ -0,0 +1,107 @@
+use hdrhistogram::Histogram;
+use serde::Serialize;
+
+/// Longest latency tracked; anything slower is recorded as this
+const MAX_LATENCY_MICROS: u64 = 3_600_000_000;
+
+/// Latency percentiles, in microseconds
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LatencySummary {
+    pub(crate) samples: u64,
+    pub(crate) p50: u64,
+    pub(crate) p90: u64,
+    pub(crate) p99: u64,
+    pub(crate) max: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct LatencyHistogram {
+    histogram: Histogram<u64>,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            histogram: Histogram::new_with_bounds(1, MAX_LATENCY_MICROS, 3).expect("valid histogram bounds"),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub(crate) fn record(&mut self, micros: u64) {
+        self.histogram.saturating_record(micros.clamp(1, MAX_LATENCY_MICROS));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.histogram.is_empty()
+    }
+
+    /// Fold another histogram's samples into this one
+    pub(crate) fn add(&mut self, other: &Self) {
+        self.histogram.add(&other.histogram).expect("histograms share bounds");
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.histogram.reset();
+    }
+
+    pub(crate) fn summary(&self) -> LatencySummary {
+        LatencySummary {
+            samples: self.histogram.len(),
+            p50: self.histogram.value_at_quantile(0.5),
+            p90: self.histogram.value_at_quantile(0.9),
+            p99: self.histogram.value_at_quantile(0.99),
+            max: self.histogram.max(),
+        }
+    }
+}
+
+pub(crate) fn human_events(events: f64) -> String {
+    if events < 1_000.0 {
+        events.to_string()
+    } else if events < 1_000_000.0 {
+        format!("{:.1}k", events / 1_000.0)
+    } else if events < 1_000_000_000.0 {
+        format!("{:.1}M", events / 1_000_000.0)
+    } else {
+        format!("{:.1}B", events / 1_000_000_000.0)
+    }
+}
+
+pub(crate) fn human_latency(micros: u64) -> String {
+    if micros < 1_000 {
+        format!("{micros}µs")
+    } else if micros < 1_000_000 {
+        format!("{:.1}ms", micros as f64 / 1_000.0)
+    } else {
+        format!("{:.2}s", micros as f64 / 1_000_000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn summarizes_percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        assert!(histogram.is_empty());
+        for micros in 1..=1000 {
+            histogram.record(micros);
+        }
+        histogram.record(u64::MAX);
+
+        let summary = histogram.summary();
+        assert_eq!(summary.samples, 1001);
+        assert!((499..=501).contains(&summary.p50), "{summary:?}");
+        assert!((899..=901).contains(&summary.p90), "{summary:?}");
+        assert!((989..=991).contains(&summary.p99), "{summary:?}");
+        assert!(summary.max >= MAX_LATENCY_MICROS, "{summary:?}");
+
+        histogram.clear();
+        assert!(histogram.is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+use std::io::Write as _;
+
+use async_compression::tokio::write::{BrotliEncoder, GzipEncoder, Lz4Encoder, ZstdEncoder};
+use tokio::io::AsyncWriteExt as _;
+
+use crate::config::Compression;
+
+/// Compressor writing into an in-memory buffer, so the compressed size of every batch is known
+/// before it touches the network
+enum Encoder {
+    Gzip(GzipEncoder<Vec<u8>>),
+    Zstd(ZstdEncoder<Vec<u8>>),
+    Lz4(Lz4Encoder<Vec<u8>>),
+    Snappy(Box<snap::write::FrameEncoder<Vec<u8>>>),
+    Brotli(Box<BrotliEncoder<Vec<u8>>>),
+}
+
+impl Encoder {
+    fn new(compression: Compression) -> Option<Self> {
+        let buf = Vec::with_capacity(1024);
+        match compression {
+            Compression::None => None,
+            Compression::Gzip => Some(Self::Gzip(GzipEncoder::new(buf))),
+            Compression::Zstd => Some(Self::Zstd(ZstdEncoder::new(buf))),
+            Compression::Lz4 => Some(Self::Lz4(Lz4Encoder::new(buf))),
+            Compression::Snappy => Some(Self::Snappy(Box::new(snap::write::FrameEncoder::new(buf)))),
+            Compression::Brotli => Some(Self::Brotli(Box::new(BrotliEncoder::new(buf)))),
+        }
+    }
+
+    /// Compress `data` and flush, so everything written so far can be decoded by the receiver
+    async fn write_and_flush(&mut self, data: &[u8]) -> tokio::io::Result<()> {
+        match self {
+            Self::Gzip(encoder) => write_and_flush(encoder, data).await,
+            Self::Zstd(encoder) => write_and_flush(encoder, data).await,
+            Self::Lz4(encoder) => write_and_flush(encoder, data).await,
+            Self::Brotli(encoder) => write_and_flush(encoder.as_mut(), data).await,
+            Self::Snappy(encoder) => {
+                encoder.write_all(data)?;
+                encoder.flush()
+            }
+        }
+    }
+
+    async fn finish(self) -> tokio::io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(mut encoder) => encoder.shutdown().await.map(|_| encoder.into_inner()),
+            Self::Zstd(mut encoder) => encoder.shutdown().await.map(|_| encoder.into_inner()),
+            Self::Lz4(mut encoder) => encoder.shutdown().await.map(|_| encoder.into_inner()),
+            Self::Brotli(mut encoder) => encoder.shutdown().await.map(|_| encoder.into_inner()),
+            Self::Snappy(encoder) => encoder.into_inner().map_err(|e| e.into_error()),
+        }
+    }
+
+    fn output(&mut self) -> &mut Vec<u8> {
+        match self {
+            Self::Gzip(encoder) => encoder.get_mut(),
+            Self::Zstd(encoder) => encoder.get_mut(),
+            Self::Lz4(encoder) => encoder.get_mut(),
+            Self::Brotli(encoder) => encoder.get_mut(),
+            Self::Snappy(encoder) => encoder.get_mut(),
+        }
+    }
+}
+
+async fn write_and_flush<W: tokio::io::AsyncWrite + Unpin>(encoder: &mut W, data: &[u8]) -> tokio::io::Result<()> {
+    encoder.write_all(data).await?;
+    encoder.flush().await
+}
+
+/// Compresses a long-lived stream one batch at a time. Each batch is flushed so the receiver can
+/// decode it without waiting for the stream to end.
+pub struct StreamEncoder {
+    compression: Compression,
+    encoder: Option<Box<Encoder>>,
+    compressed_bytes: u64,
+}
+
+impl StreamEncoder {
+    pub fn new(compression: Compression) -> Self {
+        Self {
+            compression,
+            encoder: Encoder::new(compression).map(Box::new),
+            compressed_bytes: 0,
+        }
+    }
+
+    /// Record `len` encoded bytes as delivered to the transport
+    pub fn sent(&mut self, len: usize) {
+        self.compressed_bytes += len as u64;
+    }
+
+    /// Total compressed bytes sent, or `None` when the stream isn't compressed
+    pub fn compressed_bytes(&self) -> Option<u64> {
+        self.encoder.as_ref().map(|_| self.compressed_bytes)
+    }
+
+    /// Start a new compressed stream, as needed after reconnecting
+    pub fn reset(&mut self) {
+        self.encoder = Encoder::new(self.compression).map(Box::new);
+    }
+
+    /// Bytes to put on the wire for `data`, which is passed through untouched without compression
+    pub async fn encode<'a>(&'a mut self, data: &'a [u8]) -> tokio::io::Result<&'a [u8]> {
+        let Some(encoder) = self.encoder.as_mut() else {
+            return Ok(data);
+        };
+        encoder.output().clear();
+        encoder.write_and_flush(data).await?;
+        Ok(encoder.output())
+    }
+}
+
+/// Compress `data` as one complete stream, e.g. for an HTTP body
+pub async fn compress(compression: Compression, data: &[u8]) -> tokio::io::Result<Vec<u8>> {
+    let Some(mut encoder) = Encoder::new(compression) else {
+        return Ok(data.to_vec());
+    };
+    encoder.write_and_flush(data).await?;
+    encoder.finish().await
+}
+
+/// `Content-Encoding` value understood by the HTTP absorber
+pub fn content_encoding(compression: Compression) -> Option<&'static str> {
+    match compression {
+        Compression::None => None,
+        Compression::Gzip => Some("gzip"),
+        Compression::Zstd => Some("zstd"),
+        Compression::Lz4 => Some("lz4"),
+        Compression::Snappy => Some("snappy"),
+        Compression::Brotli => Some("br"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_compression::tokio::bufread::{BrotliDecoder, GzipDecoder, Lz4Decoder, ZstdDecoder};
+    use tokio::io::AsyncReadExt as _;
+    use tokio_snappy::SnappyIO;
+
+    use super::*;
+
+    /// Read `len` decompressed bytes, which works on streams that were flushed but never finished
+    async fn decompress(compression: Compression, data: &[u8], len: usize) -> Vec<u8> {
+        let mut out = vec![0; len];
+        match compression {
+            Compression::None => out.copy_from_slice(data),
+            Compression::Gzip => GzipDecoder::new(data).read_exact(&mut out).await.map(|_| ()).unwrap(),
+            Compression::Zstd => ZstdDecoder::new(data).read_exact(&mut out).await.map(|_| ()).unwrap(),
+            Compression::Lz4 => Lz4Decoder::new(data).read_exact(&mut out).await.map(|_| ()).unwrap(),
+            Compression::Snappy => SnappyIO::new(data).read_exact(&mut out).await.map(|_| ()).unwrap(),
+            Compression::Brotli => BrotliDecoder::new(data).read_exact(&mut out).await.map(|_| ()).unwrap(),
+        }
+        out
+    }
+
+    const ALL: [Compression; 6] = [
+        Compression::None,
+        Compression::Gzip,
+        Compression::Zstd,
+        Compression::Lz4,
+        Compression::Snappy,
+        Compression::Brotli,
+    ];
+
+    #[tokio::test]
+    async fn compress_round_trips() {
+        let data = b"<13>1 2024-01-01T00:00:00Z host app - - - hello\n".repeat(50);
+        for compression in ALL {
+            let compressed = compress(compression, &data).await.unwrap();
+            if compression != Compression::None {
+                assert!(compressed.len() < data.len(), "{compression} did not shrink the payload");
+            }
+            assert_eq!(decompress(compression, &compressed, data.len()).await, data, "{compression}");
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_batches_concatenate_into_one_stream() {
+        for compression in ALL {
+            let mut encoder = StreamEncoder::new(compression);
+            let mut wire = Vec::new();
+            let mut expected = Vec::new();
+            for i in 0..10 {
+                let batch = format!("event-{i}\n").into_bytes();
+                wire.extend_from_slice(encoder.encode(&batch).await.unwrap());
+                expected.extend_from_slice(&batch);
+            }
+            assert_eq!(decompress(compression, &wire, expected.len()).await, expected, "{compression}");
+        }
+    }
+}
@@ -1,38 +1,136 @@
-use std::fmt;
+use std::{fmt, net::ToSocketAddrs as _};
 
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use anyhow::Context as _;
+use base64::prelude::*;
+use reqwest::{
+    Method,
+    header::{AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue},
+};
 
-use super::Transport;
+use super::{Transport, compression, tls::TlsOptions};
+use crate::config::{
+    Compression, EmitterConfig, HttpMethod, MessageType,
+    absorber::{self, HttpAuth},
+};
+
+/// Request shape for the HTTP emitter, resolved from config and message type defaults
+#[derive(Clone, Debug)]
+pub struct HttpOptions {
+    pub path: String,
+    pub method: Method,
+    /// Sent with every request, including Content-Type and Authorization
+    pub headers: HeaderMap,
+    pub compression: Compression,
+}
+
+impl TryFrom<&EmitterConfig> for HttpOptions {
+    type Error = anyhow::Error;
+
+    fn try_from(config: &EmitterConfig) -> Result<Self, Self::Error> {
+        let path = config.http_path.clone().unwrap_or_else(|| match config.message_type {
+            MessageType::SplunkHec => "/services/collector/event".to_string(),
+            _ => "/".to_string(),
+        });
+        let method = match config.http_method {
+            HttpMethod::Post => Method::POST,
+            HttpMethod::Put => Method::PUT,
+            HttpMethod::Patch => Method::PATCH,
+        };
+
+        let mut headers = HeaderMap::new();
+        let content_type = config.http_content_type.as_deref().unwrap_or(match config.message_type {
+            MessageType::SplunkHec => "application/json",
+            MessageType::NdJson => "application/x-ndjson",
+            MessageType::Syslog3164 | MessageType::Syslog5424 | MessageType::Syslog5424Octet | MessageType::Template => "text/plain",
+        });
+        headers.insert(CONTENT_TYPE, HeaderValue::from_str(content_type)?);
+        if let Some(encoding) = compression::content_encoding(config.compression) {
+            headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
+
+        let auth = config.http_auth.clone().unwrap_or(match config.message_type {
+            MessageType::SplunkHec => HttpAuth::Splunk,
+            _ => HttpAuth::None,
+        });
+        let credentials = config.http_credentials.as_deref();
+        let authorization = match auth {
+            HttpAuth::None => None,
+            HttpAuth::Basic => {
+                let credentials = credentials.map_or_else(|| format!("{}:{}", absorber::USERNAME, absorber::PASSWORD), str::to_string);
+                Some(format!("Basic {}", BASE64_STANDARD.encode(credentials)))
+            }
+            HttpAuth::Token => Some(format!("Bearer {}", credentials.unwrap_or(absorber::TOKEN))),
+            HttpAuth::Splunk => Some(format!("Splunk {}", credentials.unwrap_or(&config.hec_token))),
+        };
+        if let Some(authorization) = authorization {
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(&authorization)?);
+        }
+
+        for (name, value) in &config.http_headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).with_context(|| format!("Invalid HTTP header name '{name}'"))?;
+            headers.insert(name, HeaderValue::from_str(value)?);
+        }
+
+        Ok(Self {
+            path,
+            method,
+            headers,
+            compression: config.compression,
+        })
+    }
+}
 
 pub struct HttpTransport {
     client: reqwest::Client,
     url: String,
-    hec_token: Option<String>,
+    method: Method,
+    compression: Compression,
+    compressed_bytes: u64,
 }
 
 impl HttpTransport {
-    pub fn new(protocol: &str, fqdn: String, port: u16, hec_token: Option<String>) -> anyhow::Result<Self> {
-        let client = reqwest::Client::builder().build()?;
-        let url = format!("{protocol}://{fqdn}:{port}/services/collector/event");
+    pub fn new(protocol: &str, fqdn: String, port: u16, opts: HttpOptions, tls: &TlsOptions) -> anyhow::Result<Self> {
+        let mut builder = reqwest::Client::builder().default_headers(opts.headers);
+        let mut host = fqdn;
+        if protocol == "https" && tls.is_customized() {
+            let mut config = tls.client_config()?;
+            config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+            builder = builder.tls_backend_preconfigured(config);
 
-        Ok(Self { client, url, hec_token })
+            // reqwest derives SNI from the URL, so address the server by its TLS name and pin
+            // that name to the real target address
+            if let Some(server_name) = &tls.server_name {
+                let addr = (host.as_str(), port)
+                    .to_socket_addrs()?
+                    .next()
+                    .context("Failed to resolve socket address")?;
+                builder = builder.resolve(server_name, addr);
+                host = server_name.clone();
+            }
+        }
+        let client = builder.build()?;
+        let path = opts.path.trim_start_matches('/');
+        let url = format!("{protocol}://{host}:{port}/{path}");
+
+        Ok(Self {
+            client,
+            url,
+            method: opts.method,
+            compression: opts.compression,
+            compressed_bytes: 0,
+        })
     }
 }
 
 impl Transport for HttpTransport {
     async fn send(&mut self, data: &[u8]) -> tokio::io::Result<()> {
-        let mut request = self
-            .client
-            .post(&self.url)
-            .header(CONTENT_TYPE, "application/json")
-            .body(data.to_vec());
-
-        if let Some(token) = &self.hec_token {
-            request = request.header(AUTHORIZATION, format!("Splunk {token}"));
-        }
+        let body = compression::compress(self.compression, data).await?;
+        let body_len = body.len() as u64;
+        let request = self.client.request(self.method.clone(), &self.url).body(body);
 
         let response = request.send().await.map_err(tokio::io::Error::other)?;
         if response.status().is_success() {
+            self.compressed_bytes += body_len;
             Ok(())
         } else {
             Err(tokio::io::Error::other(format!(
@@ -42,6 +140,10 @@ impl Transport for HttpTransport {
             )))
         }
     }
+
+    fn compressed_bytes(&self) -> Option<u64> {
+        (self.compression != Compression::None).then_some(self.compressed_bytes)
+    }
 }
 
 impl fmt::Display for HttpTransport {
@@ -59,8 +161,7 @@ mod tests {
 
     use super::*;
 
-    #[tokio::test]
-    async fn sends_json_post_with_splunk_authorization() {
+    async fn capture_one_request() -> (u16, tokio::task::JoinHandle<String>) {
         let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
         let port = listener.local_addr().unwrap().port();
 
@@ -69,10 +170,22 @@ mod tests {
             let mut buf = vec![0; 4096];
             let len = socket.read(&mut buf).await.unwrap();
             socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nOK").await.unwrap();
-            String::from_utf8(buf[..len].to_vec()).unwrap()
+            String::from_utf8_lossy(&buf[..len]).into_owned()
         });
+        (port, server)
+    }
+
+    #[tokio::test]
+    async fn sends_json_post_with_splunk_authorization() {
+        let (port, server) = capture_one_request().await;
 
-        let mut transport = HttpTransport::new("http", "127.0.0.1".to_string(), port, Some("test-token".to_string())).unwrap();
+        let config = EmitterConfig {
+            message_type: MessageType::SplunkHec,
+            hec_token: "test-token".to_string(),
+            ..Default::default()
+        };
+        let opts = HttpOptions::try_from(&config).unwrap();
+        let mut transport = HttpTransport::new("http", "127.0.0.1".to_string(), port, opts, &TlsOptions::default()).unwrap();
         transport.send(b"{\"event\":\"hello\"}\n").await.unwrap();
 
         let request = server.await.unwrap();
@@ -81,4 +194,64 @@ mod tests {
         assert!(request.contains("content-type: application/json"));
         assert!(request.ends_with("{\"event\":\"hello\"}\n"));
     }
+
+    #[tokio::test]
+    async fn sends_configured_path_method_headers_and_auth() {
+        let (port, server) = capture_one_request().await;
+
+        let config = EmitterConfig {
+            message_type: MessageType::NdJson,
+            http_path: Some("_bulk".to_string()),
+            http_method: HttpMethod::Put,
+            http_headers: [("X-Pipeline".to_string(), "ingest".to_string())].into(),
+            http_auth: Some(HttpAuth::Basic),
+            http_credentials: Some("user:pass".to_string()),
+            ..Default::default()
+        };
+        let opts = HttpOptions::try_from(&config).unwrap();
+        let mut transport = HttpTransport::new("http", "127.0.0.1".to_string(), port, opts, &TlsOptions::default()).unwrap();
+        transport.send(b"{\"hello\":\"world\"}\n").await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("PUT /_bulk HTTP/1.1"));
+        assert!(request.contains("authorization: Basic dXNlcjpwYXNz"));
+        assert!(request.contains("content-type: application/x-ndjson"));
+        assert!(request.contains("x-pipeline: ingest"));
+    }
+
+    #[tokio::test]
+    async fn compresses_body_and_sets_content_encoding() {
+        let (port, server) = capture_one_request().await;
+
+        let config = EmitterConfig {
+            message_type: MessageType::NdJson,
+            compression: Compression::Gzip,
+            ..Default::default()
+        };
+        let opts = HttpOptions::try_from(&config).unwrap();
+        let mut transport = HttpTransport::new("http", "127.0.0.1".to_string(), port, opts, &TlsOptions::default()).unwrap();
+        let body = b"{\"hello\":\"world\"}\n".repeat(20);
+        transport.send(&body).await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.contains("content-encoding: gzip"));
+        let compressed = transport.compressed_bytes().unwrap();
+        assert!(compressed > 0 && compressed < body.len() as u64);
+        assert!(request.contains(&format!("content-length: {compressed}")));
+    }
+
+    #[test]
+    fn defaults_to_root_path_without_auth_for_non_hec() {
+        let config = EmitterConfig {
+            message_type: MessageType::Syslog5424,
+            ..Default::default()
+        };
+
+        let opts = HttpOptions::try_from(&config).unwrap();
+
+        assert_eq!(opts.path, "/");
+        assert_eq!(opts.method, Method::POST);
+        assert_eq!(opts.headers[CONTENT_TYPE], "text/plain");
+        assert!(opts.headers.get(AUTHORIZATION).is_none());
+    }
 }
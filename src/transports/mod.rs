@@ -2,11 +2,13 @@ use std::fmt;
 
 use log::error;
 
-use crate::config::{EmitterConfig, MessageType, Protocol};
+use crate::config::{Compression, EmitterConfig, Protocol};
 
+pub mod compression;
 pub mod http;
 pub mod tcp;
 pub mod tcp_tls;
+pub mod tls;
 pub mod udp;
 
 pub enum TransportType {
@@ -25,10 +27,39 @@ impl Transport for TransportType {
             TransportType::Http(transport) => transport.send(data).await,
         }
     }
+
+    async fn reconnect(&mut self) -> tokio::io::Result<()> {
+        match self {
+            TransportType::Tcp(transport) => transport.reconnect().await,
+            TransportType::TcpTls(transport) => transport.reconnect().await,
+            TransportType::Udp(transport) => transport.reconnect().await,
+            TransportType::Http(transport) => transport.reconnect().await,
+        }
+    }
+
+    fn compressed_bytes(&self) -> Option<u64> {
+        match self {
+            TransportType::Tcp(transport) => transport.compressed_bytes(),
+            TransportType::TcpTls(transport) => transport.compressed_bytes(),
+            TransportType::Udp(transport) => transport.compressed_bytes(),
+            TransportType::Http(transport) => transport.compressed_bytes(),
+        }
+    }
 }
 
 pub trait Transport: Send {
     fn send(&mut self, data: &[u8]) -> impl std::future::Future<Output = tokio::io::Result<()>> + Send;
+
+    /// Re-establish the underlying connection after a failed send. Transports without a
+    /// persistent connection leave this unsupported.
+    fn reconnect(&mut self) -> impl std::future::Future<Output = tokio::io::Result<()>> + Send {
+        async { Err(tokio::io::ErrorKind::Unsupported.into()) }
+    }
+
+    /// Total bytes sent after compression, or `None` if the transport doesn't compress
+    fn compressed_bytes(&self) -> Option<u64> {
+        None
+    }
 }
 
 impl fmt::Display for TransportType {
@@ -45,9 +76,13 @@ impl fmt::Display for TransportType {
 pub async fn create_transport(config: &EmitterConfig) -> anyhow::Result<TransportType> {
     match config.protocol {
         Protocol::Tcp | Protocol::Tcps => {
+            if config.compression == Compression::Brotli {
+                // Stream receivers detect compression by magic bytes, and brotli has none
+                anyhow::bail!("Brotli compression is only supported for HTTP emitters");
+            }
             let use_tls = config.tls || matches!(config.protocol, Protocol::Tcps);
             if use_tls {
-                match tcp_tls::TcpTlsTransport::new(config.host.clone(), config.port).await {
+                match tcp_tls::TcpTlsTransport::new(config.host.clone(), config.port, &config.into(), config.compression).await {
                     Ok(transport) => Ok(TransportType::TcpTls(Box::new(transport))),
                     Err(err) => {
                         error!("Failed to create TcpTlsTransport: {}", err);
@@ -55,7 +90,7 @@ pub async fn create_transport(config: &EmitterConfig) -> anyhow::Result<Transpor
                     }
                 }
             } else {
-                match tcp::TcpTransport::new(config.host.clone(), config.port).await {
+                match tcp::TcpTransport::new(config.host.clone(), config.port, config.compression).await {
                     Ok(transport) => Ok(TransportType::Tcp(transport)),
                     Err(err) => {
                         error!("Failed to create TcpTransport: {}", err);
@@ -64,6 +99,9 @@ pub async fn create_transport(config: &EmitterConfig) -> anyhow::Result<Transpor
                 }
             }
         }
+        Protocol::Udp if config.compression != Compression::None => {
+            anyhow::bail!("Compression is not supported for UDP emitters")
+        }
         Protocol::Udp => match udp::UdpTransport::new(config.host.clone(), config.port).await {
             Ok(transport) => Ok(TransportType::Udp(transport)),
             Err(err) => {
@@ -73,11 +111,8 @@ pub async fn create_transport(config: &EmitterConfig) -> anyhow::Result<Transpor
         },
         Protocol::Http | Protocol::Https => {
             let protocol = config.protocol.to_string();
-            let hec_token = match config.message_type {
-                MessageType::SplunkHec => Some(config.hec_token.clone()),
-                _ => None,
-            };
-            http::HttpTransport::new(&protocol, config.host.clone(), config.port, hec_token)
+            http::HttpOptions::try_from(config)
+                .and_then(|opts| http::HttpTransport::new(&protocol, config.host.clone(), config.port, opts, &config.into()))
                 .map(TransportType::Http)
                 .inspect_err(|err| error!("Failed to create HttpTransport: {}", err))
         }
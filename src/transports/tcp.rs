@@ -1,36 +1,56 @@
 use std::fmt;
 
-use anyhow::Context;
 use log::error;
 
-use super::Transport;
+use super::{Transport, compression::StreamEncoder};
+use crate::config::Compression;
 
 pub struct TcpTransport {
     fqdn: String,
     port: u16,
     stream: tokio::net::TcpStream,
+    encoder: StreamEncoder,
 }
 
 impl TcpTransport {
-    pub async fn new(fqdn: String, port: u16) -> anyhow::Result<Self> {
-        let addr = (fqdn.as_str(), port);
-        let ip = tokio::net::lookup_host(addr)
-            .await?
-            .next()
-            .context("Failed to resolve socket address")?;
-        match tokio::net::TcpStream::connect(ip).await {
-            Ok(stream) => Ok(Self { fqdn, port, stream }),
-            Err(e) => {
-                error!("Failed to connect to {}: {}", ip, e);
-                Err(e.into())
-            }
-        }
+    pub async fn new(fqdn: String, port: u16, compression: Compression) -> anyhow::Result<Self> {
+        let stream = connect(&fqdn, port).await?;
+        Ok(Self {
+            fqdn,
+            port,
+            stream,
+            encoder: StreamEncoder::new(compression),
+        })
     }
 }
 
+async fn connect(fqdn: &str, port: u16) -> tokio::io::Result<tokio::net::TcpStream> {
+    let ip = tokio::net::lookup_host((fqdn, port))
+        .await?
+        .next()
+        .ok_or_else(|| tokio::io::Error::other("Failed to resolve socket address"))?;
+    tokio::net::TcpStream::connect(ip)
+        .await
+        .inspect_err(|e| error!("Failed to connect to {}: {}", ip, e))
+}
+
 impl Transport for TcpTransport {
     async fn send(&mut self, data: &[u8]) -> tokio::io::Result<()> {
-        tokio::io::AsyncWriteExt::write_all(&mut self.stream, data).await
+        let wire = self.encoder.encode(data).await?;
+        let len = wire.len();
+        tokio::io::AsyncWriteExt::write_all(&mut self.stream, wire).await?;
+        self.encoder.sent(len);
+        Ok(())
+    }
+
+    async fn reconnect(&mut self) -> tokio::io::Result<()> {
+        self.stream = connect(&self.fqdn, self.port).await?;
+        self.encoder.reset();
+        Ok(())
+    }
+
+    fn compressed_bytes(&self) -> Option<u64> {
+        self.encoder.compressed_bytes()
     }
 }
 
@@ -2,70 +2,100 @@ use std::{fmt, sync::Arc};
 
 use anyhow::Context as _;
 use log::{debug, error};
-use rustls::{ClientConfig, RootCertStore, pki_types::ServerName};
+use rustls::pki_types::ServerName;
 use tokio::{
     net::TcpStream,
     time::{self, Duration},
 };
 use tokio_rustls::TlsConnector;
 
-use super::Transport;
+use super::{Transport, compression::StreamEncoder, tls::TlsOptions};
+use crate::config::Compression;
 
 pub struct TcpTlsTransport {
     fqdn: String,
     port: u16,
+    connector: TlsConnector,
+    domain: ServerName<'static>,
     stream: tokio_rustls::client::TlsStream<tokio::net::TcpStream>,
+    encoder: StreamEncoder,
 }
 
 impl TcpTlsTransport {
-    pub async fn new(fqdn: String, port: u16) -> anyhow::Result<Self> {
-        let addr = (fqdn.as_str(), port);
-        let domain = ServerName::try_from(fqdn.to_string()).expect("Invalid DNS name");
+    pub async fn new(fqdn: String, port: u16, tls: &TlsOptions, compression: Compression) -> anyhow::Result<Self> {
+        let domain = tls.server_name(&fqdn)?;
+        let config = tls.client_config()?;
 
-        let mut root_store = RootCertStore::empty();
-        for cert in rustls_native_certs::load_native_certs().expect("Failed to load native certs") {
-            root_store.add(cert).unwrap();
-        }
+        let connector = TlsConnector::from(Arc::new(config));
+        let stream = connect(&connector, &domain, &fqdn, port).await?;
 
-        let config = ClientConfig::builder().with_root_certificates(root_store).with_no_client_auth();
+        Ok(Self {
+            fqdn,
+            port,
+            connector,
+            domain,
+            stream,
+            encoder: StreamEncoder::new(compression),
+        })
+    }
+}
 
-        let connector = TlsConnector::from(Arc::new(config));
+async fn connect(
+    connector: &TlsConnector,
+    domain: &ServerName<'static>,
+    fqdn: &str,
+    port: u16,
+) -> anyhow::Result<tokio_rustls::client::TlsStream<TcpStream>> {
+    let ip = tokio::net::lookup_host((fqdn, port))
+        .await?
+        .next()
+        .context("Failed to resolve socket address")?;
 
-        let ip = tokio::net::lookup_host(addr)
-            .await?
-            .next()
-            .context("Failed to resolve socket address")?;
-
-        match TcpStream::connect(ip).await {
-            Ok(tcp_stream) => {
-                let handshake_duration = Duration::from_secs(5);
-                let handshake_result = time::timeout(handshake_duration, connector.connect(domain, tcp_stream)).await;
-                match handshake_result {
-                    Ok(Ok(stream)) => {
-                        debug!("TLS handshake succeeded to {ip}");
-                        Ok(Self { fqdn, port, stream })
-                    }
-                    Ok(Err(e)) => {
-                        error!("TLS handshake failed to {ip}: {e}");
-                        Err(e.into())
-                    }
-                    Err(_) => {
-                        error!("TLS handshake timed out to {ip}");
-                        Err(anyhow::anyhow!("TLS handshake timed out"))
-                    }
+    match TcpStream::connect(ip).await {
+        Ok(tcp_stream) => {
+            let handshake_duration = Duration::from_secs(5);
+            let handshake_result = time::timeout(handshake_duration, connector.connect(domain.clone(), tcp_stream)).await;
+            match handshake_result {
+                Ok(Ok(stream)) => {
+                    debug!("TLS handshake succeeded to {ip}");
+                    Ok(stream)
+                }
+                Ok(Err(e)) => {
+                    error!("TLS handshake failed to {ip}: {e}");
+                    Err(e.into())
+                }
+                Err(_) => {
+                    error!("TLS handshake timed out to {ip}");
+                    Err(anyhow::anyhow!("TLS handshake timed out"))
                 }
             }
-            Err(e) => {
-                error!("Failed to connect to {ip}: {e}");
-                Err(e.into())
-            }
+        }
+        Err(e) => {
+            error!("Failed to connect to {ip}: {e}");
+            Err(e.into())
         }
     }
 }
 
 impl Transport for TcpTlsTransport {
     async fn send(&mut self, data: &[u8]) -> tokio::io::Result<()> {
-        tokio::io::AsyncWriteExt::write_all(&mut self.stream, data).await
+        let wire = self.encoder.encode(data).await?;
+        let len = wire.len();
+        tokio::io::AsyncWriteExt::write_all(&mut self.stream, wire).await?;
+        self.encoder.sent(len);
+        Ok(())
+    }
+
+    async fn reconnect(&mut self) -> tokio::io::Result<()> {
+        self.stream = connect(&self.connector, &self.domain, &self.fqdn, self.port)
+            .await
+            .map_err(tokio::io::Error::other)?;
+        self.encoder.reset();
+        Ok(())
+    }
+
+    fn compressed_bytes(&self) -> Option<u64> {
+        self.encoder.compressed_bytes()
     }
 }
 
@@ -74,3 +104,90 @@ impl fmt::Display for TcpTlsTransport {
         write!(f, "tcp_tls/{}:{}", self.fqdn, self.port)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject as _};
+    use tokio::{io::AsyncReadExt as _, net::TcpListener};
+    use tokio_rustls::TlsAcceptor;
+
+    use super::*;
+
+    async fn spawn_tls_server() -> (u16, String, tokio::task::JoinHandle<Vec<u8>>) {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert_pem = cert.cert.pem();
+        let config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![CertificateDer::from_pem_slice(cert_pem.as_bytes()).unwrap()],
+                PrivateKeyDer::from_pem_slice(cert.key_pair.serialize_pem().as_bytes()).unwrap(),
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut stream = acceptor.accept(socket).await.unwrap();
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).await.unwrap();
+            received
+        });
+        (port, cert_pem, server)
+    }
+
+    #[tokio::test]
+    async fn trusts_ca_file_with_server_name_override() {
+        let (port, cert_pem, server) = spawn_tls_server().await;
+        let ca_file = std::env::temp_dir().join(format!("protoglot-test-ca-{port}.pem"));
+        std::fs::write(&ca_file, cert_pem).unwrap();
+
+        let tls = TlsOptions {
+            ca_file: Some(ca_file.clone()),
+            server_name: Some("localhost".to_string()),
+            ..Default::default()
+        };
+        let mut transport = TcpTlsTransport::new("127.0.0.1".to_string(), port, &tls, Compression::None)
+            .await
+            .unwrap();
+        transport.send(b"hello\n").await.unwrap();
+        tokio::io::AsyncWriteExt::shutdown(&mut transport.stream).await.unwrap();
+
+        assert_eq!(server.await.unwrap(), b"hello\n");
+        std::fs::remove_file(ca_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn insecure_skips_verification() {
+        let (port, _, server) = spawn_tls_server().await;
+
+        let tls = TlsOptions {
+            insecure: true,
+            ..Default::default()
+        };
+        let mut transport = TcpTlsTransport::new("127.0.0.1".to_string(), port, &tls, Compression::None)
+            .await
+            .unwrap();
+        transport.send(b"hello\n").await.unwrap();
+        tokio::io::AsyncWriteExt::shutdown(&mut transport.stream).await.unwrap();
+
+        assert_eq!(server.await.unwrap(), b"hello\n");
+    }
+
+    #[tokio::test]
+    async fn rejects_untrusted_server_cert() {
+        let (port, _, _server) = spawn_tls_server().await;
+
+        let tls = TlsOptions {
+            server_name: Some("localhost".to_string()),
+            ..Default::default()
+        };
+        assert!(
+            TcpTlsTransport::new("127.0.0.1".to_string(), port, &tls, Compression::None)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context as _;
+use log::warn;
+use rustls::{
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature},
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime, pem::PemObject as _},
+};
+
+use crate::config::EmitterConfig;
+
+/// Client-side TLS settings shared by the TLS-capable transports
+#[derive(Clone, Debug, Default)]
+pub struct TlsOptions {
+    pub ca_file: Option<PathBuf>,
+    pub insecure: bool,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    pub server_name: Option<String>,
+}
+
+impl From<&EmitterConfig> for TlsOptions {
+    fn from(config: &EmitterConfig) -> Self {
+        Self {
+            ca_file: config.tls_ca_file.clone(),
+            insecure: config.tls_insecure,
+            client_cert: config.tls_client_cert.clone(),
+            client_key: config.tls_client_key.clone(),
+            server_name: config.tls_server_name.clone(),
+        }
+    }
+}
+
+impl TlsOptions {
+    /// Whether anything differs from a default client trusting only the native roots
+    pub fn is_customized(&self) -> bool {
+        self.ca_file.is_some() || self.insecure || self.client_cert.is_some() || self.client_key.is_some() || self.server_name.is_some()
+    }
+
+    /// Name to verify the server cert against (and send as SNI), falling back to the target host
+    pub fn server_name(&self, host: &str) -> anyhow::Result<ServerName<'static>> {
+        let name = self.server_name.as_deref().unwrap_or(host);
+        ServerName::try_from(name.to_string()).with_context(|| format!("Invalid TLS server name '{name}'"))
+    }
+
+    pub fn client_config(&self) -> anyhow::Result<ClientConfig> {
+        let builder = ClientConfig::builder();
+        let builder = if self.insecure {
+            warn!("TLS certificate verification is disabled");
+            let provider = CryptoProvider::get_default().context("No default TLS crypto provider installed")?;
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoVerification(provider.clone())))
+        } else {
+            builder.with_root_certificates(self.root_store()?)
+        };
+
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                let certs = load_certs(cert)?;
+                let key = PrivateKeyDer::from_pem_file(key).with_context(|| format!("Failed to read client key from {}", key.display()))?;
+                Ok(builder.with_client_auth_cert(certs, key)?)
+            }
+            (None, None) => Ok(builder.with_no_client_auth()),
+            _ => anyhow::bail!("A TLS client cert and client key must be provided together"),
+        }
+    }
+
+    fn root_store(&self) -> anyhow::Result<RootCertStore> {
+        let mut root_store = RootCertStore::empty();
+        if let Some(ca_file) = &self.ca_file {
+            for cert in load_certs(ca_file)? {
+                root_store
+                    .add(cert)
+                    .with_context(|| format!("Invalid CA certificate in {}", ca_file.display()))?;
+            }
+        } else {
+            let mut native = rustls_native_certs::load_native_certs();
+            if native.certs.is_empty() && !native.errors.is_empty() {
+                return Err(native.errors.remove(0)).context("Failed to load native root certificates");
+            }
+            for err in native.errors {
+                warn!("Skipping native root certificates: {err}");
+            }
+            for cert in native.certs {
+                root_store.add(cert).context("Invalid native root certificate")?;
+            }
+        }
+        Ok(root_store)
+    }
+}
+
+fn load_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read certificates from {}", path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates found in {}", path.display());
+    }
+    Ok(certs)
+}
+
+/// Accepts any server certificate, while still checking handshake signatures
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}